@@ -7,7 +7,10 @@ use anyhow::Result;
 use clap::Parser;
 use http::Uri;
 use tracing::{debug, info, warn};
-use veto_config::{Config, DEFAULT_CONFIG_PATH, FileConfig, Overrides, load_file, resolve_config};
+use veto_config::{
+    BalanceStrategy, ChainIdSetting, Config, ConfigSource, DEFAULT_CONFIG_PATH, FileConfig,
+    MethodMatcher, Overrides, PolicyMode, default_blocked_methods, load_file, resolve_config,
+};
 
 /// Parse CLI arguments, resolve a [`Config`], and run the proxy.
 pub(crate) async fn run() -> Result<()> {
@@ -15,26 +18,94 @@ pub(crate) async fn run() -> Result<()> {
     debug!(config_path = %cli.config.display(), "parsed CLI arguments");
     let config = cli.resolve_configuration()?;
 
-    log_configuration(&config);
+    log_configuration(&config)?;
 
-    veto_core::run(config).await?;
+    if cli.no_reload {
+        veto_core::run(config).await?;
+    } else {
+        let source = ConfigSource::new(cli.config.clone(), cli.overrides());
+        veto_core::run_with_reload(config, source).await?;
+    }
     Ok(())
 }
 
-fn log_configuration(config: &Config) {
+fn log_configuration(config: &Config) -> Result<()> {
+    let scheme = if config.listener_tls().is_some() {
+        "https"
+    } else {
+        "http"
+    };
     info!(
-        "starting proxy on http://{} forwarding to {}",
+        "starting proxy on {scheme}://{} forwarding to {}",
         config.bind_address(),
         config.upstream_url()
     );
+    if let [_, fallbacks @ ..] = config.upstreams()
+        && !fallbacks.is_empty()
+    {
+        let fallbacks: Vec<_> = fallbacks
+            .iter()
+            .map(|upstream| upstream.url.to_string())
+            .collect();
+        info!(
+            "additional upstreams ({} balancing): {}",
+            config.balance(),
+            fallbacks.join(", ")
+        );
+    }
 
-    if config.blocked_methods().is_empty() {
-        info!("no blocked methods configured");
-    } else {
-        let mut blocked: Vec<_> = config.blocked_methods().iter().cloned().collect();
-        blocked.sort();
-        info!("blocking methods: {}", blocked.join(", "));
+    match config.mode() {
+        PolicyMode::Blocklist => {
+            log_removed_defaults(config)?;
+            if config.blocked_methods().is_empty() {
+                info!("no blocked methods configured");
+            } else {
+                let mut blocked: Vec<_> = config.blocked_methods().iter().cloned().collect();
+                blocked.sort();
+                info!("blocking methods: {}", blocked.join(", "));
+            }
+        }
+        PolicyMode::Allowlist => {
+            if config.allowed_methods().is_empty() {
+                warn!("allowlist mode with no allowed methods; every request will be rejected");
+            } else {
+                let mut allowed: Vec<_> = config.allowed_methods().iter().cloned().collect();
+                allowed.sort();
+                info!("allowlist mode, forwarding only: {}", allowed.join(", "));
+            }
+        }
     }
+
+    log_chain_id(config);
+    Ok(())
+}
+
+/// Log how signed raw transactions are pinned to a chain.
+fn log_chain_id(config: &Config) {
+    match config.chain_id() {
+        Some(ChainIdSetting::Fixed(chain_id)) => {
+            info!("pinning raw transactions to chain id {chain_id}");
+        }
+        Some(ChainIdSetting::Upstream) => {
+            info!("pinning raw transactions to the upstream chain id")
+        }
+        None => debug!("raw transactions are not pinned to a chain id"),
+    }
+}
+
+/// Log the default Anvil/EVM methods that no blocked name or pattern matches any more.
+fn log_removed_defaults(config: &Config) -> Result<()> {
+    let blocked = MethodMatcher::new(config.blocked_methods())?;
+    let removed: Vec<_> = default_blocked_methods()
+        .filter(|method| !blocked.matches(method))
+        .collect();
+
+    if removed.len() == default_blocked_methods().count() {
+        info!("default blocklist disabled");
+    } else if !removed.is_empty() {
+        info!("unblocked default methods: {}", removed.join(", "));
+    }
+    Ok(())
 }
 
 #[derive(Debug, Parser)]
@@ -56,20 +127,87 @@ struct Cli {
     #[arg(long, value_name = "URL")]
     upstream_url: Option<Uri>,
 
-    /// Comma separated JSON-RPC method names to block.
+    /// Comma separated upstream endpoints, tried in order when one fails.
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_name = "URL",
+        conflicts_with = "upstream_url"
+    )]
+    upstreams: Vec<Uri>,
+
+    /// Override how requests are spread across upstreams: `failover` (default), `round_robin`,
+    /// `weighted`, `least_outstanding`, or `ewma_latency`.
+    #[arg(long, value_name = "STRATEGY")]
+    balance: Option<BalanceStrategy>,
+
+    /// Comma separated JSON-RPC method names or glob patterns (e.g. `debug_*`) to block.
     #[arg(long = "blocked-methods", value_delimiter = ',', value_name = "METHOD")]
     blocked_methods: Vec<String>,
+
+    /// Comma separated JSON-RPC method names to remove from the blocklist, including defaults.
+    #[arg(long = "allow-methods", value_delimiter = ',', value_name = "METHOD")]
+    allow_methods: Vec<String>,
+
+    /// Whether to include the default Anvil/EVM blocklist (true or false).
+    #[arg(long, value_name = "BOOL")]
+    use_defaults: Option<bool>,
+
+    /// Override the policy mode: `blocklist` (default) or `allowlist`.
+    #[arg(long, value_name = "MODE")]
+    mode: Option<PolicyMode>,
+
+    /// Comma separated JSON-RPC method names or glob patterns forwarded in allowlist mode.
+    #[arg(long = "allowed-methods", value_delimiter = ',', value_name = "METHOD")]
+    allowed_methods: Vec<String>,
+
+    /// Override the maximum number of entries accepted in a JSON-RPC batch.
+    #[arg(long, value_name = "COUNT")]
+    max_batch_size: Option<usize>,
+
+    /// Override the maximum size of a request body, in bytes.
+    #[arg(long, value_name = "BYTES")]
+    max_request_bytes: Option<usize>,
+
+    /// Override the time allowed for an upstream to answer a call, in milliseconds.
+    #[arg(long, value_name = "MS")]
+    upstream_timeout_ms: Option<u64>,
+
+    /// Override how often idempotent calls are retried after an upstream connection error.
+    #[arg(long, value_name = "COUNT")]
+    upstream_retries: Option<u32>,
+
+    /// Pin raw transactions to a chain id, or `upstream` to learn it from `eth_chainId`.
+    #[arg(long, value_name = "ID")]
+    chain_id: Option<ChainIdSetting>,
+
+    /// Serve Prometheus metrics at `/metrics` on this address (e.g. 127.0.0.1:9100).
+    #[arg(long, value_name = "ADDR")]
+    metrics_address: Option<SocketAddr>,
+
+    /// Serve the admin API (`/rules`, `/config`) on this address (e.g. 127.0.0.1:9300).
+    #[arg(long, value_name = "ADDR")]
+    admin_address: Option<SocketAddr>,
+
+    /// Bearer token required by the admin API.
+    #[arg(
+        long,
+        value_name = "TOKEN",
+        env = "VETO_ADMIN_TOKEN",
+        hide_env_values = true
+    )]
+    admin_token: Option<String>,
+
+    /// Do not reload the method policy when the config file changes or on SIGHUP.
+    #[arg(long)]
+    no_reload: bool,
 }
 
 impl Cli {
     /// Merge `.veto.toml` (if present) with CLI overrides into a [`Config`].
     fn resolve_configuration(&self) -> Result<Config> {
         let file_config = self.load_file_configuration()?;
-        let overrides = Overrides::new(
-            self.bind_address,
-            self.upstream_url.clone(),
-            self.blocked_methods.clone(),
-        );
+        let overrides = self.overrides();
 
         if overrides.is_empty() {
             debug!("no CLI overrides supplied");
@@ -77,7 +215,20 @@ impl Cli {
             debug!(
                 bind_override = ?self.bind_address,
                 upstream_override = ?self.upstream_url,
+                upstreams_override_count = self.upstreams.len(),
+                balance_override = ?self.balance,
                 blocked_override_count = self.blocked_methods.len(),
+                unblocked_override_count = self.allow_methods.len(),
+                use_defaults_override = ?self.use_defaults,
+                max_batch_size_override = ?self.max_batch_size,
+                max_request_bytes_override = ?self.max_request_bytes,
+                upstream_timeout_override = ?self.upstream_timeout_ms,
+                upstream_retries_override = ?self.upstream_retries,
+                mode_override = ?self.mode,
+                allowed_override_count = self.allowed_methods.len(),
+                chain_id_override = ?self.chain_id,
+                metrics_override = ?self.metrics_address,
+                admin_override = ?self.admin_address,
                 "applying CLI overrides"
             );
         }
@@ -86,13 +237,51 @@ impl Cli {
         debug!(
             bind_address = %config.bind_address(),
             upstream_url = %config.upstream_url(),
+            upstreams = config.upstreams().len(),
+            balance = %config.balance(),
+            health_check = config.health_check().is_some(),
             blocked_methods = config.blocked_methods().len(),
+            max_batch_size = config.max_batch_size(),
+            max_request_bytes = config.max_request_bytes(),
+            upstream_timeout = ?config.upstream_timeouts().default_timeout(),
+            upstream_retries = config.upstream_retries().retries(),
+            mode = %config.mode(),
+            allowed_methods = config.allowed_methods().len(),
+            chain_id = ?config.chain_id(),
+            metrics_address = ?config.metrics_address(),
+            admin_address = ?config.admin().map(|admin| admin.address),
+            rate_limits = config.rate_limits().len(),
+            api_keys = config.api_keys().len(),
+            client_access = config.client_access().is_some(),
             "resolved effective configuration"
         );
 
         Ok(config)
     }
 
+    /// Collect the CLI values that override the config file.
+    fn overrides(&self) -> Overrides {
+        Overrides::new(
+            self.bind_address,
+            self.upstream_url.clone(),
+            self.blocked_methods.clone(),
+        )
+        .with_upstreams(self.upstreams.clone())
+        .with_balance(self.balance)
+        .with_unblocked_methods(self.allow_methods.clone())
+        .with_use_defaults(self.use_defaults)
+        .with_max_batch_size(self.max_batch_size)
+        .with_max_request_bytes(self.max_request_bytes)
+        .with_upstream_timeout_ms(self.upstream_timeout_ms)
+        .with_upstream_retries(self.upstream_retries)
+        .with_mode(self.mode)
+        .with_allowed_methods(self.allowed_methods.clone())
+        .with_chain_id(self.chain_id)
+        .with_metrics_address(self.metrics_address)
+        .with_admin_address(self.admin_address)
+        .with_admin_token(self.admin_token.clone())
+    }
+
     /// Attempt to load a [`FileConfig`] from disk.
     fn load_file_configuration(&self) -> Result<Option<FileConfig>> {
         let file_config = load_file(self.config.as_path())?;
@@ -0,0 +1,108 @@
+use crate::ConfigError;
+use ipnet::IpNet;
+use std::net::IpAddr;
+
+/// Client IP filtering applied before a request is read.
+///
+/// A client matching `denied` is refused. When `allowed` is not empty, a client must also
+/// match it. `trusted_proxies` lists the peers whose `X-Forwarded-For` header is believed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientAccess {
+    /// Blocks a client must belong to; every client when empty.
+    pub allowed: Vec<IpNet>,
+    /// Blocks whose clients are refused, even when also allowed.
+    pub denied: Vec<IpNet>,
+    /// Reverse proxies allowed to report the client address in `X-Forwarded-For`.
+    pub trusted_proxies: Vec<IpNet>,
+}
+
+impl ClientAccess {
+    /// Parse the CIDR lists of the config file, where a bare IP address is a single-host block.
+    ///
+    /// Returns `Ok(None)` when every list is unset or empty.
+    pub fn from_file(
+        allowed: Option<Vec<String>>,
+        denied: Option<Vec<String>>,
+        trusted_proxies: Option<Vec<String>>,
+    ) -> Result<Option<Self>, ConfigError> {
+        let access = Self {
+            allowed: parse_blocks(allowed)?,
+            denied: parse_blocks(denied)?,
+            trusted_proxies: parse_blocks(trusted_proxies)?,
+        };
+        Ok((access != Self::default()).then_some(access))
+    }
+
+    /// Whether a client at `ip` may use the proxy.
+    pub fn permits(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        !contains(&self.denied, ip) && (self.allowed.is_empty() || contains(&self.allowed, ip))
+    }
+
+    /// Whether `ip` is a trusted reverse proxy.
+    pub fn trusts(&self, ip: IpAddr) -> bool {
+        contains(&self.trusted_proxies, ip.to_canonical())
+    }
+}
+
+fn contains(blocks: &[IpNet], ip: IpAddr) -> bool {
+    blocks.iter().any(|block| block.contains(&ip))
+}
+
+fn parse_blocks(blocks: Option<Vec<String>>) -> Result<Vec<IpNet>, ConfigError> {
+    blocks
+        .into_iter()
+        .flatten()
+        .map(|value| {
+            let trimmed = value.trim();
+            trimmed
+                .parse::<IpNet>()
+                .or_else(|error| {
+                    trimmed
+                        .parse::<IpAddr>()
+                        .map(IpNet::from)
+                        .map_err(|_| error)
+                })
+                .map_err(|source| ConfigError::Cidr { value, source })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn blocks(values: &[&str]) -> Option<Vec<String>> {
+        Some(values.iter().map(|value| value.to_string()).collect())
+    }
+
+    #[rstest]
+    #[case::allowed("10.1.2.3", true)]
+    #[case::denied_within_allowed("10.0.0.9", false)]
+    #[case::outside_allowed("192.168.1.1", false)]
+    #[case::single_host("2001:db8::1", true)]
+    #[case::mapped_v4("::ffff:10.1.2.3", true)]
+    fn denied_blocks_win(#[case] ip: IpAddr, #[case] permitted: bool) {
+        let access = ClientAccess::from_file(
+            blocks(&["10.0.0.0/8", "2001:db8::1"]),
+            blocks(&["10.0.0.0/24"]),
+            None,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(access.permits(ip), permitted);
+    }
+
+    #[rstest]
+    fn empty_lists_disable_filtering() {
+        assert_eq!(
+            ClientAccess::from_file(None, Some(Vec::new()), None).unwrap(),
+            None
+        );
+        assert!(matches!(
+            ClientAccess::from_file(blocks(&["10.0.0.0/33"]), None, None),
+            Err(ConfigError::Cidr { value, .. }) if value == "10.0.0.0/33"
+        ));
+    }
+}
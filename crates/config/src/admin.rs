@@ -0,0 +1,78 @@
+use crate::ConfigError;
+use std::fmt;
+use std::net::SocketAddr;
+
+/// Admin HTTP listener for inspecting the configuration and editing method rules at runtime.
+#[derive(Clone, PartialEq, Eq)]
+pub struct AdminApi {
+    /// Address the admin listener binds to.
+    pub address: SocketAddr,
+    /// Bearer token required on every admin request, if set.
+    pub token: Option<String>,
+    /// Whether rule changes are written back to the configuration file. Changes that are not
+    /// are lost on the next reload of the file.
+    pub persist: bool,
+}
+
+impl AdminApi {
+    /// Build admin settings from the resolved address and the optional token and persist flag.
+    ///
+    /// Returns `Ok(None)` when no address is set, and an error when the token is empty or the
+    /// other settings are given without an address.
+    pub fn from_parts(
+        address: Option<SocketAddr>,
+        token: Option<String>,
+        persist: Option<bool>,
+    ) -> Result<Option<Self>, ConfigError> {
+        let Some(address) = address else {
+            if token.is_some() || persist.is_some() {
+                return Err(ConfigError::Admin {
+                    message: "`admin_token` and `admin_persist` require `admin_address`".into(),
+                });
+            }
+            return Ok(None);
+        };
+
+        if token.as_deref().is_some_and(str::is_empty) {
+            return Err(ConfigError::Admin {
+                message: "`admin_token` must not be empty".into(),
+            });
+        }
+
+        Ok(Some(Self {
+            address,
+            token,
+            persist: persist.unwrap_or(false),
+        }))
+    }
+}
+
+impl fmt::Debug for AdminApi {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AdminApi")
+            .field("address", &self.address)
+            .field("token", &self.token.as_ref().map(|_| "<redacted>"))
+            .field("persist", &self.persist)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    fn admin_api_is_enabled_by_address() {
+        let address: SocketAddr = "127.0.0.1:9300".parse().unwrap();
+        assert_eq!(AdminApi::from_parts(None, None, None).unwrap(), None);
+        assert!(AdminApi::from_parts(None, Some("secret".into()), None).is_err());
+        assert!(AdminApi::from_parts(Some(address), Some(String::new()), None).is_err());
+
+        let admin = AdminApi::from_parts(Some(address), Some("secret".into()), None)
+            .unwrap()
+            .unwrap();
+        assert!(!admin.persist);
+        assert!(!format!("{admin:?}").contains("secret"));
+    }
+}
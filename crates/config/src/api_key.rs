@@ -0,0 +1,152 @@
+use crate::is_method_pattern;
+use crate::matcher::compile_pattern;
+use crate::{ConfigError, PolicyMode, RateLimit};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// API key clients present to use the proxy, with the policy applied to its calls.
+///
+/// Once any key is configured, requests without a valid key are refused. Settings left unset
+/// fall back to the top-level configuration; `blocked_methods` and `allowed_methods` replace the
+/// top-level lists rather than extending them.
+///
+/// ```toml
+/// [[api_keys]]
+/// name = "ci"
+/// key = "ci-secret"
+/// blocked_methods = []
+///
+/// [[api_keys]]
+/// name = "tooling"
+/// key = "tooling-secret"
+/// rate_limits = [{ requests_per_second = 20 }]
+/// ```
+#[derive(Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiKey {
+    /// Name identifying the key in logs and the audit log.
+    pub name: String,
+    /// Secret token presented by clients.
+    pub key: String,
+    /// Policy mode for calls made with this key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<PolicyMode>,
+    /// Methods blocked for this key, replacing the top-level blocklist.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocked_methods: Option<Vec<String>>,
+    /// Methods forwarded for this key in allowlist mode, replacing the top-level allowlist.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_methods: Option<Vec<String>>,
+    /// Rate limits for this key, replacing the top-level limits.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limits: Option<Vec<RateLimit>>,
+}
+
+impl ApiKey {
+    /// Check that the name and token are set and the method rules and limits are well formed.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |message: &str| ConfigError::ApiKey {
+            key: self.name.clone(),
+            message: message.into(),
+        };
+
+        if self.name.trim().is_empty() {
+            return Err(invalid("`name` must not be empty"));
+        }
+        if self.key.trim().is_empty() {
+            return Err(invalid("`key` must not be empty"));
+        }
+        self.blocked_methods
+            .iter()
+            .chain(&self.allowed_methods)
+            .flatten()
+            .filter(|method| is_method_pattern(method))
+            .try_for_each(|pattern| compile_pattern(&pattern.to_ascii_lowercase()).map(drop))?;
+        self.rate_limits
+            .iter()
+            .flatten()
+            .try_for_each(RateLimit::validate)
+    }
+}
+
+impl fmt::Debug for ApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ApiKey")
+            .field("name", &self.name)
+            .field("key", &"<redacted>")
+            .field("mode", &self.mode)
+            .field("blocked_methods", &self.blocked_methods)
+            .field("allowed_methods", &self.allowed_methods)
+            .field("rate_limits", &self.rate_limits)
+            .finish()
+    }
+}
+
+/// Contents of an API keys file: a list of `[[api_keys]]` tables.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ApiKeysFile {
+    #[serde(default)]
+    api_keys: Vec<ApiKey>,
+}
+
+/// Read the `[[api_keys]]` tables of a keys file.
+pub(crate) fn load_api_keys(path: &Path) -> Result<Vec<ApiKey>, ConfigError> {
+    let contents = fs::read_to_string(path).map_err(|source| ConfigError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let parsed: ApiKeysFile =
+        toml::from_str(&contents).map_err(|source| ConfigError::ApiKeysFile {
+            path: path.to_path_buf(),
+            source,
+        })?;
+    Ok(parsed.api_keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn key(name: &str, key: &str, blocked: &[&str]) -> ApiKey {
+        ApiKey {
+            name: name.into(),
+            key: key.into(),
+            mode: None,
+            blocked_methods: Some(blocked.iter().map(|method| method.to_string()).collect()),
+            allowed_methods: None,
+            rate_limits: None,
+        }
+    }
+
+    #[rstest]
+    #[case(key("ci", "secret", &["anvil_*"]), true)]
+    #[case(key(" ", "secret", &[]), false)]
+    #[case(key("ci", "", &[]), false)]
+    #[case(key("ci", "secret", &["debug_["]), false)]
+    fn validates_keys(#[case] key: ApiKey, #[case] valid: bool) {
+        assert_eq!(key.validate().is_ok(), valid);
+    }
+
+    #[rstest]
+    fn keys_file_is_parsed_and_redacted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys.toml");
+        fs::write(
+            &path,
+            "[[api_keys]]\nname = \"ci\"\nkey = \"ci-secret\"\nblocked_methods = []\n",
+        )
+        .unwrap();
+
+        let keys = load_api_keys(&path).unwrap();
+        assert_eq!(keys, vec![key("ci", "ci-secret", &[])]);
+        assert!(!format!("{keys:?}").contains("ci-secret"));
+        assert!(matches!(
+            load_api_keys(&dir.path().join("missing.toml")),
+            Err(ConfigError::Io { .. })
+        ));
+    }
+}
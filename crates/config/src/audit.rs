@@ -0,0 +1,75 @@
+use crate::{ConfigError, DEFAULT_AUDIT_LOG_MAX_BYTES, DEFAULT_AUDIT_LOG_MAX_FILES};
+use std::path::PathBuf;
+
+/// Audit sink appending one JSON line per handled JSON-RPC call to a rotating file.
+///
+/// Once the file would grow past `max_bytes` it is renamed to `<path>.1` (shifting older files
+/// to `<path>.2` and so on) and a fresh file is started; at most `max_files` rotated files are
+/// kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditLog {
+    /// File the audit lines are appended to.
+    pub path: PathBuf,
+    /// Size at which the file is rotated, in bytes.
+    pub max_bytes: u64,
+    /// Number of rotated files kept next to `path`.
+    pub max_files: usize,
+}
+
+impl AuditLog {
+    /// Build audit log settings from the optional file values.
+    ///
+    /// Returns `Ok(None)` when no path is set, and an error when the limits are zero or given
+    /// without a path.
+    pub fn from_file(
+        path: Option<PathBuf>,
+        max_bytes: Option<u64>,
+        max_files: Option<usize>,
+    ) -> Result<Option<Self>, ConfigError> {
+        let Some(path) = path else {
+            if max_bytes.is_some() || max_files.is_some() {
+                return Err(ConfigError::AuditLog {
+                    message:
+                        "`audit_log_max_bytes` and `audit_log_max_files` require `audit_log_path`"
+                            .into(),
+                });
+            }
+            return Ok(None);
+        };
+
+        let max_bytes = max_bytes.unwrap_or(DEFAULT_AUDIT_LOG_MAX_BYTES);
+        let max_files = max_files.unwrap_or(DEFAULT_AUDIT_LOG_MAX_FILES);
+        if max_bytes == 0 || max_files == 0 {
+            return Err(ConfigError::AuditLog {
+                message:
+                    "`audit_log_max_bytes` and `audit_log_max_files` must be greater than zero"
+                        .into(),
+            });
+        }
+
+        Ok(Some(Self {
+            path,
+            max_bytes,
+            max_files,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    fn audit_log_is_enabled_by_path() {
+        assert_eq!(AuditLog::from_file(None, None, None).unwrap(), None);
+        assert!(AuditLog::from_file(None, Some(1024), None).is_err());
+        assert!(AuditLog::from_file(Some("audit.log".into()), None, Some(0)).is_err());
+
+        let audit = AuditLog::from_file(Some("audit.log".into()), None, None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(audit.max_bytes, DEFAULT_AUDIT_LOG_MAX_BYTES);
+        assert_eq!(audit.max_files, DEFAULT_AUDIT_LOG_MAX_FILES);
+    }
+}
@@ -0,0 +1,85 @@
+use crate::ConfigError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// How requests are spread across the configured upstreams.
+///
+/// Every strategy only picks among upstreams in rotation and falls back to the remaining ones
+/// when the chosen upstream fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BalanceStrategy {
+    /// Always prefer the first healthy upstream in configured order.
+    #[default]
+    Failover,
+    /// Rotate through the healthy upstreams.
+    RoundRobin,
+    /// Rotate in proportion to each upstream's `weight`.
+    Weighted,
+    /// Prefer the upstream with the fewest requests in flight.
+    LeastOutstanding,
+    /// Prefer the upstream with the lowest exponentially weighted moving average latency.
+    EwmaLatency,
+}
+
+impl BalanceStrategy {
+    /// Name of the strategy as used in configuration files.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Failover => "failover",
+            Self::RoundRobin => "round_robin",
+            Self::Weighted => "weighted",
+            Self::LeastOutstanding => "least_outstanding",
+            Self::EwmaLatency => "ewma_latency",
+        }
+    }
+}
+
+impl fmt::Display for BalanceStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for BalanceStrategy {
+    type Err = ConfigError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().replace('-', "_").as_str() {
+            "failover" => Ok(Self::Failover),
+            "round_robin" => Ok(Self::RoundRobin),
+            "weighted" => Ok(Self::Weighted),
+            "least_outstanding" => Ok(Self::LeastOutstanding),
+            "ewma_latency" => Ok(Self::EwmaLatency),
+            _ => Err(ConfigError::BalanceStrategy {
+                value: value.to_string(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("failover", BalanceStrategy::Failover)]
+    #[case("round-robin", BalanceStrategy::RoundRobin)]
+    #[case(" Least_Outstanding ", BalanceStrategy::LeastOutstanding)]
+    #[case("ewma_latency", BalanceStrategy::EwmaLatency)]
+    fn parses_strategies(#[case] value: &str, #[case] expected: BalanceStrategy) {
+        assert_eq!(value.parse::<BalanceStrategy>().unwrap(), expected);
+        assert_eq!(
+            expected.as_str().parse::<BalanceStrategy>().unwrap(),
+            expected
+        );
+    }
+
+    #[rstest]
+    fn rejects_unknown_strategy() {
+        let err = "random".parse::<BalanceStrategy>().unwrap_err();
+        assert!(matches!(err, ConfigError::BalanceStrategy { value } if value == "random"));
+    }
+}
@@ -0,0 +1,120 @@
+use crate::ConfigError;
+use crate::is_method_pattern;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Whether responses to a method may be served from the response cache.
+///
+/// ```toml
+/// [cache_methods]
+/// eth_getBlockByHash = "always"
+/// eth_getBalance = { block_param = 1 }
+/// eth_getLogs = "never"
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheRule {
+    /// The answer never changes, whatever the params, such as a lookup by hash.
+    Always,
+    /// Cacheable when the param at this position names a block by hash or is `finalized`.
+    BlockParam(usize),
+    /// Never cached.
+    Never,
+}
+
+/// In-memory LRU cache of upstream answers to calls about immutable chain data.
+///
+/// Only successful, non-null results of methods with a cacheable [`CacheRule`] are stored.
+/// Entries expire after `ttl`, and the least recently used entry is evicted once the cache
+/// holds `max_entries`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseCache {
+    max_entries: usize,
+    ttl: Duration,
+    methods: BTreeMap<String, CacheRule>,
+}
+
+impl ResponseCache {
+    /// Create cache settings, rejecting a zero size or TTL and method patterns.
+    ///
+    /// `methods` is keyed by lowercase method name.
+    pub fn new(
+        max_entries: usize,
+        ttl: Duration,
+        methods: BTreeMap<String, CacheRule>,
+    ) -> Result<Self, ConfigError> {
+        if max_entries == 0 || ttl.is_zero() {
+            return Err(ConfigError::ResponseCache {
+                message: "`cache_max_entries` and `cache_ttl_ms` must be greater than zero".into(),
+            });
+        }
+        if let Some(pattern) = methods.keys().find(|method| is_method_pattern(method)) {
+            return Err(ConfigError::ResponseCache {
+                message: format!("`cache_methods` takes method names, not patterns: '{pattern}'"),
+            });
+        }
+        Ok(Self {
+            max_entries,
+            ttl,
+            methods,
+        })
+    }
+
+    /// Entries kept before the least recently used one is evicted.
+    pub const fn max_entries(&self) -> usize {
+        self.max_entries
+    }
+
+    /// Time an entry is served before it expires.
+    pub const fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Cacheability rules keyed by lowercase method name.
+    pub const fn methods(&self) -> &BTreeMap<String, CacheRule> {
+        &self.methods
+    }
+
+    /// Rule for `method`, or [`CacheRule::Never`] when it is not listed.
+    pub fn rule(&self, method: &str) -> CacheRule {
+        self.methods
+            .get(&method.to_ascii_lowercase())
+            .copied()
+            .unwrap_or(CacheRule::Never)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::default_cache_rules;
+    use rstest::rstest;
+
+    #[rstest]
+    fn file_rules_accept_names_and_block_params() {
+        let file: crate::FileConfig = toml::from_str(
+            r#"cache_methods = { eth_getBlockByHash = "always", eth_getBalance = { block_param = 1 } }"#,
+        )
+        .unwrap();
+        let methods = file.cache_methods.unwrap();
+        assert_eq!(methods["eth_getBlockByHash"], CacheRule::Always);
+        assert_eq!(methods["eth_getBalance"], CacheRule::BlockParam(1));
+    }
+
+    #[rstest]
+    fn rejects_zero_limits_and_patterns() {
+        let ttl = Duration::from_secs(60);
+        let cache = ResponseCache::new(10, ttl, default_cache_rules()).unwrap();
+        assert_eq!(cache.rule("eth_chainId"), CacheRule::Always);
+        assert_eq!(cache.rule("eth_blockNumber"), CacheRule::Never);
+
+        assert!(ResponseCache::new(0, ttl, BTreeMap::new()).is_err());
+        assert!(ResponseCache::new(10, Duration::ZERO, BTreeMap::new()).is_err());
+        let patterns = BTreeMap::from([("eth_get*".to_string(), CacheRule::Always)]);
+        assert!(matches!(
+            ResponseCache::new(10, ttl, patterns),
+            Err(ConfigError::ResponseCache { .. })
+        ));
+    }
+}
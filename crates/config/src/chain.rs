@@ -0,0 +1,118 @@
+use crate::{ConfigError, Quantity};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// Chain id that signed raw transactions must carry.
+///
+/// Written as an integer, a decimal or `0x` hex string, or `"upstream"` to learn the id from the
+/// upstream `eth_chainId` at start-up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChainIdSetting {
+    /// Pin to the given chain id.
+    Fixed(u64),
+    /// Query the upstream `eth_chainId` once at start-up and pin to the answer.
+    Upstream,
+}
+
+impl ChainIdSetting {
+    /// Keyword selecting [`ChainIdSetting::Upstream`].
+    pub const UPSTREAM: &'static str = "upstream";
+
+    /// The pinned chain id, or `None` while it still has to be learned from the upstream.
+    pub const fn fixed(&self) -> Option<u64> {
+        match self {
+            Self::Fixed(chain_id) => Some(*chain_id),
+            Self::Upstream => None,
+        }
+    }
+}
+
+impl fmt::Display for ChainIdSetting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fixed(chain_id) => write!(f, "{chain_id}"),
+            Self::Upstream => f.write_str(Self::UPSTREAM),
+        }
+    }
+}
+
+impl FromStr for ChainIdSetting {
+    type Err = ConfigError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let trimmed = value.trim();
+        if trimmed.eq_ignore_ascii_case(Self::UPSTREAM) {
+            return Ok(Self::Upstream);
+        }
+
+        Quantity::parse(trimmed)
+            .and_then(|quantity| u64::try_from(quantity.0).ok())
+            .map(Self::Fixed)
+            .ok_or_else(|| ConfigError::ChainId {
+                value: value.to_string(),
+            })
+    }
+}
+
+impl Serialize for ChainIdSetting {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Fixed(chain_id) => serializer.serialize_u64(*chain_id),
+            Self::Upstream => serializer.serialize_str(Self::UPSTREAM),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ChainIdSetting {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Integer(u64),
+            String(String),
+        }
+
+        match Raw::deserialize(deserializer)? {
+            Raw::Integer(chain_id) => Ok(Self::Fixed(chain_id)),
+            Raw::String(value) => value.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("1", ChainIdSetting::Fixed(1))]
+    #[case("0x7a69", ChainIdSetting::Fixed(31337))]
+    #[case(" Upstream ", ChainIdSetting::Upstream)]
+    fn parses_settings(#[case] value: &str, #[case] expected: ChainIdSetting) {
+        assert_eq!(value.parse::<ChainIdSetting>().unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case("mainnet")]
+    #[case("0x10000000000000000")]
+    fn rejects_invalid_settings(#[case] value: &str) {
+        assert!(matches!(
+            value.parse::<ChainIdSetting>(),
+            Err(ConfigError::ChainId { .. })
+        ));
+    }
+
+    #[rstest]
+    fn deserializes_integers_and_keywords() {
+        #[derive(Deserialize)]
+        struct File {
+            chain_id: ChainIdSetting,
+        }
+
+        let file: File = toml::from_str("chain_id = 10").unwrap();
+        assert_eq!(file.chain_id, ChainIdSetting::Fixed(10));
+        let file: File = toml::from_str(r#"chain_id = "upstream""#).unwrap();
+        assert_eq!(file.chain_id, ChainIdSetting::Upstream);
+    }
+}
@@ -1,41 +1,372 @@
+use crate::{
+    AdminApi, ApiKey, AuditLog, Backoff, BalanceStrategy, ChainIdSetting, CircuitBreaker,
+    ClientAccess, DEFAULT_MAX_BATCH_SIZE, DEFAULT_MAX_REQUEST_BYTES, HealthCheck, ListenerTls,
+    ParamRule, PolicyMode, RateLimit, ResponseCache, RetryPolicy, TxRule, UpstreamTarget,
+    UpstreamTimeouts, UpstreamTls, default_idempotent_methods,
+};
 use http::Uri;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::net::SocketAddr;
 
 /// Fully resolved proxy configuration.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Config {
     bind_address: SocketAddr,
-    upstream_url: Uri,
+    upstreams: Vec<UpstreamTarget>,
+    upstream_backoff: Backoff,
+    upstream_timeouts: UpstreamTimeouts,
+    upstream_retries: RetryPolicy,
+    idempotent_methods: BTreeMap<String, bool>,
+    balance: BalanceStrategy,
+    health_check: Option<HealthCheck>,
+    circuit_breaker: Option<CircuitBreaker>,
+    response_cache: Option<ResponseCache>,
     blocked_methods: HashSet<String>,
+    max_batch_size: usize,
+    max_request_bytes: usize,
+    mode: PolicyMode,
+    allowed_methods: HashSet<String>,
+    param_rules: Vec<ParamRule>,
+    tx_rules: Vec<TxRule>,
+    rate_limits: Vec<RateLimit>,
+    api_keys: Vec<ApiKey>,
+    client_access: Option<ClientAccess>,
+    chain_id: Option<ChainIdSetting>,
+    upstream_tls: UpstreamTls,
+    listener_tls: Option<ListenerTls>,
+    metrics_address: Option<SocketAddr>,
+    audit_log: Option<AuditLog>,
+    admin: Option<AdminApi>,
 }
 
 impl Config {
     /// Construct a new [`Config`].
-    pub const fn new(
+    pub fn new(
         bind_address: SocketAddr,
         upstream_url: Uri,
         blocked_methods: HashSet<String>,
     ) -> Self {
         Self {
             bind_address,
-            upstream_url,
+            upstreams: vec![UpstreamTarget::from(upstream_url)],
+            upstream_backoff: Backoff::default(),
+            upstream_timeouts: UpstreamTimeouts::default(),
+            upstream_retries: RetryPolicy::default(),
+            idempotent_methods: default_idempotent_methods(),
+            balance: BalanceStrategy::Failover,
+            health_check: None,
+            circuit_breaker: None,
+            response_cache: None,
             blocked_methods,
+            max_batch_size: DEFAULT_MAX_BATCH_SIZE,
+            max_request_bytes: DEFAULT_MAX_REQUEST_BYTES,
+            mode: PolicyMode::Blocklist,
+            allowed_methods: HashSet::new(),
+            param_rules: Vec::new(),
+            tx_rules: Vec::new(),
+            rate_limits: Vec::new(),
+            api_keys: Vec::new(),
+            client_access: None,
+            chain_id: None,
+            upstream_tls: UpstreamTls::default(),
+            listener_tls: None,
+            metrics_address: None,
+            audit_log: None,
+            admin: None,
         }
     }
 
+    /// Replace the upstream set with `upstreams`, given as [`Uri`]s or [`UpstreamTarget`]s.
+    ///
+    /// An empty list leaves the current upstreams unchanged.
+    pub fn with_upstreams(
+        mut self,
+        upstreams: impl IntoIterator<Item = impl Into<UpstreamTarget>>,
+    ) -> Self {
+        let upstreams: Vec<_> = upstreams.into_iter().map(Into::into).collect();
+        if !upstreams.is_empty() {
+            self.upstreams = upstreams;
+        }
+        self
+    }
+
+    /// Set the [`BalanceStrategy`] used to spread requests across upstreams.
+    pub const fn with_balance(mut self, balance: BalanceStrategy) -> Self {
+        self.balance = balance;
+        self
+    }
+
+    /// Set the [`Backoff`] applied before an unhealthy upstream is retried.
+    pub const fn with_upstream_backoff(mut self, upstream_backoff: Backoff) -> Self {
+        self.upstream_backoff = upstream_backoff;
+        self
+    }
+
+    /// Set the [`UpstreamTimeouts`] applied to forwarded calls.
+    pub fn with_upstream_timeouts(mut self, upstream_timeouts: UpstreamTimeouts) -> Self {
+        self.upstream_timeouts = upstream_timeouts;
+        self
+    }
+
+    /// Set the [`RetryPolicy`] applied to idempotent calls.
+    pub const fn with_upstream_retries(mut self, upstream_retries: RetryPolicy) -> Self {
+        self.upstream_retries = upstream_retries;
+        self
+    }
+
+    /// Replace the idempotency table, keyed by lowercase method name or pattern.
+    pub fn with_idempotent_methods(mut self, idempotent_methods: BTreeMap<String, bool>) -> Self {
+        self.idempotent_methods = idempotent_methods;
+        self
+    }
+
+    /// Enable active upstream [`HealthCheck`]s.
+    pub const fn with_health_check(mut self, health_check: Option<HealthCheck>) -> Self {
+        self.health_check = health_check;
+        self
+    }
+
+    /// Enable a per-upstream [`CircuitBreaker`].
+    pub const fn with_circuit_breaker(mut self, circuit_breaker: Option<CircuitBreaker>) -> Self {
+        self.circuit_breaker = circuit_breaker;
+        self
+    }
+
+    /// Enable the in-memory [`ResponseCache`].
+    pub fn with_response_cache(mut self, response_cache: Option<ResponseCache>) -> Self {
+        self.response_cache = response_cache;
+        self
+    }
+
+    /// Set the [`PolicyMode`] used to filter methods.
+    pub const fn with_mode(mut self, mode: PolicyMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Set the methods forwarded in [`PolicyMode::Allowlist`] mode (lowercase).
+    pub fn with_allowed_methods(mut self, allowed_methods: HashSet<String>) -> Self {
+        self.allowed_methods = allowed_methods;
+        self
+    }
+
+    /// Set the maximum number of entries accepted in a JSON-RPC batch.
+    pub const fn with_max_batch_size(mut self, max_batch_size: usize) -> Self {
+        self.max_batch_size = max_batch_size;
+        self
+    }
+
+    /// Set the maximum size of a request body, in bytes.
+    pub const fn with_max_request_bytes(mut self, max_request_bytes: usize) -> Self {
+        self.max_request_bytes = max_request_bytes;
+        self
+    }
+
+    /// Set the parameter-aware [`ParamRule`]s evaluated before forwarding.
+    pub fn with_param_rules(mut self, param_rules: Vec<ParamRule>) -> Self {
+        self.param_rules = param_rules;
+        self
+    }
+
+    /// Set the [`TxRule`]s applied to decoded `eth_sendRawTransaction` payloads.
+    pub fn with_tx_rules(mut self, tx_rules: Vec<TxRule>) -> Self {
+        self.tx_rules = tx_rules;
+        self
+    }
+
+    /// Pin signed raw transactions to a chain id, see [`ChainIdSetting`].
+    pub const fn with_chain_id(mut self, chain_id: Option<ChainIdSetting>) -> Self {
+        self.chain_id = chain_id;
+        self
+    }
+
+    /// Set the [`UpstreamTls`] settings used for `https://` upstreams.
+    pub fn with_upstream_tls(mut self, upstream_tls: UpstreamTls) -> Self {
+        self.upstream_tls = upstream_tls;
+        self
+    }
+
+    /// Serve HTTPS on the proxy listener with the given [`ListenerTls`] settings.
+    pub fn with_listener_tls(mut self, listener_tls: Option<ListenerTls>) -> Self {
+        self.listener_tls = listener_tls;
+        self
+    }
+
+    /// Serve Prometheus metrics on a separate listener bound to `metrics_address`.
+    pub const fn with_metrics_address(mut self, metrics_address: Option<SocketAddr>) -> Self {
+        self.metrics_address = metrics_address;
+        self
+    }
+
+    /// Append an [`AuditLog`] line for every handled call.
+    pub fn with_audit_log(mut self, audit_log: Option<AuditLog>) -> Self {
+        self.audit_log = audit_log;
+        self
+    }
+
+    /// Limit the calls each client may make with [`RateLimit`]s.
+    pub fn with_rate_limits(mut self, rate_limits: Vec<RateLimit>) -> Self {
+        self.rate_limits = rate_limits;
+        self
+    }
+
+    /// Require one of the given [`ApiKey`]s on every request.
+    pub fn with_api_keys(mut self, api_keys: Vec<ApiKey>) -> Self {
+        self.api_keys = api_keys;
+        self
+    }
+
+    /// Filter clients by IP address with [`ClientAccess`] lists.
+    pub fn with_client_access(mut self, client_access: Option<ClientAccess>) -> Self {
+        self.client_access = client_access;
+        self
+    }
+
+    /// Serve the [`AdminApi`] on a separate listener.
+    pub fn with_admin(mut self, admin: Option<AdminApi>) -> Self {
+        self.admin = admin;
+        self
+    }
+
+    /// Replace the blocked method names and patterns.
+    pub fn with_blocked_methods(mut self, blocked_methods: HashSet<String>) -> Self {
+        self.blocked_methods = blocked_methods;
+        self
+    }
+
+    /// Active upstream health check settings, if enabled.
+    pub const fn health_check(&self) -> Option<HealthCheck> {
+        self.health_check
+    }
+
+    /// Circuit breaker settings applied to every upstream, if enabled.
+    pub const fn circuit_breaker(&self) -> Option<CircuitBreaker> {
+        self.circuit_breaker
+    }
+
+    /// Response cache settings, if caching is enabled.
+    pub const fn response_cache(&self) -> Option<&ResponseCache> {
+        self.response_cache.as_ref()
+    }
+
     /// Address the proxy server will bind to.
     pub const fn bind_address(&self) -> SocketAddr {
         self.bind_address
     }
 
-    /// Upstream JSON-RPC endpoint.
-    pub const fn upstream_url(&self) -> &Uri {
-        &self.upstream_url
+    /// Primary upstream JSON-RPC endpoint (the first of [`Config::upstreams`]).
+    pub fn upstream_url(&self) -> &Uri {
+        &self.upstreams[0].url
+    }
+
+    /// Upstream JSON-RPC endpoints in configured order.
+    pub fn upstreams(&self) -> &[UpstreamTarget] {
+        &self.upstreams
+    }
+
+    /// Strategy used to spread requests across upstreams.
+    pub const fn balance(&self) -> BalanceStrategy {
+        self.balance
+    }
+
+    /// Backoff applied before an unhealthy upstream is retried.
+    pub const fn upstream_backoff(&self) -> Backoff {
+        self.upstream_backoff
+    }
+
+    /// Deadlines for upstream calls, by method.
+    pub const fn upstream_timeouts(&self) -> &UpstreamTimeouts {
+        &self.upstream_timeouts
+    }
+
+    /// Retries of idempotent calls after an upstream connection error.
+    pub const fn upstream_retries(&self) -> RetryPolicy {
+        self.upstream_retries
+    }
+
+    /// Whether calls to each method or pattern may be retried, see
+    /// [`DEFAULT_IDEMPOTENT_METHODS`](crate::DEFAULT_IDEMPOTENT_METHODS).
+    pub const fn idempotent_methods(&self) -> &BTreeMap<String, bool> {
+        &self.idempotent_methods
     }
 
     /// Blocked JSON-RPC method names (lowercase).
     pub const fn blocked_methods(&self) -> &HashSet<String> {
         &self.blocked_methods
     }
+
+    /// Maximum number of entries accepted in a JSON-RPC batch.
+    pub const fn max_batch_size(&self) -> usize {
+        self.max_batch_size
+    }
+
+    /// Maximum size of a request body or WebSocket message, in bytes.
+    pub const fn max_request_bytes(&self) -> usize {
+        self.max_request_bytes
+    }
+
+    /// Policy mode deciding how blocked and allowed methods are applied.
+    pub const fn mode(&self) -> PolicyMode {
+        self.mode
+    }
+
+    /// Allowed JSON-RPC method names (lowercase), enforced in allowlist mode.
+    pub const fn allowed_methods(&self) -> &HashSet<String> {
+        &self.allowed_methods
+    }
+
+    /// Parameter-aware rules evaluated against request `params`.
+    pub fn param_rules(&self) -> &[ParamRule] {
+        &self.param_rules
+    }
+
+    /// Rules applied to decoded raw transactions.
+    pub fn tx_rules(&self) -> &[TxRule] {
+        &self.tx_rules
+    }
+
+    /// Rate limits applied to every client, unless its API key sets its own.
+    pub fn rate_limits(&self) -> &[RateLimit] {
+        &self.rate_limits
+    }
+
+    /// API keys accepted by the proxy; empty when authentication is disabled.
+    pub fn api_keys(&self) -> &[ApiKey] {
+        &self.api_keys
+    }
+
+    /// Client IP filtering, if any list is configured.
+    pub const fn client_access(&self) -> Option<&ClientAccess> {
+        self.client_access.as_ref()
+    }
+
+    /// Chain id that raw transactions must carry, if pinned.
+    pub const fn chain_id(&self) -> Option<ChainIdSetting> {
+        self.chain_id
+    }
+
+    /// TLS settings for connecting to the upstream.
+    pub const fn upstream_tls(&self) -> &UpstreamTls {
+        &self.upstream_tls
+    }
+
+    /// TLS settings for the proxy listener, if it serves HTTPS.
+    pub const fn listener_tls(&self) -> Option<&ListenerTls> {
+        self.listener_tls.as_ref()
+    }
+
+    /// Address of the Prometheus metrics listener, if metrics are enabled.
+    pub const fn metrics_address(&self) -> Option<SocketAddr> {
+        self.metrics_address
+    }
+
+    /// Audit log settings, if auditing is enabled.
+    pub const fn audit_log(&self) -> Option<&AuditLog> {
+        self.audit_log.as_ref()
+    }
+
+    /// Admin API settings, if the admin listener is enabled.
+    pub const fn admin(&self) -> Option<&AdminApi> {
+        self.admin.as_ref()
+    }
 }
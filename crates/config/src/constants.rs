@@ -1,14 +1,62 @@
 //! Constants used by the configuration.
 
+use crate::CacheRule;
+use std::collections::BTreeMap;
+
 /// Default socket address the proxy binds to.
 pub const DEFAULT_BIND_ADDRESS: &str = "0.0.0.0:8546";
 
 /// Default upstream JSON-RPC endpoint (Anvil).
 pub const DEFAULT_UPSTREAM_URL: &str = "http://127.0.0.1:8545";
 
+/// Default delay before an unhealthy upstream is retried, in milliseconds.
+pub const DEFAULT_UPSTREAM_BACKOFF_MS: u64 = 1_000;
+
+/// Default upper bound on the upstream retry delay, in milliseconds.
+pub const DEFAULT_UPSTREAM_MAX_BACKOFF_MS: u64 = 30_000;
+
+/// Default time allowed for an upstream to answer a forwarded call, in milliseconds.
+pub const DEFAULT_UPSTREAM_TIMEOUT_MS: u64 = 30_000;
+
+/// Default number of times an idempotent call is retried after an upstream connection error.
+pub const DEFAULT_UPSTREAM_RETRIES: u32 = 2;
+
+/// Default delay before the first retry of an idempotent call, in milliseconds.
+pub const DEFAULT_UPSTREAM_RETRY_BACKOFF_MS: u64 = 50;
+
+/// Default upper bound on the delay between retries, in milliseconds.
+pub const DEFAULT_UPSTREAM_RETRY_MAX_BACKOFF_MS: u64 = 1_000;
+
+/// Default time an open circuit waits before letting trial calls through, in milliseconds.
+pub const DEFAULT_CIRCUIT_OPEN_MS: u64 = 30_000;
+
+/// Default number of trial calls let through a half-open circuit.
+pub const DEFAULT_CIRCUIT_HALF_OPEN_REQUESTS: u32 = 1;
+
+/// Default time a cached response is served before it expires, in milliseconds.
+pub const DEFAULT_CACHE_TTL_MS: u64 = 300_000;
+
+/// Default time allowed for an upstream to answer a health check, in milliseconds.
+pub const DEFAULT_HEALTH_CHECK_TIMEOUT_MS: u64 = 2_000;
+
+/// Default number of blocks an upstream may trail the best upstream before leaving rotation.
+pub const DEFAULT_HEALTH_CHECK_MAX_BLOCK_LAG: u64 = 5;
+
+/// Default size at which the audit log is rotated, in bytes (100 MiB).
+pub const DEFAULT_AUDIT_LOG_MAX_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Default number of rotated audit log files kept.
+pub const DEFAULT_AUDIT_LOG_MAX_FILES: usize = 5;
+
 /// Default on-disk configuration file path.
 pub const DEFAULT_CONFIG_PATH: &str = ".veto.toml";
 
+/// Default maximum number of entries accepted in a single JSON-RPC batch.
+pub const DEFAULT_MAX_BATCH_SIZE: usize = 100;
+
+/// Default maximum size of a request body, in bytes (5 MiB).
+pub const DEFAULT_MAX_REQUEST_BYTES: usize = 5 * 1024 * 1024;
+
 /// Default Anvil-specific JSON-RPC methods blocked by the proxy.
 pub const ANVIL_BLOCKED_METHODS: &[&str] = &[
     "anvil_autoImpersonateAccount",
@@ -58,6 +106,80 @@ pub const EVM_BLOCKED_METHODS: &[&str] = &[
     "evm_snapshot",
 ];
 
+/// Default idempotency of JSON-RPC methods and patterns.
+///
+/// Calls to methods marked `true` only read state and may be sent again after an upstream
+/// connection error. Methods marked `false`, or missing from the table, are never retried; a
+/// `false` entry wins over a matching `true` pattern, as for `eth_getFilterChanges`, which
+/// consumes the changes it returns.
+pub const DEFAULT_IDEMPOTENT_METHODS: &[(&str, bool)] = &[
+    ("eth_accounts", true),
+    ("eth_blockNumber", true),
+    ("eth_call", true),
+    ("eth_chainId", true),
+    ("eth_estimateGas", true),
+    ("eth_feeHistory", true),
+    ("eth_gasPrice", true),
+    ("eth_get*", true),
+    ("eth_getFilterChanges", false),
+    ("eth_maxPriorityFeePerGas", true),
+    ("eth_protocolVersion", true),
+    ("eth_sendRawTransaction", false),
+    ("eth_sendTransaction", false),
+    ("eth_syncing", true),
+    ("net_listening", true),
+    ("net_peerCount", true),
+    ("net_version", true),
+    ("web3_clientVersion", true),
+    ("web3_sha3", true),
+];
+
+/// Returns the default idempotency table, keyed by lowercase method name or pattern.
+pub fn default_idempotent_methods() -> BTreeMap<String, bool> {
+    DEFAULT_IDEMPOTENT_METHODS
+        .iter()
+        .map(|(method, idempotent)| (method.to_ascii_lowercase(), *idempotent))
+        .collect()
+}
+
+/// Default cacheability rules: lookups by hash are always cacheable, and calls taking a block
+/// parameter are cacheable when it names a block by hash or is `finalized`.
+pub const DEFAULT_CACHE_RULES: &[(&str, CacheRule)] = &[
+    ("eth_call", CacheRule::BlockParam(1)),
+    ("eth_chainId", CacheRule::Always),
+    ("eth_getBalance", CacheRule::BlockParam(1)),
+    ("eth_getBlockByHash", CacheRule::Always),
+    ("eth_getBlockByNumber", CacheRule::BlockParam(0)),
+    ("eth_getBlockReceipts", CacheRule::BlockParam(0)),
+    ("eth_getBlockTransactionCountByHash", CacheRule::Always),
+    (
+        "eth_getBlockTransactionCountByNumber",
+        CacheRule::BlockParam(0),
+    ),
+    ("eth_getCode", CacheRule::BlockParam(1)),
+    ("eth_getProof", CacheRule::BlockParam(2)),
+    ("eth_getStorageAt", CacheRule::BlockParam(2)),
+    ("eth_getTransactionByBlockHashAndIndex", CacheRule::Always),
+    (
+        "eth_getTransactionByBlockNumberAndIndex",
+        CacheRule::BlockParam(0),
+    ),
+    ("eth_getTransactionByHash", CacheRule::Always),
+    ("eth_getTransactionCount", CacheRule::BlockParam(1)),
+    ("eth_getTransactionReceipt", CacheRule::Always),
+    ("eth_getUncleByBlockHashAndIndex", CacheRule::Always),
+    ("eth_getUncleCountByBlockHash", CacheRule::Always),
+    ("net_version", CacheRule::Always),
+];
+
+/// Returns the default cacheability rules, keyed by lowercase method name.
+pub fn default_cache_rules() -> BTreeMap<String, CacheRule> {
+    DEFAULT_CACHE_RULES
+        .iter()
+        .map(|(method, rule)| (method.to_ascii_lowercase(), *rule))
+        .collect()
+}
+
 /// Returns an iterator over the default blocked JSON-RPC method names.
 pub fn default_blocked_methods() -> impl Iterator<Item = &'static str> {
     ANVIL_BLOCKED_METHODS
@@ -34,4 +34,155 @@ pub enum ConfigError {
         /// Error returned while parsing the URI.
         source: http::uri::InvalidUri,
     },
+    /// Inconsistent upstream set, failover, or health check settings.
+    #[error("invalid upstream configuration: {message}")]
+    Upstreams {
+        /// Description of the problem.
+        message: String,
+    },
+    /// Unknown upstream balancing strategy.
+    #[error(
+        "invalid balance strategy '{value}': expected 'failover', 'round_robin', 'weighted', 'least_outstanding', or 'ewma_latency'"
+    )]
+    BalanceStrategy {
+        /// The strategy provided by the user.
+        value: String,
+    },
+    /// Unknown policy mode.
+    #[error("invalid policy mode '{value}': expected 'blocklist' or 'allowlist'")]
+    PolicyMode {
+        /// The mode provided by the user.
+        value: String,
+    },
+    /// Inconsistent TLS settings.
+    #[error("invalid TLS configuration: {message}")]
+    Tls {
+        /// Description of the problem.
+        message: String,
+    },
+    /// Inconsistent audit log settings.
+    #[error("invalid audit log configuration: {message}")]
+    AuditLog {
+        /// Description of the problem.
+        message: String,
+    },
+    /// Inconsistent response cache settings.
+    #[error("invalid response cache configuration: {message}")]
+    ResponseCache {
+        /// Description of the problem.
+        message: String,
+    },
+    /// Invalid upstream timeout.
+    #[error("invalid upstream timeout '{method}': {message}")]
+    UpstreamTimeout {
+        /// Method (or `default`) of the offending timeout.
+        method: String,
+        /// Description of the problem.
+        message: String,
+    },
+    /// Invalid rate limit.
+    #[error("invalid rate limit '{limit}': {message}")]
+    RateLimit {
+        /// Method (or `global`) of the offending limit.
+        limit: String,
+        /// Description of the problem.
+        message: String,
+    },
+    /// Invalid API key entry.
+    #[error("invalid API key '{key}': {message}")]
+    ApiKey {
+        /// Name of the offending key.
+        key: String,
+        /// Description of the problem.
+        message: String,
+    },
+    /// Failure to parse the API keys file.
+    #[error("unable to parse API keys file {path:?}: {source}")]
+    ApiKeysFile {
+        /// Path of the keys file.
+        path: std::path::PathBuf,
+        /// Error returned by the TOML parser.
+        source: toml::de::Error,
+    },
+    /// Invalid CIDR block or IP address in a client access list.
+    #[error("invalid CIDR block '{value}': {source}")]
+    Cidr {
+        /// The offending entry.
+        value: String,
+        /// Error returned while parsing the block.
+        source: ipnet::AddrParseError,
+    },
+    /// Inconsistent admin API settings.
+    #[error("invalid admin API configuration: {message}")]
+    Admin {
+        /// Description of the problem.
+        message: String,
+    },
+    /// Failure to encode the configuration as TOML.
+    #[error("unable to encode config file as TOML: {source}")]
+    TomlSerialize {
+        #[from]
+        /// Error returned by the TOML serializer.
+        source: toml::ser::Error,
+    },
+    /// Failure to write the configuration file.
+    #[error("failed to write config file {path:?}: {source}")]
+    Write {
+        /// Path that failed to be written.
+        path: std::path::PathBuf,
+        /// Underlying IO error.
+        source: std::io::Error,
+    },
+    /// Chain id is neither a number nor `upstream`.
+    #[error("invalid chain id '{value}': expected a number or 'upstream'")]
+    ChainId {
+        /// The chain id provided by the user.
+        value: String,
+    },
+    /// Invalid glob pattern in a method rule.
+    #[error("invalid method pattern '{pattern}': {source}")]
+    MethodPattern {
+        /// The offending pattern.
+        pattern: String,
+        /// Error returned while compiling the glob.
+        source: globset::Error,
+    },
+    /// Glob pattern in `unblocked_methods`, which only removes exact names.
+    #[error("invalid unblocked method '{pattern}': expected an exact method name, not a pattern")]
+    UnblockedPattern {
+        /// The offending pattern.
+        pattern: String,
+    },
+    /// Invalid JSONPath-style parameter selector.
+    #[error("invalid parameter path '{path}': {message}")]
+    ParamPath {
+        /// The offending path.
+        path: String,
+        /// Description of the problem.
+        message: String,
+    },
+    /// Invalid regular expression in a parameter rule.
+    #[error("invalid regex in parameter rule '{rule}': {source}")]
+    ParamRegex {
+        /// Name of the rule holding the regex.
+        rule: String,
+        /// Error returned while compiling the regex.
+        source: regex::Error,
+    },
+    /// Parameter rule failed validation.
+    #[error("invalid parameter rule '{rule}': {message}")]
+    ParamRule {
+        /// Name of the offending rule.
+        rule: String,
+        /// Description of the problem.
+        message: String,
+    },
+    /// Transaction rule failed validation.
+    #[error("invalid transaction rule '{rule}': {message}")]
+    TxRule {
+        /// Name of the offending rule.
+        rule: String,
+        /// Description of the problem.
+        message: String,
+    },
 }
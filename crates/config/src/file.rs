@@ -1,7 +1,11 @@
-use crate::ConfigError;
+use crate::{
+    ApiKey, BalanceStrategy, CacheRule, ChainIdSetting, ConfigError, FileUpstream, MethodTimeout,
+    ParamRule, PolicyMode, RateLimit, TxRule,
+};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// Representation of the on-disk `.veto.toml` configuration.
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, Default)]
@@ -10,8 +14,103 @@ pub struct FileConfig {
     pub bind_address: Option<String>,
     /// Upstream Anvil endpoint.
     pub upstream_url: Option<String>,
+    /// Upstream endpoints in failover order, optionally weighted (replaces `upstream_url`).
+    pub upstreams: Option<Vec<FileUpstream>>,
+    /// How requests are spread across `upstreams` (defaults to `failover`).
+    pub balance: Option<BalanceStrategy>,
+    /// Poll every upstream on this interval, in milliseconds (disabled when unset).
+    pub health_check_interval_ms: Option<u64>,
+    /// Time allowed for an upstream to answer a health check, in milliseconds.
+    pub health_check_timeout_ms: Option<u64>,
+    /// Blocks an upstream may trail the best upstream before leaving rotation.
+    pub health_check_max_block_lag: Option<u64>,
+    /// Delay before an unhealthy upstream is retried, in milliseconds.
+    pub upstream_backoff_ms: Option<u64>,
+    /// Upper bound on the retry delay, which doubles after each failure, in milliseconds.
+    pub upstream_max_backoff_ms: Option<u64>,
+    /// Consecutive upstream failures that open its circuit (circuit breaking disabled when unset).
+    pub circuit_failure_threshold: Option<u32>,
+    /// Time an open circuit fails calls fast before letting trial calls through, in milliseconds.
+    pub circuit_open_ms: Option<u64>,
+    /// Trial calls let through a half-open circuit, all of which must succeed to close it.
+    pub circuit_half_open_requests: Option<u32>,
+    /// Time allowed for an upstream to answer a forwarded call, in milliseconds.
+    pub upstream_timeout_ms: Option<u64>,
+    /// Per-method deadlines overriding `upstream_timeout_ms`, first match wins.
+    pub upstream_timeouts: Option<Vec<MethodTimeout>>,
+    /// Times an idempotent call is retried after an upstream connection error.
+    pub upstream_retries: Option<u32>,
+    /// Delay before the first retry, in milliseconds.
+    pub upstream_retry_backoff_ms: Option<u64>,
+    /// Upper bound on the delay between retries, which doubles each time, in milliseconds.
+    pub upstream_retry_max_backoff_ms: Option<u64>,
+    /// Entries added to or replacing the default idempotency table, by method or pattern.
+    pub idempotent_methods: Option<BTreeMap<String, bool>>,
+    /// Responses kept in the in-memory response cache (caching disabled when unset).
+    pub cache_max_entries: Option<usize>,
+    /// Time a cached response is served before it expires, in milliseconds.
+    pub cache_ttl_ms: Option<u64>,
+    /// Entries added to or replacing the default cacheability rules, by method name.
+    pub cache_methods: Option<BTreeMap<String, CacheRule>>,
     /// Methods to block when encountered in JSON-RPC payloads.
     pub blocked_methods: Option<Vec<String>>,
+    /// Methods removed from the blocklist after defaults and `blocked_methods` are merged.
+    pub unblocked_methods: Option<Vec<String>>,
+    /// Whether the default Anvil/EVM blocklist is included (defaults to `true`).
+    pub use_defaults: Option<bool>,
+    /// Maximum number of entries accepted in a JSON-RPC batch.
+    pub max_batch_size: Option<usize>,
+    /// Maximum size of a request body, in bytes.
+    pub max_request_bytes: Option<usize>,
+    /// Whether methods are filtered by blocklist or allowlist.
+    pub mode: Option<PolicyMode>,
+    /// Methods forwarded when running in allowlist mode.
+    pub allowed_methods: Option<Vec<String>>,
+    /// Rules that block a method based on its `params`.
+    pub param_rules: Option<Vec<ParamRule>>,
+    /// Rules that block decoded `eth_sendRawTransaction` payloads.
+    pub tx_rules: Option<Vec<TxRule>>,
+    /// Token-bucket limits on the calls each client may make.
+    pub rate_limits: Option<Vec<RateLimit>>,
+    /// API keys required to use the proxy, each with its own policy.
+    pub api_keys: Option<Vec<ApiKey>>,
+    /// TOML file holding more `[[api_keys]]` tables.
+    pub api_keys_path: Option<PathBuf>,
+    /// CIDR blocks clients must belong to (every client when unset).
+    pub allowed_cidrs: Option<Vec<String>>,
+    /// CIDR blocks whose clients are refused.
+    pub denied_cidrs: Option<Vec<String>>,
+    /// Reverse proxies whose `X-Forwarded-For` header names the client.
+    pub trusted_proxies: Option<Vec<String>>,
+    /// Chain id raw transactions must carry, or `"upstream"` to learn it at start-up.
+    pub chain_id: Option<ChainIdSetting>,
+    /// PEM bundle of extra certificate authorities trusted for an `https://` upstream.
+    pub upstream_ca_path: Option<PathBuf>,
+    /// PEM client certificate presented to the upstream.
+    pub upstream_client_cert_path: Option<PathBuf>,
+    /// PEM private key for `upstream_client_cert_path`.
+    pub upstream_client_key_path: Option<PathBuf>,
+    /// PEM certificate chain served by the proxy listener.
+    pub tls_cert_path: Option<PathBuf>,
+    /// PEM private key for `tls_cert_path`.
+    pub tls_key_path: Option<PathBuf>,
+    /// PEM bundle of authorities for client certificates (enables mutual TLS).
+    pub tls_client_ca_path: Option<PathBuf>,
+    /// Address serving Prometheus metrics at `/metrics` (disabled when unset).
+    pub metrics_address: Option<String>,
+    /// File receiving one JSON line per handled call (disabled when unset).
+    pub audit_log_path: Option<PathBuf>,
+    /// Size at which the audit log is rotated, in bytes.
+    pub audit_log_max_bytes: Option<u64>,
+    /// Number of rotated audit log files kept.
+    pub audit_log_max_files: Option<usize>,
+    /// Address of the admin API for managing rules at runtime (disabled when unset).
+    pub admin_address: Option<String>,
+    /// Bearer token required by the admin API.
+    pub admin_token: Option<String>,
+    /// Whether admin API rule changes are written back to this file (defaults to `false`).
+    /// Changes kept in memory are discarded by the next reload of the file.
+    pub admin_persist: Option<bool>,
 }
 
 /// Parse and load the configuration file, returning `Ok(None)` when it is missing.
@@ -28,3 +127,41 @@ pub fn load_file(path: &Path) -> Result<Option<FileConfig>, ConfigError> {
         toml::from_str(&contents).map_err(|source| ConfigError::TomlParse { source })?;
     Ok(Some(parsed))
 }
+
+/// Write `config` to `path` as TOML, replacing the file atomically.
+///
+/// Comments and formatting of the previous file are not preserved.
+pub fn save_file(path: &Path, config: &FileConfig) -> Result<(), ConfigError> {
+    let contents = toml::to_string_pretty(config)?;
+    let mut staging = path.as_os_str().to_owned();
+    staging.push(".tmp");
+    let staging = PathBuf::from(staging);
+    let write_error = |source| ConfigError::Write {
+        path: path.to_path_buf(),
+        source,
+    };
+    fs::write(&staging, contents).map_err(write_error)?;
+    fs::rename(&staging, path).map_err(write_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    fn save_file_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".veto.toml");
+        let config = FileConfig {
+            blocked_methods: Some(vec!["eth_sign".into()]),
+            unblocked_methods: Some(vec!["anvil_mine".into()]),
+            mode: Some(PolicyMode::Blocklist),
+            ..FileConfig::default()
+        };
+
+        save_file(&path, &config).unwrap();
+        assert_eq!(load_file(&path).unwrap(), Some(config));
+        assert!(!dir.path().join(".veto.toml.tmp").exists());
+    }
+}
@@ -12,18 +12,78 @@ pub use config::Config;
 
 mod constants;
 pub use constants::{
-    ANVIL_BLOCKED_METHODS, DEFAULT_BIND_ADDRESS, DEFAULT_CONFIG_PATH, DEFAULT_UPSTREAM_URL,
-    EVM_BLOCKED_METHODS, default_blocked_methods,
+    ANVIL_BLOCKED_METHODS, DEFAULT_AUDIT_LOG_MAX_BYTES, DEFAULT_AUDIT_LOG_MAX_FILES,
+    DEFAULT_BIND_ADDRESS, DEFAULT_CACHE_RULES, DEFAULT_CACHE_TTL_MS,
+    DEFAULT_CIRCUIT_HALF_OPEN_REQUESTS, DEFAULT_CIRCUIT_OPEN_MS, DEFAULT_CONFIG_PATH,
+    DEFAULT_HEALTH_CHECK_MAX_BLOCK_LAG, DEFAULT_HEALTH_CHECK_TIMEOUT_MS,
+    DEFAULT_IDEMPOTENT_METHODS, DEFAULT_MAX_BATCH_SIZE, DEFAULT_MAX_REQUEST_BYTES,
+    DEFAULT_UPSTREAM_BACKOFF_MS, DEFAULT_UPSTREAM_MAX_BACKOFF_MS, DEFAULT_UPSTREAM_RETRIES,
+    DEFAULT_UPSTREAM_RETRY_BACKOFF_MS, DEFAULT_UPSTREAM_RETRY_MAX_BACKOFF_MS,
+    DEFAULT_UPSTREAM_TIMEOUT_MS, DEFAULT_UPSTREAM_URL, EVM_BLOCKED_METHODS,
+    default_blocked_methods, default_cache_rules, default_idempotent_methods,
 };
 
 mod errors;
 pub use errors::ConfigError;
 
+mod matcher;
+pub use matcher::{MethodMatcher, is_method_pattern};
+
+mod mode;
+pub use mode::PolicyMode;
+
 mod file;
-pub use file::{FileConfig, load_file};
+pub use file::{FileConfig, load_file, save_file};
+
+mod rules;
+pub use rules::{
+    ParamCondition, ParamPath, ParamPredicate, ParamRule, PathSegment, Quantity, QuantityRange,
+};
+
+mod chain;
+pub use chain::ChainIdSetting;
+
+mod tls;
+pub use tls::{ListenerTls, UpstreamTls};
+
+mod balance;
+pub use balance::BalanceStrategy;
+
+mod upstream;
+pub use upstream::{Backoff, CircuitBreaker, FileUpstream, HealthCheck, UpstreamTarget};
+
+mod timeout;
+pub use timeout::{MethodTimeout, UpstreamTimeouts};
+
+mod retry;
+pub use retry::RetryPolicy;
+
+mod cache;
+pub use cache::{CacheRule, ResponseCache};
+
+mod audit;
+pub use audit::AuditLog;
+
+mod admin;
+pub use admin::AdminApi;
+
+mod rate_limit;
+pub use rate_limit::RateLimit;
+
+mod access;
+pub use access::ClientAccess;
+
+mod api_key;
+pub use api_key::ApiKey;
+
+mod transaction;
+pub use transaction::TxRule;
 
 mod overrides;
 pub use overrides::Overrides;
 
 mod resolver;
 pub use resolver::resolve_config;
+
+mod source;
+pub use source::ConfigSource;
@@ -0,0 +1,121 @@
+use crate::ConfigError;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::collections::HashSet;
+
+/// Characters that turn a method rule into a glob pattern.
+const GLOB_CHARS: &[char] = &['*', '?', '[', '{'];
+
+/// Returns `true` when a method rule contains glob syntax (e.g. `debug_*`).
+pub fn is_method_pattern(rule: &str) -> bool {
+    rule.contains(GLOB_CHARS)
+}
+
+/// Compiled matcher over exact method names and glob patterns such as `debug_*`.
+///
+/// Exact names are checked with a hash lookup first; patterns are compiled once into a
+/// single [`GlobSet`] and only consulted when no exact name matches.
+#[derive(Debug, Clone, Default)]
+pub struct MethodMatcher {
+    exact: HashSet<String>,
+    patterns: Vec<String>,
+    globs: GlobSet,
+}
+
+impl MethodMatcher {
+    /// Compile lowercase method rules into a matcher.
+    pub fn new<I, S>(rules: I) -> Result<Self, ConfigError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut exact = HashSet::new();
+        let mut patterns = Vec::new();
+        let mut builder = GlobSetBuilder::new();
+
+        for rule in rules {
+            let rule = rule.as_ref();
+            if is_method_pattern(rule) {
+                builder.add(compile_pattern(rule)?);
+                patterns.push(rule.to_string());
+            } else {
+                exact.insert(rule.to_string());
+            }
+        }
+
+        let globs = builder
+            .build()
+            .map_err(|source| ConfigError::MethodPattern {
+                pattern: patterns.join(", "),
+                source,
+            })?;
+
+        Ok(Self {
+            exact,
+            patterns,
+            globs,
+        })
+    }
+
+    /// Returns the rule matching the method (case-insensitive), if any.
+    pub fn find(&self, method: &str) -> Option<&str> {
+        let normalized = method.to_ascii_lowercase();
+        if let Some(rule) = self.exact.get(&normalized) {
+            return Some(rule);
+        }
+        if self.patterns.is_empty() {
+            return None;
+        }
+        self.globs
+            .matches(&normalized)
+            .first()
+            .map(|index| self.patterns[*index].as_str())
+    }
+
+    /// Returns `true` when any rule matches the method.
+    pub fn matches(&self, method: &str) -> bool {
+        self.find(method).is_some()
+    }
+
+    /// Number of rules (exact names and patterns) in the matcher.
+    pub fn len(&self) -> usize {
+        self.exact.len() + self.patterns.len()
+    }
+
+    /// Returns `true` if the matcher holds no rules.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Validate a single glob pattern.
+pub(crate) fn compile_pattern(pattern: &str) -> Result<Glob, ConfigError> {
+    Glob::new(pattern).map_err(|source| ConfigError::MethodPattern {
+        pattern: pattern.to_string(),
+        source,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::exact("eth_sendtransaction", Some("eth_sendtransaction"))]
+    #[case::case_insensitive("ETH_SendTransaction", Some("eth_sendtransaction"))]
+    #[case::namespace("debug_traceTransaction", Some("debug_*"))]
+    #[case::prefix("anvil_setBalance", Some("anvil_set*"))]
+    #[case::prefix_miss("anvil_mine", None)]
+    #[case::no_match("eth_call", None)]
+    fn finds_matching_rule(#[case] method: &str, #[case] expected: Option<&str>) {
+        let matcher = MethodMatcher::new(["eth_sendtransaction", "debug_*", "anvil_set*"]).unwrap();
+        assert_eq!(matcher.find(method), expected);
+        assert_eq!(matcher.len(), 3);
+    }
+
+    #[rstest]
+    fn invalid_pattern_is_rejected() {
+        let err = MethodMatcher::new(["debug_[*"]).unwrap_err();
+        assert!(matches!(err, ConfigError::MethodPattern { pattern, .. } if pattern == "debug_[*"));
+    }
+}
@@ -0,0 +1,64 @@
+use crate::ConfigError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// How the proxy decides which JSON-RPC methods are forwarded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyMode {
+    /// Forward every method except the blocked ones.
+    #[default]
+    Blocklist,
+    /// Reject every method that is not explicitly allowed.
+    Allowlist,
+}
+
+impl PolicyMode {
+    /// Lowercase name of the mode as used in configuration files.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Blocklist => "blocklist",
+            Self::Allowlist => "allowlist",
+        }
+    }
+}
+
+impl fmt::Display for PolicyMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PolicyMode {
+    type Err = ConfigError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "blocklist" => Ok(Self::Blocklist),
+            "allowlist" => Ok(Self::Allowlist),
+            _ => Err(ConfigError::PolicyMode {
+                value: value.to_string(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("blocklist", PolicyMode::Blocklist)]
+    #[case(" Allowlist ", PolicyMode::Allowlist)]
+    fn parses_modes(#[case] value: &str, #[case] expected: PolicyMode) {
+        assert_eq!(value.parse::<PolicyMode>().unwrap(), expected);
+    }
+
+    #[rstest]
+    fn rejects_unknown_mode() {
+        let err = "denylist".parse::<PolicyMode>().unwrap_err();
+        assert!(matches!(err, ConfigError::PolicyMode { value } if value == "denylist"));
+    }
+}
@@ -1,3 +1,6 @@
+use crate::BalanceStrategy;
+use crate::ChainIdSetting;
+use crate::PolicyMode;
 use http::Uri;
 use std::net::SocketAddr;
 
@@ -6,7 +9,21 @@ use std::net::SocketAddr;
 pub struct Overrides {
     bind_address: Option<SocketAddr>,
     upstream_url: Option<Uri>,
+    upstreams: Vec<Uri>,
+    balance: Option<BalanceStrategy>,
     blocked_methods: Vec<String>,
+    unblocked_methods: Vec<String>,
+    use_defaults: Option<bool>,
+    max_batch_size: Option<usize>,
+    max_request_bytes: Option<usize>,
+    upstream_timeout_ms: Option<u64>,
+    upstream_retries: Option<u32>,
+    mode: Option<PolicyMode>,
+    allowed_methods: Vec<String>,
+    chain_id: Option<ChainIdSetting>,
+    metrics_address: Option<SocketAddr>,
+    admin_address: Option<SocketAddr>,
+    admin_token: Option<String>,
 }
 
 impl Overrides {
@@ -19,15 +36,127 @@ impl Overrides {
         Self {
             bind_address,
             upstream_url,
+            upstreams: Vec::new(),
+            balance: None,
             blocked_methods,
+            unblocked_methods: Vec::new(),
+            use_defaults: None,
+            max_batch_size: None,
+            max_request_bytes: None,
+            upstream_timeout_ms: None,
+            upstream_retries: None,
+            mode: None,
+            allowed_methods: Vec::new(),
+            chain_id: None,
+            metrics_address: None,
+            admin_address: None,
+            admin_token: None,
         }
     }
 
+    /// Override the upstream set with several endpoints in failover order.
+    pub fn with_upstreams(mut self, upstreams: Vec<Uri>) -> Self {
+        self.upstreams = upstreams;
+        self
+    }
+
+    /// Override the [`BalanceStrategy`].
+    pub const fn with_balance(mut self, balance: Option<BalanceStrategy>) -> Self {
+        self.balance = balance;
+        self
+    }
+
+    /// Remove methods from the blocklist, including default Anvil/EVM entries.
+    pub fn with_unblocked_methods(mut self, unblocked_methods: Vec<String>) -> Self {
+        self.unblocked_methods = unblocked_methods;
+        self
+    }
+
+    /// Override whether the default Anvil/EVM blocklist is included.
+    pub const fn with_use_defaults(mut self, use_defaults: Option<bool>) -> Self {
+        self.use_defaults = use_defaults;
+        self
+    }
+
+    /// Override the maximum number of entries accepted in a JSON-RPC batch.
+    pub const fn with_max_batch_size(mut self, max_batch_size: Option<usize>) -> Self {
+        self.max_batch_size = max_batch_size;
+        self
+    }
+
+    /// Override the maximum size of a request body, in bytes.
+    pub const fn with_max_request_bytes(mut self, max_request_bytes: Option<usize>) -> Self {
+        self.max_request_bytes = max_request_bytes;
+        self
+    }
+
+    /// Override the default upstream timeout, in milliseconds.
+    pub const fn with_upstream_timeout_ms(mut self, upstream_timeout_ms: Option<u64>) -> Self {
+        self.upstream_timeout_ms = upstream_timeout_ms;
+        self
+    }
+
+    /// Override the number of retries of idempotent calls.
+    pub const fn with_upstream_retries(mut self, upstream_retries: Option<u32>) -> Self {
+        self.upstream_retries = upstream_retries;
+        self
+    }
+
+    /// Override the [`PolicyMode`].
+    pub const fn with_mode(mut self, mode: Option<PolicyMode>) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Add methods forwarded in allowlist mode.
+    pub fn with_allowed_methods(mut self, allowed_methods: Vec<String>) -> Self {
+        self.allowed_methods = allowed_methods;
+        self
+    }
+
+    /// Override the chain id raw transactions are pinned to.
+    pub const fn with_chain_id(mut self, chain_id: Option<ChainIdSetting>) -> Self {
+        self.chain_id = chain_id;
+        self
+    }
+
+    /// Override the address serving Prometheus metrics.
+    pub const fn with_metrics_address(mut self, metrics_address: Option<SocketAddr>) -> Self {
+        self.metrics_address = metrics_address;
+        self
+    }
+
+    /// Override the address serving the admin API.
+    pub const fn with_admin_address(mut self, admin_address: Option<SocketAddr>) -> Self {
+        self.admin_address = admin_address;
+        self
+    }
+
+    /// Override the bearer token required by the admin API.
+    pub fn with_admin_token(mut self, admin_token: Option<String>) -> Self {
+        self.admin_token = admin_token;
+        self
+    }
+
     /// Returns `true` if no overriding values were provided.
     pub const fn is_empty(&self) -> bool {
         self.bind_address.is_none()
             && self.upstream_url.is_none()
+            && self.upstreams.is_empty()
+            && self.balance.is_none()
             && self.blocked_methods.is_empty()
+            && self.unblocked_methods.is_empty()
+            && self.use_defaults.is_none()
+            && self.max_batch_size.is_none()
+            && self.max_request_bytes.is_none()
+            && self.upstream_timeout_ms.is_none()
+            && self.upstream_retries.is_none()
+            && self.mode.is_none()
+            && self.allowed_methods.is_empty()
+            && self.chain_id.is_none()
+            && self.metrics_address.is_none()
+            && self.admin_address.is_none()
+            && self.admin_token.is_none()
     }
 
     /// Accessor for the bind address override.
@@ -40,8 +169,78 @@ impl Overrides {
         self.upstream_url.as_ref()
     }
 
+    /// Borrow the upstream set override.
+    pub fn upstreams(&self) -> &[Uri] {
+        &self.upstreams
+    }
+
+    /// Accessor for the balance strategy override.
+    pub const fn balance(&self) -> Option<BalanceStrategy> {
+        self.balance
+    }
+
     /// Borrow the blocked methods override.
     pub fn blocked_methods(&self) -> &[String] {
         &self.blocked_methods
     }
+
+    /// Borrow the unblocked methods override.
+    pub fn unblocked_methods(&self) -> &[String] {
+        &self.unblocked_methods
+    }
+
+    /// Accessor for the default blocklist override.
+    pub const fn use_defaults(&self) -> Option<bool> {
+        self.use_defaults
+    }
+
+    /// Accessor for the maximum batch size override.
+    pub const fn max_batch_size(&self) -> Option<usize> {
+        self.max_batch_size
+    }
+
+    /// Accessor for the maximum request size override.
+    pub const fn max_request_bytes(&self) -> Option<usize> {
+        self.max_request_bytes
+    }
+
+    /// Accessor for the default upstream timeout override.
+    pub const fn upstream_timeout_ms(&self) -> Option<u64> {
+        self.upstream_timeout_ms
+    }
+
+    /// Accessor for the upstream retries override.
+    pub const fn upstream_retries(&self) -> Option<u32> {
+        self.upstream_retries
+    }
+
+    /// Accessor for the policy mode override.
+    pub const fn mode(&self) -> Option<PolicyMode> {
+        self.mode
+    }
+
+    /// Borrow the allowed methods override.
+    pub fn allowed_methods(&self) -> &[String] {
+        &self.allowed_methods
+    }
+
+    /// Accessor for the chain id override.
+    pub const fn chain_id(&self) -> Option<ChainIdSetting> {
+        self.chain_id
+    }
+
+    /// Accessor for the metrics address override.
+    pub const fn metrics_address(&self) -> Option<SocketAddr> {
+        self.metrics_address
+    }
+
+    /// Accessor for the admin address override.
+    pub const fn admin_address(&self) -> Option<SocketAddr> {
+        self.admin_address
+    }
+
+    /// Accessor for the admin token override.
+    pub fn admin_token(&self) -> Option<&str> {
+        self.admin_token.as_deref()
+    }
 }
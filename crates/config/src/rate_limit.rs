@@ -0,0 +1,98 @@
+use crate::ConfigError;
+use crate::is_method_pattern;
+use crate::matcher::compile_pattern;
+use serde::{Deserialize, Serialize};
+
+/// Token-bucket limit on the calls each client may make.
+///
+/// Every client gets its own bucket holding up to `burst` calls, refilled at
+/// `requests_per_second`. A limit without `method` counts every call; with one, only calls to
+/// that method name or glob pattern. A call must fit in every limit that applies to it.
+///
+/// ```toml
+/// [[rate_limits]]
+/// requests_per_second = 50
+/// burst = 100
+///
+/// [[rate_limits]]
+/// method = "eth_call"
+/// requests_per_second = 10
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimit {
+    /// Method name or glob pattern the limit applies to; every call when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    /// Calls a client regains per second.
+    pub requests_per_second: u32,
+    /// Calls a client may make at once; defaults to `requests_per_second`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub burst: Option<u32>,
+}
+
+impl RateLimit {
+    /// Name used to identify the limit in errors and logs: the method, or `global`.
+    pub fn display_name(&self) -> &str {
+        self.method.as_deref().unwrap_or("global")
+    }
+
+    /// Bucket size, falling back to `requests_per_second`.
+    pub fn burst(&self) -> u32 {
+        self.burst.unwrap_or(self.requests_per_second)
+    }
+
+    /// Check that the rates are positive and the method pattern is well formed.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |message: &str| ConfigError::RateLimit {
+            limit: self.display_name().to_string(),
+            message: message.into(),
+        };
+
+        if self.requests_per_second == 0 {
+            return Err(invalid("`requests_per_second` must be greater than zero"));
+        }
+        if self.burst == Some(0) {
+            return Err(invalid("`burst` must be greater than zero"));
+        }
+        match self.method.as_deref() {
+            Some(method) if method.trim().is_empty() => Err(invalid("`method` must not be empty")),
+            Some(method) if is_method_pattern(method) => {
+                compile_pattern(&method.to_ascii_lowercase()).map(drop)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn limit(method: Option<&str>, requests_per_second: u32, burst: Option<u32>) -> RateLimit {
+        RateLimit {
+            method: method.map(Into::into),
+            requests_per_second,
+            burst,
+        }
+    }
+
+    #[rstest]
+    #[case(limit(None, 10, None), true)]
+    #[case(limit(Some("eth_*"), 10, Some(20)), true)]
+    #[case(limit(None, 0, None), false)]
+    #[case(limit(None, 10, Some(0)), false)]
+    #[case(limit(Some(" "), 10, None), false)]
+    #[case(limit(Some("debug_["), 10, None), false)]
+    fn validates_limits(#[case] limit: RateLimit, #[case] valid: bool) {
+        assert_eq!(limit.validate().is_ok(), valid);
+    }
+
+    #[rstest]
+    fn burst_defaults_to_rate() {
+        assert_eq!(limit(None, 10, None).burst(), 10);
+        assert_eq!(limit(Some("eth_call"), 10, Some(3)).burst(), 3);
+        assert_eq!(limit(None, 10, None).display_name(), "global");
+    }
+}
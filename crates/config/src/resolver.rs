@@ -1,59 +1,416 @@
+use crate::AdminApi;
+use crate::ApiKey;
+use crate::AuditLog;
+use crate::Backoff;
+use crate::CircuitBreaker;
+use crate::ClientAccess;
 use crate::Config;
 use crate::ConfigError;
 use crate::FileConfig;
+use crate::HealthCheck;
+use crate::ListenerTls;
 use crate::Overrides;
-use crate::{DEFAULT_BIND_ADDRESS, DEFAULT_UPSTREAM_URL, default_blocked_methods};
+use crate::ParamRule;
+use crate::RateLimit;
+use crate::ResponseCache;
+use crate::RetryPolicy;
+use crate::TxRule;
+use crate::UpstreamTarget;
+use crate::UpstreamTimeouts;
+use crate::UpstreamTls;
+use crate::api_key::load_api_keys;
+use crate::is_method_pattern;
+use crate::matcher::compile_pattern;
+use crate::{
+    DEFAULT_BIND_ADDRESS, DEFAULT_CACHE_TTL_MS, DEFAULT_CIRCUIT_HALF_OPEN_REQUESTS,
+    DEFAULT_CIRCUIT_OPEN_MS, DEFAULT_HEALTH_CHECK_MAX_BLOCK_LAG, DEFAULT_HEALTH_CHECK_TIMEOUT_MS,
+    DEFAULT_MAX_BATCH_SIZE, DEFAULT_MAX_REQUEST_BYTES, DEFAULT_UPSTREAM_BACKOFF_MS,
+    DEFAULT_UPSTREAM_MAX_BACKOFF_MS, DEFAULT_UPSTREAM_RETRIES, DEFAULT_UPSTREAM_RETRY_BACKOFF_MS,
+    DEFAULT_UPSTREAM_RETRY_MAX_BACKOFF_MS, DEFAULT_UPSTREAM_TIMEOUT_MS, DEFAULT_UPSTREAM_URL,
+    default_blocked_methods, default_cache_rules, default_idempotent_methods,
+};
 use http::Uri;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::net::SocketAddr;
+use std::path::Path;
 use std::str::FromStr;
+use std::time::Duration;
 
 /// Resolve the final configuration by merging file values with CLI overrides.
 pub fn resolve_config(
     file: Option<FileConfig>,
     overrides: Overrides,
 ) -> Result<Config, ConfigError> {
-    let (file_bind, file_upstream, file_methods) = match file {
-        Some(cfg) => (
-            cfg.bind_address,
-            cfg.upstream_url,
-            cfg.blocked_methods.unwrap_or_default(),
-        ),
-        None => (None, None, Vec::new()),
-    };
+    let file = file.unwrap_or_default();
 
     let bind_address = if let Some(addr) = overrides.bind_address() {
         addr
-    } else if let Some(value) = file_bind.as_deref() {
+    } else if let Some(value) = file.bind_address.as_deref() {
         parse_socket_addr(value)?
     } else {
         parse_socket_addr(DEFAULT_BIND_ADDRESS)?
     };
 
-    let upstream_url = if let Some(uri) = overrides.upstream_url().cloned() {
-        uri
-    } else if let Some(value) = file_upstream.as_deref() {
-        parse_uri(value)?
-    } else {
-        parse_uri(DEFAULT_UPSTREAM_URL)?
+    let metrics_address = match (overrides.metrics_address(), file.metrics_address.as_deref()) {
+        (Some(addr), _) => Some(addr),
+        (None, Some(value)) => Some(parse_socket_addr(value)?),
+        (None, None) => None,
     };
 
-    let mut blocked_methods: HashSet<String> = default_blocked_methods()
-        .map(|method| method.to_ascii_lowercase())
-        .collect();
-    blocked_methods.extend(
-        file_methods
+    let upstreams = resolve_upstreams(&file, &overrides)?;
+    let upstream_backoff = Backoff::new(
+        Duration::from_millis(
+            file.upstream_backoff_ms
+                .unwrap_or(DEFAULT_UPSTREAM_BACKOFF_MS),
+        ),
+        Duration::from_millis(
+            file.upstream_max_backoff_ms
+                .unwrap_or(DEFAULT_UPSTREAM_MAX_BACKOFF_MS),
+        ),
+    )?;
+    let health_check = resolve_health_check(&file)?;
+    let circuit_breaker = resolve_circuit_breaker(&file)?;
+    let response_cache = resolve_response_cache(&file)?;
+    let upstream_retries = RetryPolicy::new(
+        overrides
+            .upstream_retries()
+            .or(file.upstream_retries)
+            .unwrap_or(DEFAULT_UPSTREAM_RETRIES),
+        Duration::from_millis(
+            file.upstream_retry_backoff_ms
+                .unwrap_or(DEFAULT_UPSTREAM_RETRY_BACKOFF_MS),
+        ),
+        Duration::from_millis(
+            file.upstream_retry_max_backoff_ms
+                .unwrap_or(DEFAULT_UPSTREAM_RETRY_MAX_BACKOFF_MS),
+        ),
+    )?;
+    let idempotent_methods = resolve_idempotent_methods(file.idempotent_methods)?;
+    let upstream_timeouts = UpstreamTimeouts::new(
+        Duration::from_millis(
+            overrides
+                .upstream_timeout_ms()
+                .or(file.upstream_timeout_ms)
+                .unwrap_or(DEFAULT_UPSTREAM_TIMEOUT_MS),
+        ),
+        file.upstream_timeouts
+            .unwrap_or_default()
             .into_iter()
-            .filter_map(|method| normalize_method(&method)),
-    );
-    blocked_methods.extend(
+            .map(|mut timeout| {
+                timeout.method = timeout.method.trim().to_ascii_lowercase();
+                timeout
+            })
+            .collect(),
+    )?;
+
+    let use_defaults = overrides
+        .use_defaults()
+        .or(file.use_defaults)
+        .unwrap_or(true);
+    let mut blocked_methods: HashSet<String> = if use_defaults {
+        default_blocked_methods()
+            .map(|method| method.to_ascii_lowercase())
+            .collect()
+    } else {
+        HashSet::new()
+    };
+    blocked_methods.extend(normalize_methods(
+        file.blocked_methods.iter().flatten(),
+        overrides.blocked_methods(),
+    ));
+    for method in normalize_methods(
+        file.unblocked_methods.iter().flatten(),
+        overrides.unblocked_methods(),
+    ) {
+        if is_method_pattern(&method) {
+            return Err(ConfigError::UnblockedPattern { pattern: method });
+        }
+        blocked_methods.remove(&method);
+    }
+
+    let allowed_methods: HashSet<String> = normalize_methods(
+        file.allowed_methods.iter().flatten(),
+        overrides.allowed_methods(),
+    )
+    .collect();
+
+    let mode = overrides.mode().or(file.mode).unwrap_or_default();
+
+    validate_patterns(&blocked_methods)?;
+    validate_patterns(&allowed_methods)?;
+
+    let param_rules = file.param_rules.unwrap_or_default();
+    param_rules.iter().try_for_each(ParamRule::validate)?;
+
+    let tx_rules = file.tx_rules.unwrap_or_default();
+    tx_rules.iter().try_for_each(TxRule::validate)?;
+
+    let mut rate_limits = file.rate_limits.unwrap_or_default();
+    rate_limits.iter().try_for_each(RateLimit::validate)?;
+    normalize_rate_limits(&mut rate_limits);
+
+    let api_keys = resolve_api_keys(file.api_keys, file.api_keys_path.as_deref())?;
+    let client_access =
+        ClientAccess::from_file(file.allowed_cidrs, file.denied_cidrs, file.trusted_proxies)?;
+
+    let upstream_tls = UpstreamTls {
+        ca_path: file.upstream_ca_path,
+        client_cert_path: file.upstream_client_cert_path,
+        client_key_path: file.upstream_client_key_path,
+    };
+    upstream_tls.validate()?;
+
+    let listener_tls = ListenerTls::from_paths(
+        file.tls_cert_path,
+        file.tls_key_path,
+        file.tls_client_ca_path,
+    )?;
+
+    let audit_log = AuditLog::from_file(
+        file.audit_log_path,
+        file.audit_log_max_bytes,
+        file.audit_log_max_files,
+    )?;
+
+    let admin_address = match (overrides.admin_address(), file.admin_address.as_deref()) {
+        (Some(addr), _) => Some(addr),
+        (None, Some(value)) => Some(parse_socket_addr(value)?),
+        (None, None) => None,
+    };
+    let admin = AdminApi::from_parts(
+        admin_address,
         overrides
-            .blocked_methods()
+            .admin_token()
+            .map(ToOwned::to_owned)
+            .or(file.admin_token),
+        file.admin_persist,
+    )?;
+
+    let max_batch_size = overrides
+        .max_batch_size()
+        .or(file.max_batch_size)
+        .unwrap_or(DEFAULT_MAX_BATCH_SIZE);
+    let max_request_bytes = overrides
+        .max_request_bytes()
+        .or(file.max_request_bytes)
+        .unwrap_or(DEFAULT_MAX_REQUEST_BYTES);
+
+    let balance = overrides.balance().or(file.balance).unwrap_or_default();
+    let primary = upstreams[0].url.clone();
+
+    Ok(Config::new(bind_address, primary, blocked_methods)
+        .with_upstreams(upstreams)
+        .with_upstream_backoff(upstream_backoff)
+        .with_upstream_timeouts(upstream_timeouts)
+        .with_upstream_retries(upstream_retries)
+        .with_idempotent_methods(idempotent_methods)
+        .with_balance(balance)
+        .with_health_check(health_check)
+        .with_circuit_breaker(circuit_breaker)
+        .with_response_cache(response_cache)
+        .with_max_batch_size(max_batch_size)
+        .with_max_request_bytes(max_request_bytes)
+        .with_mode(mode)
+        .with_allowed_methods(allowed_methods)
+        .with_param_rules(param_rules)
+        .with_tx_rules(tx_rules)
+        .with_rate_limits(rate_limits)
+        .with_api_keys(api_keys)
+        .with_client_access(client_access)
+        .with_chain_id(overrides.chain_id().or(file.chain_id))
+        .with_upstream_tls(upstream_tls)
+        .with_listener_tls(listener_tls)
+        .with_metrics_address(metrics_address)
+        .with_audit_log(audit_log)
+        .with_admin(admin))
+}
+
+/// Pick the upstream set: CLI overrides win over the file, and `upstreams` over `upstream_url`.
+fn resolve_upstreams(
+    file: &FileConfig,
+    overrides: &Overrides,
+) -> Result<Vec<UpstreamTarget>, ConfigError> {
+    if !overrides.upstreams().is_empty() {
+        return Ok(overrides
+            .upstreams()
+            .iter()
+            .cloned()
+            .map(Into::into)
+            .collect());
+    }
+    if let Some(uri) = overrides.upstream_url() {
+        return Ok(vec![uri.clone().into()]);
+    }
+
+    match (&file.upstreams, &file.upstream_url) {
+        (Some(_), Some(_)) => Err(ConfigError::Upstreams {
+            message: "set either `upstream_url` or `upstreams`, not both".into(),
+        }),
+        (Some(upstreams), None) if upstreams.is_empty() => Err(ConfigError::Upstreams {
+            message: "`upstreams` must list at least one endpoint".into(),
+        }),
+        (Some(upstreams), None) => upstreams
             .iter()
-            .filter_map(|method| normalize_method(method)),
-    );
+            .map(|upstream| {
+                if upstream.weight() == 0 {
+                    return Err(ConfigError::Upstreams {
+                        message: format!(
+                            "upstream '{}' must have a weight of at least 1",
+                            upstream.url()
+                        ),
+                    });
+                }
+                Ok(UpstreamTarget {
+                    url: parse_uri(upstream.url())?,
+                    weight: upstream.weight(),
+                })
+            })
+            .collect(),
+        (None, Some(value)) => Ok(vec![parse_uri(value)?.into()]),
+        (None, None) => Ok(vec![parse_uri(DEFAULT_UPSTREAM_URL)?.into()]),
+    }
+}
+
+/// Collect the API keys of the config file and the keys file, validated and normalized.
+///
+/// Names and tokens must be unique across both sources.
+fn resolve_api_keys(
+    keys: Option<Vec<ApiKey>>,
+    keys_path: Option<&Path>,
+) -> Result<Vec<ApiKey>, ConfigError> {
+    let mut keys = keys.unwrap_or_default();
+    if let Some(path) = keys_path {
+        keys.extend(load_api_keys(path)?);
+    }
+
+    let mut names = HashSet::new();
+    let mut tokens = HashSet::new();
+    for key in &mut keys {
+        key.validate()?;
+        key.name = key.name.trim().to_owned();
+        let duplicate = |message: &str| ConfigError::ApiKey {
+            key: key.name.clone(),
+            message: message.into(),
+        };
+        if !names.insert(key.name.clone()) {
+            return Err(duplicate("`name` is used by another key"));
+        }
+        if !tokens.insert(key.key.clone()) {
+            return Err(duplicate("`key` is used by another key"));
+        }
+
+        for methods in [&mut key.blocked_methods, &mut key.allowed_methods]
+            .into_iter()
+            .flatten()
+        {
+            *methods = methods
+                .iter()
+                .filter_map(|method| normalize_method(method))
+                .collect();
+        }
+        if let Some(rate_limits) = &mut key.rate_limits {
+            normalize_rate_limits(rate_limits);
+        }
+    }
+    Ok(keys)
+}
+
+/// Merge the file's idempotency entries over the defaults, an entry replacing the default for
+/// the same method or pattern.
+fn resolve_idempotent_methods(
+    entries: Option<BTreeMap<String, bool>>,
+) -> Result<BTreeMap<String, bool>, ConfigError> {
+    let mut methods = default_idempotent_methods();
+    for (method, idempotent) in entries.into_iter().flatten() {
+        if let Some(method) = normalize_method(&method) {
+            methods.insert(method, idempotent);
+        }
+    }
+    methods
+        .keys()
+        .filter(|method| is_method_pattern(method))
+        .try_for_each(|pattern| compile_pattern(pattern).map(drop))?;
+    Ok(methods)
+}
+
+/// Lowercase the method patterns of the limits.
+fn normalize_rate_limits(rate_limits: &mut [RateLimit]) {
+    for limit in rate_limits {
+        limit.method = limit
+            .method
+            .take()
+            .map(|method| method.to_ascii_lowercase());
+    }
+}
+
+/// Build the [`HealthCheck`] settings; checks are enabled by `health_check_interval_ms`.
+fn resolve_health_check(file: &FileConfig) -> Result<Option<HealthCheck>, ConfigError> {
+    let Some(interval) = file.health_check_interval_ms else {
+        if file.health_check_timeout_ms.is_some() || file.health_check_max_block_lag.is_some() {
+            return Err(ConfigError::Upstreams {
+                message: "health check settings require `health_check_interval_ms`".into(),
+            });
+        }
+        return Ok(None);
+    };
+
+    HealthCheck::new(
+        Duration::from_millis(interval),
+        Duration::from_millis(
+            file.health_check_timeout_ms
+                .unwrap_or(DEFAULT_HEALTH_CHECK_TIMEOUT_MS),
+        ),
+        file.health_check_max_block_lag
+            .unwrap_or(DEFAULT_HEALTH_CHECK_MAX_BLOCK_LAG),
+    )
+    .map(Some)
+}
 
-    Ok(Config::new(bind_address, upstream_url, blocked_methods))
+/// Build the [`CircuitBreaker`] settings; breaking is enabled by `circuit_failure_threshold`.
+fn resolve_circuit_breaker(file: &FileConfig) -> Result<Option<CircuitBreaker>, ConfigError> {
+    let Some(failure_threshold) = file.circuit_failure_threshold else {
+        if file.circuit_open_ms.is_some() || file.circuit_half_open_requests.is_some() {
+            return Err(ConfigError::Upstreams {
+                message: "circuit breaker settings require `circuit_failure_threshold`".into(),
+            });
+        }
+        return Ok(None);
+    };
+
+    CircuitBreaker::new(
+        failure_threshold,
+        Duration::from_millis(file.circuit_open_ms.unwrap_or(DEFAULT_CIRCUIT_OPEN_MS)),
+        file.circuit_half_open_requests
+            .unwrap_or(DEFAULT_CIRCUIT_HALF_OPEN_REQUESTS),
+    )
+    .map(Some)
+}
+
+/// Build the [`ResponseCache`] settings; caching is enabled by `cache_max_entries`.
+///
+/// Entries in `cache_methods` add to or replace the default rule for the same method.
+fn resolve_response_cache(file: &FileConfig) -> Result<Option<ResponseCache>, ConfigError> {
+    let Some(max_entries) = file.cache_max_entries else {
+        if file.cache_ttl_ms.is_some() || file.cache_methods.is_some() {
+            return Err(ConfigError::ResponseCache {
+                message: "response cache settings require `cache_max_entries`".into(),
+            });
+        }
+        return Ok(None);
+    };
+
+    let mut methods = default_cache_rules();
+    for (method, rule) in file.cache_methods.iter().flatten() {
+        if let Some(method) = normalize_method(method) {
+            methods.insert(method, *rule);
+        }
+    }
+    ResponseCache::new(
+        max_entries,
+        Duration::from_millis(file.cache_ttl_ms.unwrap_or(DEFAULT_CACHE_TTL_MS)),
+        methods,
+    )
+    .map(Some)
 }
 
 fn parse_socket_addr(value: &str) -> Result<SocketAddr, ConfigError> {
@@ -70,6 +427,23 @@ fn parse_uri(value: &str) -> Result<Uri, ConfigError> {
     })
 }
 
+/// Ensure every glob pattern among the method rules compiles.
+fn validate_patterns(methods: &HashSet<String>) -> Result<(), ConfigError> {
+    methods
+        .iter()
+        .filter(|method| is_method_pattern(method))
+        .try_for_each(|pattern| compile_pattern(pattern).map(drop))
+}
+
+/// Normalize file entries followed by CLI entries, discarding empty names.
+fn normalize_methods<'a>(
+    file: impl Iterator<Item = &'a String> + 'a,
+    overrides: &'a [String],
+) -> impl Iterator<Item = String> + 'a {
+    file.chain(overrides)
+        .filter_map(|method| normalize_method(method))
+}
+
 fn normalize_method(value: &str) -> Option<String> {
     let normalized = value.trim().to_ascii_lowercase();
     if normalized.is_empty() {
@@ -82,6 +456,11 @@ fn normalize_method(value: &str) -> Option<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::BalanceStrategy;
+    use crate::CacheRule;
+    use crate::ChainIdSetting;
+    use crate::FileUpstream;
+    use crate::PolicyMode;
     use rstest::rstest;
 
     #[rstest]
@@ -99,6 +478,10 @@ mod tests {
             .map(|method| method.to_ascii_lowercase())
             .collect();
         assert_eq!(config.blocked_methods(), &expected);
+        assert_eq!(config.max_batch_size(), DEFAULT_MAX_BATCH_SIZE);
+        assert_eq!(config.max_request_bytes(), DEFAULT_MAX_REQUEST_BYTES);
+        assert_eq!(config.mode(), PolicyMode::Blocklist);
+        assert!(config.allowed_methods().is_empty());
     }
 
     #[rstest]
@@ -107,6 +490,8 @@ mod tests {
             bind_address: Some("127.0.0.1:9000".to_string()),
             upstream_url: Some("http://127.0.0.1:9001".to_string()),
             blocked_methods: Some(vec!["eth_sendTransaction".into(), " personal_sign ".into()]),
+            max_batch_size: Some(25),
+            ..Default::default()
         };
         let config = resolve_config(Some(file), Overrides::default()).expect("config resolves");
         assert_eq!(
@@ -120,6 +505,7 @@ mod tests {
         let methods = config.blocked_methods();
         assert!(methods.contains("eth_sendtransaction"));
         assert!(methods.contains("personal_sign"));
+        assert_eq!(config.max_batch_size(), 25);
     }
 
     #[rstest]
@@ -128,13 +514,20 @@ mod tests {
             bind_address: Some("127.0.0.1:9000".to_string()),
             upstream_url: Some("http://127.0.0.1:9001".to_string()),
             blocked_methods: Some(vec!["eth_sendtransaction".into()]),
+            max_batch_size: Some(25),
+            max_request_bytes: Some(1_024),
+            chain_id: Some(ChainIdSetting::Upstream),
+            ..Default::default()
         };
 
         let overrides = Overrides::new(
             Some("127.0.0.1:9100".parse().unwrap()),
             Some("http://127.0.0.1:9101".parse().unwrap()),
             vec!["eth_getBalance".into()],
-        );
+        )
+        .with_max_batch_size(Some(5))
+        .with_max_request_bytes(Some(2_048))
+        .with_chain_id(Some(ChainIdSetting::Fixed(10)));
 
         let config = resolve_config(Some(file), overrides).expect("config resolves");
 
@@ -148,6 +541,521 @@ mod tests {
         );
         assert!(config.blocked_methods().contains("eth_sendtransaction"));
         assert!(config.blocked_methods().contains("eth_getbalance"));
+        assert_eq!(config.max_batch_size(), 5);
+        assert_eq!(config.max_request_bytes(), 2_048);
+        assert_eq!(config.chain_id(), Some(ChainIdSetting::Fixed(10)));
+    }
+
+    #[rstest]
+    fn allowlist_mode_merges_file_and_cli_methods() {
+        let file = FileConfig {
+            mode: Some(PolicyMode::Allowlist),
+            allowed_methods: Some(vec!["eth_chainId".into(), " ".into()]),
+            ..Default::default()
+        };
+        let overrides = Overrides::default().with_allowed_methods(vec!["ETH_CALL".into()]);
+
+        let config = resolve_config(Some(file), overrides).expect("config resolves");
+        assert_eq!(config.mode(), PolicyMode::Allowlist);
+        assert_eq!(
+            config.allowed_methods(),
+            &HashSet::from(["eth_chainid".to_string(), "eth_call".to_string()])
+        );
+
+        let overrides = Overrides::default().with_mode(Some(PolicyMode::Blocklist));
+        let file = FileConfig {
+            mode: Some(PolicyMode::Allowlist),
+            ..Default::default()
+        };
+        let config = resolve_config(Some(file), overrides).expect("config resolves");
+        assert_eq!(config.mode(), PolicyMode::Blocklist);
+    }
+
+    #[rstest]
+    fn method_patterns_are_validated() {
+        let file = FileConfig {
+            blocked_methods: Some(vec!["Debug_*".into(), "anvil_set*".into()]),
+            ..Default::default()
+        };
+        let config = resolve_config(Some(file), Overrides::default()).expect("config resolves");
+        assert!(config.blocked_methods().contains("debug_*"));
+        assert!(config.blocked_methods().contains("anvil_set*"));
+
+        let overrides = Overrides::default().with_allowed_methods(vec!["eth_{get".into()]);
+        let err = resolve_config(None, overrides).unwrap_err();
+        assert!(matches!(err, ConfigError::MethodPattern { pattern, .. } if pattern == "eth_{get"));
+    }
+
+    #[rstest]
+    fn unblocked_methods_are_removed_after_defaults() {
+        let file = FileConfig {
+            blocked_methods: Some(vec!["eth_sendTransaction".into()]),
+            unblocked_methods: Some(vec!["anvil_mine".into()]),
+            ..Default::default()
+        };
+        let overrides = Overrides::default()
+            .with_unblocked_methods(vec!["EVM_SNAPSHOT".into(), "eth_sendtransaction".into()]);
+
+        let config = resolve_config(Some(file), overrides).expect("config resolves");
+        let methods = config.blocked_methods();
+        assert!(!methods.contains("anvil_mine"));
+        assert!(!methods.contains("evm_snapshot"));
+        assert!(!methods.contains("eth_sendtransaction"));
+        assert!(methods.contains("anvil_setbalance"));
+        assert_eq!(methods.len(), default_blocked_methods().count() - 2);
+    }
+
+    #[rstest]
+    #[case::file(Some(vec!["anvil_*".into()]), vec![])]
+    #[case::cli(None, vec!["evm_{snapshot,revert}".into()])]
+    fn unblocked_patterns_are_rejected(
+        #[case] unblocked: Option<Vec<String>>,
+        #[case] allow_methods: Vec<String>,
+    ) {
+        let file = FileConfig {
+            unblocked_methods: unblocked,
+            ..Default::default()
+        };
+        let overrides = Overrides::default().with_unblocked_methods(allow_methods);
+        let err = resolve_config(Some(file), overrides).unwrap_err();
+        assert!(matches!(err, ConfigError::UnblockedPattern { .. }));
+    }
+
+    #[rstest]
+    fn defaults_can_be_disabled() {
+        let file = FileConfig {
+            blocked_methods: Some(vec!["eth_sendTransaction".into()]),
+            use_defaults: Some(false),
+            ..Default::default()
+        };
+        let config = resolve_config(Some(file.clone()), Overrides::default()).unwrap();
+        assert_eq!(
+            config.blocked_methods(),
+            &HashSet::from(["eth_sendtransaction".to_string()])
+        );
+
+        let overrides = Overrides::default().with_use_defaults(Some(true));
+        let config = resolve_config(Some(file), overrides).unwrap();
+        assert!(config.blocked_methods().contains("anvil_mine"));
+    }
+
+    #[rstest]
+    fn param_rules_are_loaded_and_validated() {
+        let file: FileConfig = toml::from_str(
+            r#"
+            [[param_rules]]
+            method = "anvil_reset"
+            when = [{ path = "$[0].forking.jsonRpcUrl", exists = true }]
+            "#,
+        )
+        .unwrap();
+        let config = resolve_config(Some(file), Overrides::default()).expect("config resolves");
+        assert_eq!(config.param_rules().len(), 1);
+        assert_eq!(config.param_rules()[0].display_name(), "anvil_reset");
+
+        let file: FileConfig = toml::from_str(
+            r#"
+            [[param_rules]]
+            method = "eth_call"
+            when = [{ path = "$[0].data", regex = "[" }]
+            "#,
+        )
+        .unwrap();
+        let err = resolve_config(Some(file), Overrides::default()).unwrap_err();
+        assert!(matches!(err, ConfigError::ParamRegex { .. }));
+    }
+
+    #[rstest]
+    fn rate_limits_are_loaded_and_validated() {
+        let file: FileConfig = toml::from_str(
+            r#"
+            [[rate_limits]]
+            requests_per_second = 50
+
+            [[rate_limits]]
+            method = "ETH_call"
+            requests_per_second = 10
+            burst = 20
+            "#,
+        )
+        .unwrap();
+        let config = resolve_config(Some(file), Overrides::default()).expect("config resolves");
+        assert_eq!(config.rate_limits().len(), 2);
+        assert_eq!(config.rate_limits()[0].burst(), 50);
+        assert_eq!(config.rate_limits()[1].method.as_deref(), Some("eth_call"));
+
+        let file: FileConfig = toml::from_str(
+            r#"
+            [[rate_limits]]
+            method = "eth_call"
+            requests_per_second = 0
+            "#,
+        )
+        .unwrap();
+        let err = resolve_config(Some(file), Overrides::default()).unwrap_err();
+        assert!(matches!(err, ConfigError::RateLimit { .. }));
+    }
+
+    #[rstest]
+    fn upstream_timeouts_are_loaded_and_overridden() {
+        let file: FileConfig = toml::from_str(
+            r#"
+            upstream_timeout_ms = 5000
+
+            [[upstream_timeouts]]
+            method = "DEBUG_trace*"
+            timeout_ms = 120000
+            "#,
+        )
+        .unwrap();
+        let config =
+            resolve_config(Some(file.clone()), Overrides::default()).expect("config resolves");
+        let timeouts = config.upstream_timeouts();
+        assert_eq!(timeouts.default_timeout(), Duration::from_secs(5));
+        assert_eq!(timeouts.methods()[0].method, "debug_trace*");
+        assert_eq!(timeouts.methods()[0].timeout(), Duration::from_secs(120));
+
+        let overrides = Overrides::default().with_upstream_timeout_ms(Some(1_000));
+        let config = resolve_config(Some(file), overrides).expect("config resolves");
+        assert_eq!(
+            config.upstream_timeouts().default_timeout(),
+            Duration::from_secs(1)
+        );
+
+        let overrides = Overrides::default().with_upstream_timeout_ms(Some(0));
+        let err = resolve_config(None, overrides).unwrap_err();
+        assert!(matches!(err, ConfigError::UpstreamTimeout { .. }));
+    }
+
+    #[rstest]
+    fn idempotency_table_extends_the_defaults() {
+        let file: FileConfig = toml::from_str(
+            r#"
+            upstream_retries = 4
+            upstream_retry_backoff_ms = 10
+
+            [idempotent_methods]
+            "eth_call" = false
+            "Trace_*" = true
+            "#,
+        )
+        .unwrap();
+        let config = resolve_config(Some(file), Overrides::default()).expect("config resolves");
+        assert_eq!(config.upstream_retries().retries(), 4);
+        assert_eq!(
+            config.upstream_retries().backoff(),
+            Duration::from_millis(10)
+        );
+        let methods = config.idempotent_methods();
+        assert_eq!(methods.get("eth_call"), Some(&false));
+        assert_eq!(methods.get("trace_*"), Some(&true));
+        assert_eq!(methods.get("eth_get*"), Some(&true));
+        assert_eq!(methods.get("eth_sendrawtransaction"), Some(&false));
+
+        let overrides = Overrides::default().with_upstream_retries(Some(0));
+        let config = resolve_config(None, overrides).expect("config resolves");
+        assert_eq!(config.upstream_retries().retries(), 0);
+
+        let file: FileConfig = toml::from_str("idempotent_methods = { \"eth_[\" = true }").unwrap();
+        assert!(resolve_config(Some(file), Overrides::default()).is_err());
+    }
+
+    #[rstest]
+    fn api_keys_merge_the_keys_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let keys_path = dir.path().join("keys.toml");
+        std::fs::write(
+            &keys_path,
+            r#"
+            [[api_keys]]
+            name = "tooling"
+            key = "tooling-secret"
+            "#,
+        )
+        .unwrap();
+        let file = |second_key: &str| -> FileConfig {
+            toml::from_str(&format!(
+                r#"
+                api_keys_path = {keys_path:?}
+
+                [[api_keys]]
+                name = "ci"
+                key = "{second_key}"
+                blocked_methods = ["Anvil_Mine", " "]
+                rate_limits = [{{ method = "ETH_*", requests_per_second = 5 }}]
+                "#
+            ))
+            .unwrap()
+        };
+
+        let config = resolve_config(Some(file("ci-secret")), Overrides::default()).unwrap();
+        let names: Vec<_> = config
+            .api_keys()
+            .iter()
+            .map(|key| key.name.as_str())
+            .collect();
+        assert_eq!(names, ["ci", "tooling"]);
+        let ci = &config.api_keys()[0];
+        assert_eq!(ci.blocked_methods, Some(vec!["anvil_mine".to_string()]));
+        assert_eq!(
+            ci.rate_limits.as_ref().unwrap()[0].method.as_deref(),
+            Some("eth_*")
+        );
+
+        let err = resolve_config(Some(file("tooling-secret")), Overrides::default()).unwrap_err();
+        assert!(matches!(err, ConfigError::ApiKey { key, .. } if key == "tooling"));
+    }
+
+    #[rstest]
+    fn client_access_lists_are_parsed() {
+        let file = FileConfig {
+            denied_cidrs: Some(vec!["203.0.113.0/24".into()]),
+            trusted_proxies: Some(vec![" 10.0.0.1 ".into()]),
+            ..Default::default()
+        };
+        let config = resolve_config(Some(file), Overrides::default()).expect("config resolves");
+        let access = config.client_access().unwrap();
+        assert!(!access.permits("203.0.113.7".parse().unwrap()));
+        assert!(access.permits("198.51.100.7".parse().unwrap()));
+        assert!(access.trusts("10.0.0.1".parse().unwrap()));
+
+        let config = resolve_config(None, Overrides::default()).expect("config resolves");
+        assert_eq!(config.client_access(), None);
+    }
+
+    #[rstest]
+    fn upstream_tls_paths_are_resolved() {
+        let file = FileConfig {
+            upstream_url: Some("https://node.example".into()),
+            upstream_ca_path: Some("certs/ca.pem".into()),
+            ..Default::default()
+        };
+        let config = resolve_config(Some(file), Overrides::default()).expect("config resolves");
+        assert_eq!(
+            config.upstream_tls().ca_path.as_deref(),
+            Some(std::path::Path::new("certs/ca.pem"))
+        );
+
+        let file = FileConfig {
+            upstream_client_key_path: Some("client.key".into()),
+            ..Default::default()
+        };
+        let err = resolve_config(Some(file), Overrides::default()).unwrap_err();
+        assert!(matches!(err, ConfigError::Tls { .. }));
+    }
+
+    #[rstest]
+    fn upstream_set_and_backoff_are_resolved() {
+        let file = FileConfig {
+            upstreams: Some(vec![
+                "http://127.0.0.1:8545".into(),
+                FileUpstream::Weighted {
+                    url: "http://127.0.0.1:8546".into(),
+                    weight: 3,
+                },
+            ]),
+            balance: Some(BalanceStrategy::Weighted),
+            upstream_backoff_ms: Some(250),
+            upstream_max_backoff_ms: Some(4_000),
+            ..Default::default()
+        };
+        let config = resolve_config(Some(file.clone()), Overrides::default()).unwrap();
+        assert_eq!(config.upstreams().len(), 2);
+        assert_eq!(config.upstream_url().port_u16(), Some(8545));
+        assert_eq!(config.upstreams()[1].weight, 3);
+        assert_eq!(config.balance(), BalanceStrategy::Weighted);
+        assert_eq!(
+            config.upstream_backoff().initial(),
+            Duration::from_millis(250)
+        );
+        assert_eq!(config.upstream_backoff().max(), Duration::from_secs(4));
+
+        let overrides = Overrides::new(
+            None,
+            Some("http://10.0.0.1:8545".parse().unwrap()),
+            Vec::new(),
+        );
+        let config = resolve_config(Some(file), overrides).unwrap();
+        assert_eq!(
+            config.upstreams(),
+            [UpstreamTarget::from(
+                "http://10.0.0.1:8545".parse::<Uri>().unwrap()
+            )]
+        );
+    }
+
+    #[rstest]
+    #[case(Some(vec![]), None)]
+    #[case(Some(vec![FileUpstream::Weighted { url: "http://127.0.0.1:8545".into(), weight: 0 }]), None)]
+    #[case(Some(vec!["http://127.0.0.1:8545".into()]), Some("http://127.0.0.1:8546".into()))]
+    fn conflicting_or_empty_upstreams_are_rejected(
+        #[case] upstreams: Option<Vec<FileUpstream>>,
+        #[case] upstream_url: Option<String>,
+    ) {
+        let file = FileConfig {
+            upstreams,
+            upstream_url,
+            ..Default::default()
+        };
+        let err = resolve_config(Some(file), Overrides::default()).unwrap_err();
+        assert!(matches!(err, ConfigError::Upstreams { .. }));
+    }
+
+    #[rstest]
+    fn health_checks_are_enabled_by_interval() {
+        let config = resolve_config(None, Overrides::default()).unwrap();
+        assert_eq!(config.health_check(), None);
+
+        let file = FileConfig {
+            health_check_interval_ms: Some(5_000),
+            ..Default::default()
+        };
+        let check = resolve_config(Some(file), Overrides::default())
+            .unwrap()
+            .health_check()
+            .expect("health checks enabled");
+        assert_eq!(check.interval(), Duration::from_secs(5));
+        assert_eq!(
+            check.timeout(),
+            Duration::from_millis(DEFAULT_HEALTH_CHECK_TIMEOUT_MS)
+        );
+        assert_eq!(check.max_block_lag(), DEFAULT_HEALTH_CHECK_MAX_BLOCK_LAG);
+
+        let file = FileConfig {
+            health_check_max_block_lag: Some(10),
+            ..Default::default()
+        };
+        let err = resolve_config(Some(file), Overrides::default()).unwrap_err();
+        assert!(matches!(err, ConfigError::Upstreams { .. }));
+    }
+
+    #[rstest]
+    fn circuit_breaker_is_enabled_by_failure_threshold() {
+        let config = resolve_config(None, Overrides::default()).unwrap();
+        assert_eq!(config.circuit_breaker(), None);
+
+        let file = FileConfig {
+            circuit_failure_threshold: Some(3),
+            ..Default::default()
+        };
+        let breaker = resolve_config(Some(file), Overrides::default())
+            .unwrap()
+            .circuit_breaker()
+            .expect("circuit breaker enabled");
+        assert_eq!(breaker.failure_threshold(), 3);
+        assert_eq!(
+            breaker.open_duration(),
+            Duration::from_millis(DEFAULT_CIRCUIT_OPEN_MS)
+        );
+        assert_eq!(
+            breaker.half_open_requests(),
+            DEFAULT_CIRCUIT_HALF_OPEN_REQUESTS
+        );
+
+        let file = FileConfig {
+            circuit_open_ms: Some(1_000),
+            ..Default::default()
+        };
+        let err = resolve_config(Some(file), Overrides::default()).unwrap_err();
+        assert!(matches!(err, ConfigError::Upstreams { .. }));
+    }
+
+    #[rstest]
+    fn response_cache_rules_extend_the_defaults() {
+        let config = resolve_config(None, Overrides::default()).unwrap();
+        assert_eq!(config.response_cache(), None);
+
+        let file: FileConfig = toml::from_str(
+            r#"
+            cache_max_entries = 1000
+
+            [cache_methods]
+            eth_getLogs = { block_param = 0 }
+            eth_chainId = "never"
+            "#,
+        )
+        .unwrap();
+        let config = resolve_config(Some(file), Overrides::default()).unwrap();
+        let cache = config.response_cache().expect("response cache enabled");
+        assert_eq!(cache.max_entries(), 1000);
+        assert_eq!(cache.ttl(), Duration::from_millis(DEFAULT_CACHE_TTL_MS));
+        assert_eq!(cache.rule("eth_getLogs"), CacheRule::BlockParam(0));
+        assert_eq!(cache.rule("eth_chainId"), CacheRule::Never);
+        assert_eq!(cache.rule("eth_getBlockByHash"), CacheRule::Always);
+
+        let file = FileConfig {
+            cache_ttl_ms: Some(1_000),
+            ..Default::default()
+        };
+        let err = resolve_config(Some(file), Overrides::default()).unwrap_err();
+        assert!(matches!(err, ConfigError::ResponseCache { .. }));
+    }
+
+    #[rstest]
+    fn metrics_address_prefers_override() {
+        let config = resolve_config(None, Overrides::default()).unwrap();
+        assert_eq!(config.metrics_address(), None);
+
+        let file = FileConfig {
+            metrics_address: Some("127.0.0.1:9100".into()),
+            ..Default::default()
+        };
+        let config = resolve_config(Some(file.clone()), Overrides::default()).unwrap();
+        assert_eq!(
+            config.metrics_address(),
+            Some("127.0.0.1:9100".parse().unwrap())
+        );
+
+        let overrides =
+            Overrides::default().with_metrics_address(Some("0.0.0.0:9200".parse().unwrap()));
+        let config = resolve_config(Some(file), overrides).unwrap();
+        assert_eq!(
+            config.metrics_address(),
+            Some("0.0.0.0:9200".parse().unwrap())
+        );
+    }
+
+    #[rstest]
+    fn admin_token_prefers_override() {
+        let file = FileConfig {
+            admin_address: Some("127.0.0.1:9300".into()),
+            admin_token: Some("from-file".into()),
+            admin_persist: Some(true),
+            ..Default::default()
+        };
+        let config = resolve_config(Some(file.clone()), Overrides::default()).unwrap();
+        let admin = config.admin().expect("admin API enabled");
+        assert_eq!(admin.address, "127.0.0.1:9300".parse().unwrap());
+        assert_eq!(admin.token.as_deref(), Some("from-file"));
+        assert!(admin.persist);
+
+        let overrides = Overrides::default().with_admin_token(Some("from-cli".into()));
+        let config = resolve_config(Some(file), overrides).unwrap();
+        assert_eq!(config.admin().unwrap().token.as_deref(), Some("from-cli"));
+
+        let overrides = Overrides::default().with_admin_token(Some("orphan".into()));
+        let err = resolve_config(None, overrides).unwrap_err();
+        assert!(matches!(err, ConfigError::Admin { .. }));
+    }
+
+    #[rstest]
+    fn listener_tls_paths_are_resolved() {
+        let file = FileConfig {
+            tls_cert_path: Some("certs/proxy.pem".into()),
+            tls_key_path: Some("certs/proxy.key".into()),
+            ..Default::default()
+        };
+        let config = resolve_config(Some(file), Overrides::default()).expect("config resolves");
+        let tls = config.listener_tls().expect("listener TLS configured");
+        assert_eq!(tls.cert_path, std::path::Path::new("certs/proxy.pem"));
+        assert_eq!(tls.client_ca_path, None);
+
+        let file = FileConfig {
+            tls_client_ca_path: Some("certs/ca.pem".into()),
+            ..Default::default()
+        };
+        let err = resolve_config(Some(file), Overrides::default()).unwrap_err();
+        assert!(matches!(err, ConfigError::Tls { .. }));
     }
 
     #[rstest]
@@ -170,6 +1078,7 @@ mod tests {
             bind_address: None,
             upstream_url: None,
             blocked_methods: Some(vec!["  ".into(), "eth_call".into()]),
+            ..Default::default()
         };
 
         let config = resolve_config(Some(file), Overrides::default()).expect("config resolves");
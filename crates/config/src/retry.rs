@@ -0,0 +1,102 @@
+use crate::ConfigError;
+use crate::{
+    DEFAULT_UPSTREAM_RETRIES, DEFAULT_UPSTREAM_RETRY_BACKOFF_MS,
+    DEFAULT_UPSTREAM_RETRY_MAX_BACKOFF_MS,
+};
+use std::time::Duration;
+
+/// Retries of idempotent calls after an upstream connection error.
+///
+/// Only methods marked idempotent in [`Config::idempotent_methods`](crate::Config::idempotent_methods)
+/// are retried. The delay before each retry doubles from `backoff` up to `max_backoff`, and the
+/// proxy adds jitter so clients hitting the same failure do not retry in lockstep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    retries: u32,
+    backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Create a retry policy, rejecting a zero delay or a maximum below it.
+    pub fn new(
+        retries: u32,
+        backoff: Duration,
+        max_backoff: Duration,
+    ) -> Result<Self, ConfigError> {
+        if backoff.is_zero() {
+            return Err(ConfigError::Upstreams {
+                message: "`upstream_retry_backoff_ms` must be greater than zero".into(),
+            });
+        }
+        if max_backoff < backoff {
+            return Err(ConfigError::Upstreams {
+                message:
+                    "`upstream_retry_max_backoff_ms` must not be below `upstream_retry_backoff_ms`"
+                        .into(),
+            });
+        }
+        Ok(Self {
+            retries,
+            backoff,
+            max_backoff,
+        })
+    }
+
+    /// Retries allowed after the first attempt; `0` disables retrying.
+    pub const fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    /// Delay before the first retry.
+    pub const fn backoff(&self) -> Duration {
+        self.backoff
+    }
+
+    /// Upper bound on the delay.
+    pub const fn max_backoff(&self) -> Duration {
+        self.max_backoff
+    }
+
+    /// Delay before retry number `retry` (at least one), before jitter is applied.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let exponent = retry.saturating_sub(1).min(31);
+        self.backoff
+            .checked_mul(1 << exponent)
+            .map_or(self.max_backoff, |delay| delay.min(self.max_backoff))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: DEFAULT_UPSTREAM_RETRIES,
+            backoff: Duration::from_millis(DEFAULT_UPSTREAM_RETRY_BACKOFF_MS),
+            max_backoff: Duration::from_millis(DEFAULT_UPSTREAM_RETRY_MAX_BACKOFF_MS),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(1, 50)]
+    #[case(2, 100)]
+    #[case(3, 200)]
+    #[case(u32::MAX, 300)]
+    fn delay_doubles_up_to_max(#[case] retry: u32, #[case] millis: u64) {
+        let policy =
+            RetryPolicy::new(3, Duration::from_millis(50), Duration::from_millis(300)).unwrap();
+        assert_eq!(policy.delay(retry), Duration::from_millis(millis));
+    }
+
+    #[rstest]
+    fn rejects_inconsistent_bounds() {
+        assert!(RetryPolicy::new(1, Duration::ZERO, Duration::from_secs(1)).is_err());
+        assert!(RetryPolicy::new(1, Duration::from_secs(2), Duration::from_secs(1)).is_err());
+        assert!(RetryPolicy::new(0, Duration::from_secs(1), Duration::from_secs(1)).is_ok());
+    }
+}
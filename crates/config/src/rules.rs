@@ -0,0 +1,476 @@
+use crate::ConfigError;
+use crate::is_method_pattern;
+use crate::matcher::compile_pattern;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
+
+/// Blocks a method when its `params` satisfy every predicate.
+///
+/// ```toml
+/// [[param_rules]]
+/// name = "treasury-balance"
+/// method = "anvil_setBalance"
+/// when = [{ path = "$[0]", address = ["0x00000000219ab540356cBB839Cbe05303d7705Fa"] }]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ParamRule {
+    /// Name reported when the rule blocks a request; defaults to the method.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Method name or glob pattern the rule applies to.
+    pub method: String,
+    /// Predicates that must all hold for the rule to block the request.
+    #[serde(default)]
+    pub when: Vec<ParamPredicate>,
+}
+
+impl ParamRule {
+    /// Name used to identify the rule in errors and logs.
+    pub fn display_name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.method)
+    }
+
+    /// Check that the method pattern, paths, regexes and addresses are well formed.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if is_method_pattern(&self.method) {
+            compile_pattern(&self.method.to_ascii_lowercase())?;
+        }
+
+        for predicate in &self.when {
+            match &predicate.condition {
+                ParamCondition::Regex(pattern) => {
+                    regex::Regex::new(pattern).map_err(|source| ConfigError::ParamRegex {
+                        rule: self.display_name().to_string(),
+                        source,
+                    })?;
+                }
+                ParamCondition::Address(addresses) => {
+                    if let Some(address) = addresses.iter().find(|address| !is_address(address)) {
+                        return Err(ConfigError::ParamRule {
+                            rule: self.display_name().to_string(),
+                            message: format!("invalid address '{address}'"),
+                        });
+                    }
+                }
+                ParamCondition::Range(range) if range.is_unbounded() => {
+                    return Err(ConfigError::ParamRule {
+                        rule: self.display_name().to_string(),
+                        message: "range requires `min`, `max`, or both".into(),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A condition evaluated against the value found at `path` inside the request `params`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ParamPredicate {
+    /// JSONPath-style location inside `params`, such as `$[0]` or `$[0].forking.jsonRpcUrl`.
+    pub path: ParamPath,
+    /// Condition applied to the selected value.
+    #[serde(flatten)]
+    pub condition: ParamCondition,
+}
+
+/// Conditions supported by [`ParamPredicate`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParamCondition {
+    /// The value equals the given JSON value.
+    Equals(Value),
+    /// The value equals one of the given JSON values.
+    In(Vec<Value>),
+    /// The value is a quantity (number, decimal or `0x` hex string) inside the range.
+    ///
+    /// Values that are not quantities at all also match, so a range rule fails closed.
+    Range(QuantityRange),
+    /// The value is a string matching the regular expression.
+    Regex(String),
+    /// The value is one of the given addresses, compared case-insensitively.
+    Address(Vec<String>),
+    /// The value is present and not `null` (`true`) or missing/`null` (`false`).
+    Exists(bool),
+}
+
+/// Inclusive range over [`Quantity`] values; either bound may be omitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub struct QuantityRange {
+    /// Smallest value inside the range.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<Quantity>,
+    /// Largest value inside the range.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<Quantity>,
+}
+
+impl QuantityRange {
+    /// Returns `true` when the value lies inside the range.
+    pub fn contains(&self, value: u128) -> bool {
+        self.min.is_none_or(|min| value >= min.0) && self.max.is_none_or(|max| value <= max.0)
+    }
+
+    /// Returns `true` when a JSON parameter lies inside the range.
+    ///
+    /// Quantities too large for a [`Quantity`] lie above every bound. Values that are present
+    /// but are not unsigned quantities (negative, fractional, or malformed) count as inside, so
+    /// a rule bounding them blocks the call instead of letting it through.
+    pub fn contains_json(&self, value: &Value) -> bool {
+        match Reading::from_json(value) {
+            Reading::Fits(value) => self.contains(value),
+            Reading::TooLarge => self.max.is_none(),
+            Reading::Invalid => true,
+        }
+    }
+
+    /// Returns `true` when neither bound is set.
+    pub const fn is_unbounded(&self) -> bool {
+        self.min.is_none() && self.max.is_none()
+    }
+}
+
+/// Unsigned integer written as a TOML integer, a decimal string, or a `0x` hex string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Quantity(pub u128);
+
+impl Quantity {
+    /// Parse a decimal or `0x`-prefixed hex string.
+    pub fn parse(value: &str) -> Option<Self> {
+        match Reading::from_str(value) {
+            Reading::Fits(value) => Some(Self(value)),
+            Reading::TooLarge | Reading::Invalid => None,
+        }
+    }
+
+    /// Interpret a JSON value (number or quantity string) as a [`Quantity`].
+    pub fn from_json(value: &Value) -> Option<Self> {
+        match Reading::from_json(value) {
+            Reading::Fits(value) => Some(Self(value)),
+            Reading::TooLarge | Reading::Invalid => None,
+        }
+    }
+}
+
+/// A quantity read from text or JSON, telling values too large for a [`Quantity`] apart from
+/// values that are not quantities at all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Reading {
+    Fits(u128),
+    TooLarge,
+    Invalid,
+}
+
+impl Reading {
+    fn from_str(value: &str) -> Self {
+        let value = value.trim();
+        match value
+            .strip_prefix("0x")
+            .or_else(|| value.strip_prefix("0X"))
+        {
+            Some(hex) if !hex.is_empty() && hex.bytes().all(|byte| byte.is_ascii_hexdigit()) => {
+                u128::from_str_radix(hex, 16).map_or(Self::TooLarge, Self::Fits)
+            }
+            Some(_) => Self::Invalid,
+            None if !value.is_empty() && value.bytes().all(|byte| byte.is_ascii_digit()) => {
+                value.parse().map_or(Self::TooLarge, Self::Fits)
+            }
+            None => Self::Invalid,
+        }
+    }
+
+    fn from_json(value: &Value) -> Self {
+        match value {
+            Value::Number(number) => {
+                if let Some(value) = number.as_u64() {
+                    return Self::Fits(value.into());
+                }
+                match number.as_f64() {
+                    Some(value) if value >= 0.0 && value.fract() == 0.0 => {
+                        if value < 2f64.powi(128) {
+                            Self::Fits(value as u128)
+                        } else {
+                            Self::TooLarge
+                        }
+                    }
+                    _ => Self::Invalid,
+                }
+            }
+            Value::String(value) => Self::from_str(value),
+            _ => Self::Invalid,
+        }
+    }
+}
+
+impl Serialize for Quantity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{:#x}", self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for Quantity {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Integer(u64),
+            String(String),
+        }
+
+        match Raw::deserialize(deserializer)? {
+            Raw::Integer(value) => Ok(Self(value.into())),
+            Raw::String(value) => Self::parse(&value)
+                .ok_or_else(|| serde::de::Error::custom(format!("invalid quantity '{value}'"))),
+        }
+    }
+}
+
+/// One step of a [`ParamPath`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    /// Array index, written `[0]`.
+    Index(usize),
+    /// Object key, written `.key` or `["key"]`.
+    Key(String),
+}
+
+/// JSONPath-style selector rooted at the request `params`, e.g. `$[0].forking.jsonRpcUrl`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ParamPath {
+    raw: String,
+    segments: Vec<PathSegment>,
+}
+
+impl ParamPath {
+    /// Segments of the path, excluding the leading `$`.
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.segments
+    }
+
+    /// Resolve the path against `params`, returning the selected value if present.
+    pub fn resolve<'a>(&self, params: &'a Value) -> Option<&'a Value> {
+        self.segments
+            .iter()
+            .try_fold(params, |value, segment| match segment {
+                PathSegment::Index(index) => value.get(*index),
+                PathSegment::Key(key) => value.get(key),
+            })
+    }
+}
+
+impl fmt::Display for ParamPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+impl FromStr for ParamPath {
+    type Err = ConfigError;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let invalid = |message: &str| ConfigError::ParamPath {
+            path: raw.to_string(),
+            message: message.to_string(),
+        };
+
+        let trimmed = raw.trim();
+        let mut rest = trimmed.strip_prefix('$').unwrap_or(trimmed);
+        let mut segments = Vec::new();
+
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix('[') {
+                let end = after.find(']').ok_or_else(|| invalid("missing ']'"))?;
+                let inner = after[..end].trim();
+                let segment = if let Some(key) = inner
+                    .strip_prefix('"')
+                    .and_then(|key| key.strip_suffix('"'))
+                    .or_else(|| {
+                        inner
+                            .strip_prefix('\'')
+                            .and_then(|key| key.strip_suffix('\''))
+                    }) {
+                    PathSegment::Key(key.to_string())
+                } else {
+                    PathSegment::Index(inner.parse().map_err(|_| invalid("invalid array index"))?)
+                };
+                segments.push(segment);
+                rest = &after[end + 1..];
+            } else if let Some(after) = rest.strip_prefix('.') {
+                let end = after.find(['.', '[']).unwrap_or(after.len());
+                if end == 0 {
+                    return Err(invalid("empty key"));
+                }
+                segments.push(PathSegment::Key(after[..end].to_string()));
+                rest = &after[end..];
+            } else if segments.is_empty() && rest.len() == trimmed.len() {
+                // Allow a bare leading key such as `foo.bar` without `$.`.
+                let end = rest.find(['.', '[']).unwrap_or(rest.len());
+                segments.push(PathSegment::Key(rest[..end].to_string()));
+                rest = &rest[end..];
+            } else {
+                return Err(invalid("expected '.' or '['"));
+            }
+        }
+
+        Ok(Self {
+            raw: trimmed.to_string(),
+            segments,
+        })
+    }
+}
+
+impl Serialize for ParamPath {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.raw)
+    }
+}
+
+impl<'de> Deserialize<'de> for ParamPath {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        raw.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Returns `true` for a `0x`-prefixed, 20-byte hex address in any letter case.
+pub(crate) fn is_address(value: &str) -> bool {
+    value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .is_some_and(|hex| hex.len() == 40 && hex.bytes().all(|byte| byte.is_ascii_hexdigit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    #[rstest]
+    #[case("$", vec![])]
+    #[case("$[0]", vec![PathSegment::Index(0)])]
+    #[case(
+        "$[0].forking.jsonRpcUrl",
+        vec![
+            PathSegment::Index(0),
+            PathSegment::Key("forking".into()),
+            PathSegment::Key("jsonRpcUrl".into()),
+        ]
+    )]
+    #[case("$[1]['to']", vec![PathSegment::Index(1), PathSegment::Key("to".into())])]
+    #[case("$.from", vec![PathSegment::Key("from".into())])]
+    fn parses_paths(#[case] raw: &str, #[case] expected: Vec<PathSegment>) {
+        assert_eq!(
+            raw.parse::<ParamPath>().unwrap().segments(),
+            expected.as_slice()
+        );
+    }
+
+    #[rstest]
+    #[case("$[x]")]
+    #[case("$[0")]
+    #[case("$..a")]
+    fn rejects_invalid_paths(#[case] raw: &str) {
+        assert!(matches!(
+            raw.parse::<ParamPath>(),
+            Err(ConfigError::ParamPath { .. })
+        ));
+    }
+
+    #[rstest]
+    fn resolves_nested_values() {
+        let params = json!([{"forking": {"jsonRpcUrl": "https://rpc"}}]);
+        let path: ParamPath = "$[0].forking.jsonRpcUrl".parse().unwrap();
+        assert_eq!(path.resolve(&params), Some(&json!("https://rpc")));
+        let missing: ParamPath = "$[1]".parse().unwrap();
+        assert_eq!(missing.resolve(&params), None);
+    }
+
+    #[rstest]
+    #[case("0x10", Some(16))]
+    #[case("42", Some(42))]
+    #[case("0x", None)]
+    #[case("abc", None)]
+    fn parses_quantities(#[case] raw: &str, #[case] expected: Option<u128>) {
+        assert_eq!(Quantity::parse(raw).map(|quantity| quantity.0), expected);
+    }
+
+    #[rstest]
+    #[case::fits(json!("0x10"), true, true)]
+    #[case::above_u128_hex(json!(format!("0x1{}", "0".repeat(32))), true, false)]
+    #[case::above_u128_decimal(json!("1".repeat(40)), true, false)]
+    #[case::large_json_number(serde_json::from_str::<Value>("1e40").unwrap(), true, false)]
+    #[case::above_u64_json_number(serde_json::from_str::<Value>("100000000000000000000").unwrap(), true, false)]
+    #[case::zero(json!(0), false, true)]
+    #[case::negative(json!(-1), true, true)]
+    #[case::malformed(json!("0xzz"), true, true)]
+    fn large_and_malformed_quantities_fail_closed(
+        #[case] value: Value,
+        #[case] above_min: bool,
+        #[case] below_max: bool,
+    ) {
+        let min = QuantityRange {
+            min: Some(Quantity(1)),
+            max: None,
+        };
+        let max = QuantityRange {
+            min: None,
+            max: Some(Quantity(1_000)),
+        };
+        assert_eq!(min.contains_json(&value), above_min);
+        assert_eq!(max.contains_json(&value), below_max);
+    }
+
+    #[rstest]
+    fn deserializes_rules_from_toml() {
+        let rule: ParamRule = toml::from_str(
+            r#"
+            name = "treasury"
+            method = "anvil_setBalance"
+            when = [
+              { path = "$[0]", address = ["0x00000000219ab540356cBB839Cbe05303d7705Fa"] },
+              { path = "$[1]", range = { min = "0xde0b6b3a7640000" } },
+            ]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(rule.display_name(), "treasury");
+        assert_eq!(rule.when.len(), 2);
+        assert!(matches!(rule.when[0].condition, ParamCondition::Address(_)));
+        assert_eq!(
+            rule.when[1].condition,
+            ParamCondition::Range(QuantityRange {
+                min: Some(Quantity(1_000_000_000_000_000_000)),
+                max: None,
+            })
+        );
+        rule.validate().unwrap();
+    }
+
+    #[rstest]
+    fn validation_rejects_bad_regex_and_addresses() {
+        let mut rule = ParamRule {
+            name: None,
+            method: "anvil_reset".into(),
+            when: vec![ParamPredicate {
+                path: "$[0].forking.jsonRpcUrl".parse().unwrap(),
+                condition: ParamCondition::Regex("(".into()),
+            }],
+        };
+        assert!(matches!(
+            rule.validate(),
+            Err(ConfigError::ParamRegex { .. })
+        ));
+
+        rule.when[0].condition = ParamCondition::Address(vec!["0x1234".into()]);
+        assert!(matches!(
+            rule.validate(),
+            Err(ConfigError::ParamRule { .. })
+        ));
+    }
+}
@@ -0,0 +1,66 @@
+use crate::{Config, ConfigError, Overrides, load_file, resolve_config};
+use std::path::{Path, PathBuf};
+
+/// Where a [`Config`] comes from: the configuration file plus the CLI overrides layered on top.
+///
+/// Kept around by the runtime so the configuration can be resolved again when the file changes.
+#[derive(Debug, Clone)]
+pub struct ConfigSource {
+    path: PathBuf,
+    overrides: Overrides,
+}
+
+impl ConfigSource {
+    /// Create a source reading `path` and applying `overrides`.
+    pub const fn new(path: PathBuf, overrides: Overrides) -> Self {
+        Self { path, overrides }
+    }
+
+    /// Path of the configuration file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Overrides applied on top of the file.
+    pub const fn overrides(&self) -> &Overrides {
+        &self.overrides
+    }
+
+    /// Read the file (if present) and resolve it with the overrides.
+    pub fn load(&self) -> Result<Config, ConfigError> {
+        resolve_config(load_file(&self.path)?, self.overrides.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use std::fs;
+
+    #[rstest]
+    fn load_reads_the_current_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".veto.toml");
+        let source = ConfigSource::new(
+            path.clone(),
+            Overrides::new(None, None, vec!["custom_block".into()]),
+        );
+
+        fs::write(&path, "use_defaults = false\n").unwrap();
+        let config = source.load().unwrap();
+        assert_eq!(config.blocked_methods().len(), 1);
+
+        fs::write(
+            &path,
+            "use_defaults = false\nblocked_methods = [\"eth_sign\"]\n",
+        )
+        .unwrap();
+        let config = source.load().unwrap();
+        assert!(config.blocked_methods().contains("eth_sign"));
+        assert!(config.blocked_methods().contains("custom_block"));
+
+        fs::write(&path, "use_defaults = \"maybe\"\n").unwrap();
+        assert!(source.load().is_err());
+    }
+}
@@ -0,0 +1,127 @@
+use crate::ConfigError;
+use crate::DEFAULT_UPSTREAM_TIMEOUT_MS;
+use crate::is_method_pattern;
+use crate::matcher::compile_pattern;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Upstream deadline for calls to a method name or glob pattern.
+///
+/// ```toml
+/// upstream_timeout_ms = 10000
+///
+/// [[upstream_timeouts]]
+/// method = "debug_trace*"
+/// timeout_ms = 120000
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct MethodTimeout {
+    /// Method name or glob pattern the deadline applies to.
+    pub method: String,
+    /// Time allowed for the upstream to answer, in milliseconds.
+    pub timeout_ms: u64,
+}
+
+impl MethodTimeout {
+    /// Time allowed for the upstream to answer.
+    pub const fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+
+    /// Check that the deadline is positive and the method pattern is well formed.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |message: &str| ConfigError::UpstreamTimeout {
+            method: self.method.clone(),
+            message: message.into(),
+        };
+
+        if self.timeout_ms == 0 {
+            return Err(invalid("`timeout_ms` must be greater than zero"));
+        }
+        if self.method.trim().is_empty() {
+            return Err(invalid("`method` must not be empty"));
+        }
+        if is_method_pattern(&self.method) {
+            compile_pattern(&self.method.to_ascii_lowercase())?;
+        }
+        Ok(())
+    }
+}
+
+/// Deadlines for upstream calls: a default and per-method overrides.
+///
+/// The first override matching a method wins; a batch waits for the longest deadline among its
+/// forwarded calls.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpstreamTimeouts {
+    default: Duration,
+    methods: Vec<MethodTimeout>,
+}
+
+impl UpstreamTimeouts {
+    /// Create the deadlines, rejecting a zero default and invalid overrides.
+    pub fn new(default: Duration, methods: Vec<MethodTimeout>) -> Result<Self, ConfigError> {
+        if default.is_zero() {
+            return Err(ConfigError::UpstreamTimeout {
+                method: "default".into(),
+                message: "`upstream_timeout_ms` must be greater than zero".into(),
+            });
+        }
+        methods.iter().try_for_each(MethodTimeout::validate)?;
+        Ok(Self { default, methods })
+    }
+
+    /// Deadline for methods without an override.
+    pub const fn default_timeout(&self) -> Duration {
+        self.default
+    }
+
+    /// Per-method overrides in configured order.
+    pub fn methods(&self) -> &[MethodTimeout] {
+        &self.methods
+    }
+}
+
+impl Default for UpstreamTimeouts {
+    fn default() -> Self {
+        Self {
+            default: Duration::from_millis(DEFAULT_UPSTREAM_TIMEOUT_MS),
+            methods: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn timeout(method: &str, timeout_ms: u64) -> MethodTimeout {
+        MethodTimeout {
+            method: method.into(),
+            timeout_ms,
+        }
+    }
+
+    #[rstest]
+    #[case(timeout("debug_trace*", 60_000), true)]
+    #[case(timeout("eth_call", 0), false)]
+    #[case(timeout(" ", 1_000), false)]
+    #[case(timeout("debug_[", 1_000), false)]
+    fn validates_overrides(#[case] timeout: MethodTimeout, #[case] valid: bool) {
+        assert_eq!(timeout.validate().is_ok(), valid);
+        assert_eq!(
+            UpstreamTimeouts::new(Duration::from_secs(1), vec![timeout]).is_ok(),
+            valid
+        );
+    }
+
+    #[rstest]
+    fn rejects_zero_default() {
+        assert!(matches!(
+            UpstreamTimeouts::new(Duration::ZERO, Vec::new()),
+            Err(ConfigError::UpstreamTimeout { method, .. }) if method == "default"
+        ));
+    }
+}
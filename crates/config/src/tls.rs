@@ -0,0 +1,116 @@
+use crate::ConfigError;
+use std::path::PathBuf;
+
+/// TLS settings used when connecting to an `https://` (or `wss://`) upstream.
+///
+/// The bundled WebPKI roots are always trusted; `ca_path` adds further PEM certificates, such
+/// as a private CA or a self-signed node certificate.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UpstreamTls {
+    /// PEM bundle of additional trusted certificate authorities.
+    pub ca_path: Option<PathBuf>,
+    /// PEM certificate chain presented to the upstream for client authentication.
+    pub client_cert_path: Option<PathBuf>,
+    /// PEM private key matching `client_cert_path`.
+    pub client_key_path: Option<PathBuf>,
+}
+
+impl UpstreamTls {
+    /// Returns `true` when no TLS settings are configured.
+    pub const fn is_empty(&self) -> bool {
+        self.ca_path.is_none() && self.client_cert_path.is_none() && self.client_key_path.is_none()
+    }
+
+    /// Check that the client certificate and key are configured together.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.client_cert_path.is_some() != self.client_key_path.is_some() {
+            return Err(ConfigError::Tls {
+                message: "`upstream_client_cert_path` and `upstream_client_key_path` must be set together"
+                    .into(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Certificate and key used to terminate TLS on the proxy listener.
+///
+/// Setting `client_ca_path` enables mutual TLS: clients must present a certificate signed by
+/// one of the listed authorities.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListenerTls {
+    /// PEM certificate chain served to clients.
+    pub cert_path: PathBuf,
+    /// PEM private key matching `cert_path`.
+    pub key_path: PathBuf,
+    /// PEM bundle of authorities allowed to sign client certificates.
+    pub client_ca_path: Option<PathBuf>,
+}
+
+impl ListenerTls {
+    /// Build listener TLS settings from the optional file values.
+    ///
+    /// Returns `Ok(None)` when none are set and an error when only some of the required
+    /// paths are present.
+    pub fn from_paths(
+        cert_path: Option<PathBuf>,
+        key_path: Option<PathBuf>,
+        client_ca_path: Option<PathBuf>,
+    ) -> Result<Option<Self>, ConfigError> {
+        match (cert_path, key_path) {
+            (Some(cert_path), Some(key_path)) => Ok(Some(Self {
+                cert_path,
+                key_path,
+                client_ca_path,
+            })),
+            (None, None) if client_ca_path.is_none() => Ok(None),
+            (None, None) => Err(ConfigError::Tls {
+                message: "`tls_client_ca_path` requires `tls_cert_path` and `tls_key_path`".into(),
+            }),
+            _ => Err(ConfigError::Tls {
+                message: "`tls_cert_path` and `tls_key_path` must be set together".into(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    fn client_cert_requires_key() {
+        let tls = UpstreamTls {
+            client_cert_path: Some("client.pem".into()),
+            ..UpstreamTls::default()
+        };
+        assert!(matches!(tls.validate(), Err(ConfigError::Tls { .. })));
+
+        let tls = UpstreamTls {
+            client_key_path: Some("client.key".into()),
+            ..tls
+        };
+        tls.validate().unwrap();
+        assert!(!tls.is_empty());
+    }
+
+    #[rstest]
+    fn listener_tls_requires_cert_and_key() {
+        assert_eq!(ListenerTls::from_paths(None, None, None).unwrap(), None);
+        assert!(ListenerTls::from_paths(Some("cert.pem".into()), None, None).is_err());
+        assert!(ListenerTls::from_paths(None, None, Some("ca.pem".into())).is_err());
+
+        let tls = ListenerTls::from_paths(
+            Some("cert.pem".into()),
+            Some("key.pem".into()),
+            Some("ca.pem".into()),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            tls.client_ca_path.as_deref(),
+            Some(std::path::Path::new("ca.pem"))
+        );
+    }
+}
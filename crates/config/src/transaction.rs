@@ -0,0 +1,137 @@
+use crate::rules::is_address;
+use crate::{ConfigError, QuantityRange};
+use serde::{Deserialize, Serialize};
+
+/// Blocks a signed transaction sent via `eth_sendRawTransaction` when every listed field matches.
+///
+/// Fields left unset are ignored, so a rule only constrains what it names.
+///
+/// ```toml
+/// [[tx_rules]]
+/// name = "no-approvals"
+/// selector = ["0x095ea7b3"]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub struct TxRule {
+    /// Name reported when the rule blocks a transaction.
+    pub name: String,
+    /// Recipient addresses, compared case-insensitively. Contract creations never match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<Vec<String>>,
+    /// Range of transferred wei.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<QuantityRange>,
+    /// 4-byte function selectors (the first bytes of `data`), as `0x`-prefixed hex.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selector: Option<Vec<String>>,
+    /// Range of the gas limit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas_limit: Option<QuantityRange>,
+    /// Range of the max fee per gas (the gas price for legacy and EIP-2930 transactions).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_fee_per_gas: Option<QuantityRange>,
+    /// Range of the max priority fee per gas (the gas price for legacy and EIP-2930
+    /// transactions).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_priority_fee_per_gas: Option<QuantityRange>,
+    /// EIP-2718 transaction types, with `0` for legacy transactions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tx_type: Option<Vec<u8>>,
+}
+
+impl TxRule {
+    /// Check that addresses, selectors and ranges are well formed.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |message: String| ConfigError::TxRule {
+            rule: self.name.clone(),
+            message,
+        };
+
+        if self.name.trim().is_empty() {
+            return Err(invalid("rule name must not be empty".into()));
+        }
+        if let Some(address) = self.to.iter().flatten().find(|to| !is_address(to)) {
+            return Err(invalid(format!("invalid address '{address}'")));
+        }
+        if let Some(selector) = self
+            .selector
+            .iter()
+            .flatten()
+            .find(|selector| !is_selector(selector))
+        {
+            return Err(invalid(format!("invalid selector '{selector}'")));
+        }
+
+        let ranges = [
+            ("value", self.value),
+            ("gas_limit", self.gas_limit),
+            ("max_fee_per_gas", self.max_fee_per_gas),
+            ("max_priority_fee_per_gas", self.max_priority_fee_per_gas),
+        ];
+        if let Some((field, _)) = ranges
+            .iter()
+            .find(|(_, range)| range.is_some_and(|range| range.is_unbounded()))
+        {
+            return Err(invalid(format!("`{field}` requires `min`, `max`, or both")));
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns `true` for a `0x`-prefixed, 4-byte hex selector.
+fn is_selector(value: &str) -> bool {
+    value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .is_some_and(|hex| hex.len() == 8 && hex.bytes().all(|byte| byte.is_ascii_hexdigit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Quantity;
+    use rstest::rstest;
+
+    #[rstest]
+    fn deserializes_rules_from_toml() {
+        let rule: TxRule = toml::from_str(
+            r#"
+            name = "big-transfers"
+            to = ["0x00000000219ab540356cBB839Cbe05303d7705Fa"]
+            value = { min = "0xde0b6b3a7640000" }
+            tx_type = [0, 2]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(rule.name, "big-transfers");
+        assert_eq!(
+            rule.value.and_then(|range| range.min),
+            Some(Quantity(1_000_000_000_000_000_000))
+        );
+        assert_eq!(rule.tx_type, Some(vec![0, 2]));
+        rule.validate().unwrap();
+    }
+
+    #[rstest]
+    #[case::empty_name(TxRule::default())]
+    #[case::bad_selector(TxRule {
+        name: "approve".into(),
+        selector: Some(vec!["0x095ea7".into()]),
+        ..TxRule::default()
+    })]
+    #[case::bad_address(TxRule {
+        name: "to".into(),
+        to: Some(vec!["0xdead".into()]),
+        ..TxRule::default()
+    })]
+    #[case::unbounded_range(TxRule {
+        name: "gas".into(),
+        gas_limit: Some(QuantityRange::default()),
+        ..TxRule::default()
+    })]
+    fn rejects_invalid_rules(#[case] rule: TxRule) {
+        assert!(matches!(rule.validate(), Err(ConfigError::TxRule { .. })));
+    }
+}
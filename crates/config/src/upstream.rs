@@ -0,0 +1,255 @@
+use crate::{ConfigError, DEFAULT_UPSTREAM_BACKOFF_MS, DEFAULT_UPSTREAM_MAX_BACKOFF_MS};
+use http::Uri;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// An upstream endpoint and its share of traffic under [`crate::BalanceStrategy::Weighted`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpstreamTarget {
+    /// JSON-RPC endpoint.
+    pub url: Uri,
+    /// Relative weight (at least 1).
+    pub weight: u32,
+}
+
+impl From<Uri> for UpstreamTarget {
+    fn from(url: Uri) -> Self {
+        Self { url, weight: 1 }
+    }
+}
+
+/// An `upstreams` entry in the configuration file: a bare URL or a table with a weight.
+///
+/// ```toml
+/// upstreams = ["http://127.0.0.1:8545", { url = "http://127.0.0.1:9545", weight = 3 }]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum FileUpstream {
+    /// Endpoint with the default weight of 1.
+    Url(String),
+    /// Endpoint with an explicit weight.
+    Weighted {
+        /// JSON-RPC endpoint.
+        url: String,
+        /// Relative weight (at least 1).
+        weight: u32,
+    },
+}
+
+impl FileUpstream {
+    /// The endpoint URL as written in the file.
+    pub fn url(&self) -> &str {
+        match self {
+            Self::Url(url) | Self::Weighted { url, .. } => url,
+        }
+    }
+
+    /// The configured weight, defaulting to 1.
+    pub const fn weight(&self) -> u32 {
+        match self {
+            Self::Url(_) => 1,
+            Self::Weighted { weight, .. } => *weight,
+        }
+    }
+}
+
+impl From<&str> for FileUpstream {
+    fn from(url: &str) -> Self {
+        Self::Url(url.to_string())
+    }
+}
+
+/// Active health checks polling every upstream with `eth_syncing` and `eth_blockNumber`.
+///
+/// Upstreams that fail to answer, report that they are syncing, or trail the highest block seen
+/// across the set by more than `max_block_lag` are taken out of rotation until a later check
+/// passes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HealthCheck {
+    interval: Duration,
+    timeout: Duration,
+    max_block_lag: u64,
+}
+
+impl HealthCheck {
+    /// Create health check settings, rejecting a zero interval or timeout.
+    pub fn new(
+        interval: Duration,
+        timeout: Duration,
+        max_block_lag: u64,
+    ) -> Result<Self, ConfigError> {
+        if interval.is_zero() || timeout.is_zero() {
+            return Err(ConfigError::Upstreams {
+                message: "`health_check_interval_ms` and `health_check_timeout_ms` must be greater than zero".into(),
+            });
+        }
+        Ok(Self {
+            interval,
+            timeout,
+            max_block_lag,
+        })
+    }
+
+    /// Time between two rounds of checks.
+    pub const fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Time allowed for an upstream to answer.
+    pub const fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Blocks an upstream may trail the best upstream and stay in rotation.
+    pub const fn max_block_lag(&self) -> u64 {
+        self.max_block_lag
+    }
+}
+
+/// Circuit breaker kept for every upstream, failing calls fast while the upstream is down.
+///
+/// `failure_threshold` consecutive connection errors, `5xx` responses, or timeouts open the
+/// circuit. After `open_duration` it turns half-open and lets `half_open_requests` trial calls through: the
+/// circuit closes once they all succeed and opens again on the first failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    open_duration: Duration,
+    half_open_requests: u32,
+}
+
+impl CircuitBreaker {
+    /// Create circuit breaker settings, rejecting a zero threshold, duration or trial count.
+    pub fn new(
+        failure_threshold: u32,
+        open_duration: Duration,
+        half_open_requests: u32,
+    ) -> Result<Self, ConfigError> {
+        if failure_threshold == 0 || open_duration.is_zero() || half_open_requests == 0 {
+            return Err(ConfigError::Upstreams {
+                message: "`circuit_failure_threshold`, `circuit_open_ms` and `circuit_half_open_requests` must be greater than zero".into(),
+            });
+        }
+        Ok(Self {
+            failure_threshold,
+            open_duration,
+            half_open_requests,
+        })
+    }
+
+    /// Consecutive failures that open the circuit.
+    pub const fn failure_threshold(&self) -> u32 {
+        self.failure_threshold
+    }
+
+    /// Time the circuit stays open before letting trial calls through.
+    pub const fn open_duration(&self) -> Duration {
+        self.open_duration
+    }
+
+    /// Trial calls allowed while half-open, all of which must succeed to close the circuit.
+    pub const fn half_open_requests(&self) -> u32 {
+        self.half_open_requests
+    }
+}
+
+/// Exponential backoff applied before an unhealthy upstream is tried again.
+///
+/// The first failure waits `initial`; every further consecutive failure doubles the delay up
+/// to `max`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+}
+
+impl Backoff {
+    /// Create a backoff, rejecting a zero initial delay or a maximum below it.
+    pub fn new(initial: Duration, max: Duration) -> Result<Self, ConfigError> {
+        if initial.is_zero() {
+            return Err(ConfigError::Upstreams {
+                message: "`upstream_backoff_ms` must be greater than zero".into(),
+            });
+        }
+        if max < initial {
+            return Err(ConfigError::Upstreams {
+                message: "`upstream_max_backoff_ms` must not be below `upstream_backoff_ms`".into(),
+            });
+        }
+        Ok(Self { initial, max })
+    }
+
+    /// Delay after the first failure.
+    pub const fn initial(&self) -> Duration {
+        self.initial
+    }
+
+    /// Upper bound on the delay.
+    pub const fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// Delay before retrying after `failures` consecutive failures (at least one).
+    pub fn delay(&self, failures: u32) -> Duration {
+        let exponent = failures.saturating_sub(1).min(31);
+        self.initial
+            .checked_mul(1 << exponent)
+            .map_or(self.max, |delay| delay.min(self.max))
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_millis(DEFAULT_UPSTREAM_BACKOFF_MS),
+            max: Duration::from_millis(DEFAULT_UPSTREAM_MAX_BACKOFF_MS),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(1, 100)]
+    #[case(2, 200)]
+    #[case(4, 800)]
+    #[case(5, 1_000)]
+    #[case(u32::MAX, 1_000)]
+    fn delay_doubles_up_to_max(#[case] failures: u32, #[case] millis: u64) {
+        let backoff =
+            Backoff::new(Duration::from_millis(100), Duration::from_millis(1_000)).unwrap();
+        assert_eq!(backoff.delay(failures), Duration::from_millis(millis));
+    }
+
+    #[rstest]
+    fn rejects_inconsistent_bounds() {
+        assert!(Backoff::new(Duration::ZERO, Duration::from_secs(1)).is_err());
+        assert!(Backoff::new(Duration::from_secs(2), Duration::from_secs(1)).is_err());
+    }
+
+    #[rstest]
+    fn circuit_breaker_rejects_zero_settings() {
+        let open = Duration::from_secs(10);
+        assert!(CircuitBreaker::new(5, open, 1).is_ok());
+        assert!(CircuitBreaker::new(0, open, 1).is_err());
+        assert!(CircuitBreaker::new(5, Duration::ZERO, 1).is_err());
+        assert!(CircuitBreaker::new(5, open, 0).is_err());
+    }
+
+    #[rstest]
+    fn file_upstreams_accept_urls_and_weighted_tables() {
+        let file: crate::FileConfig = toml::from_str(
+            r#"upstreams = ["http://127.0.0.1:8545", { url = "http://127.0.0.1:9545", weight = 3 }]"#,
+        )
+        .unwrap();
+        let upstreams = file.upstreams.unwrap();
+        assert_eq!(upstreams[0], FileUpstream::from("http://127.0.0.1:8545"));
+        assert_eq!(upstreams[0].weight(), 1);
+        assert_eq!(upstreams[1].url(), "http://127.0.0.1:9545");
+        assert_eq!(upstreams[1].weight(), 3);
+    }
+}
@@ -0,0 +1,164 @@
+use crate::audit::{AuditCall, AuditTrail, Decision};
+use crate::server::{AppState, ClientAddr};
+use axum::body::Body;
+use axum::extract::{ConnectInfo, State};
+use axum::http::{HeaderMap, Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+use serde_json::Value;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use tracing::warn;
+use veto_config::ClientAccess;
+
+/// Header listing the addresses a request was forwarded for, client first.
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// Refuse clients outside the configured CIDR lists before their request body is read.
+///
+/// Allowed requests carry the client address in [`ClientAddr`], resolved through trusted
+/// proxies. Without a known peer address only deny-only lists let a request through.
+pub(crate) async fn filter_clients(
+    State(state): State<AppState>,
+    mut req: Request<Body>,
+    next: Next,
+) -> Response {
+    let Some(access) = state.policy.load().config.client_access().cloned() else {
+        return next.run(req).await;
+    };
+
+    let peer = req
+        .extensions()
+        .get::<ConnectInfo<ClientAddr>>()
+        .map(|ConnectInfo(ClientAddr(address))| *address);
+    let client = peer.map(|peer| client_address(&access, peer, req.headers()));
+    let permitted = client.map_or(access.allowed.is_empty(), |client| {
+        access.permits(client.ip())
+    });
+
+    if !permitted {
+        warn!(?client, ?peer, "refusing client outside the allowed cidrs");
+        if let Some(audit) = &state.audit {
+            let trail = AuditTrail {
+                calls: vec![AuditCall {
+                    method: None,
+                    id: Value::Null,
+                    decision: Decision::Blocked("client_access".into()),
+                }],
+                ..AuditTrail::default()
+            };
+            audit.record(client, &trail, StatusCode::FORBIDDEN, Duration::ZERO);
+        }
+        return Response::builder()
+            .status(StatusCode::FORBIDDEN)
+            .body(Body::from("forbidden"))
+            .expect("valid response");
+    }
+
+    if let Some(client) = client {
+        req.extensions_mut().insert(ConnectInfo(ClientAddr(client)));
+    }
+    next.run(req).await
+}
+
+/// Address of the client behind `peer`.
+///
+/// `X-Forwarded-For` is only read when `peer` is a trusted proxy, and is walked from the
+/// nearest hop back until an untrusted address is found. Forwarded clients are reported with
+/// port `0`, since the proxy does not pass the port on.
+fn client_address(access: &ClientAccess, peer: SocketAddr, headers: &HeaderMap) -> SocketAddr {
+    if !access.trusts(peer.ip()) {
+        return peer;
+    }
+
+    let hops: Vec<&str> = headers
+        .get_all(X_FORWARDED_FOR)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect();
+    let mut client = peer.ip();
+    for hop in hops.into_iter().rev() {
+        let Ok(ip) = hop.parse::<IpAddr>() else {
+            break;
+        };
+        client = ip;
+        if !access.trusts(ip) {
+            break;
+        }
+    }
+
+    if client == peer.ip() {
+        peer
+    } else {
+        SocketAddr::new(client, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::router;
+    use rstest::rstest;
+    use std::collections::HashSet;
+    use tower::util::ServiceExt;
+    use veto_config::Config;
+
+    fn access(allowed: &[&str], denied: &[&str], trusted: &[&str]) -> ClientAccess {
+        let blocks = |values: &[&str]| Some(values.iter().map(|value| value.to_string()).collect());
+        ClientAccess::from_file(blocks(allowed), blocks(denied), blocks(trusted))
+            .unwrap()
+            .unwrap()
+    }
+
+    #[rstest]
+    #[case::untrusted_peer("203.0.113.9:4000", "198.51.100.1", "203.0.113.9:4000")]
+    #[case::trusted_peer("10.0.0.1:4000", "198.51.100.1", "198.51.100.1:0")]
+    #[case::chained_proxies("10.0.0.1:4000", "198.51.100.1, 10.0.0.2", "198.51.100.1:0")]
+    #[case::spoofed_prefix("10.0.0.1:4000", "1.1.1.1, 198.51.100.1", "198.51.100.1:0")]
+    #[case::garbage("10.0.0.1:4000", "not-an-ip", "10.0.0.1:4000")]
+    fn forwarded_for_is_read_through_trusted_proxies(
+        #[case] peer: SocketAddr,
+        #[case] forwarded: &str,
+        #[case] expected: SocketAddr,
+    ) {
+        let mut headers = HeaderMap::new();
+        headers.insert(X_FORWARDED_FOR, forwarded.parse().unwrap());
+        let access = access(&[], &[], &["10.0.0.0/24"]);
+        assert_eq!(client_address(&access, peer, &headers), expected);
+    }
+
+    #[tokio::test]
+    async fn denied_clients_get_forbidden() {
+        let config = Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            "http://127.0.0.1:1".parse().unwrap(),
+            HashSet::from([String::from("anvil_mine")]),
+        )
+        .with_client_access(Some(access(&["192.0.2.0/24"], &["192.0.2.66"], &[])));
+        let app = router(AppState::try_from_config(config).unwrap());
+        let request = |peer: Option<&str>| {
+            let mut request = Request::builder()
+                .method("POST")
+                .uri("/")
+                .body(Body::from(
+                    r#"{"jsonrpc":"2.0","id":1,"method":"anvil_mine"}"#,
+                ))
+                .unwrap();
+            if let Some(peer) = peer {
+                request
+                    .extensions_mut()
+                    .insert(ConnectInfo(ClientAddr(peer.parse().unwrap())));
+            }
+            request
+        };
+
+        for peer in [Some("192.0.2.66:5000"), Some("198.51.100.1:5000"), None] {
+            let response = app.clone().oneshot(request(peer)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        }
+        let response = app.oneshot(request(Some("192.0.2.7:5000"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,573 @@
+use crate::auth::tokens_match;
+use crate::errors::ProxyError;
+use crate::policy::ActivePolicy;
+use crate::reload::log_policy_diff;
+use crate::server::AppState;
+use axum::Router;
+use axum::body::{Body, Bytes};
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::Response;
+use axum::routing::get;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+use veto_config::{
+    AdminApi, Config, ConfigError, ConfigSource, load_file, resolve_config, save_file,
+};
+
+/// Methods to add to, or remove from, the blocked and allowed lists.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleChange {
+    /// Method names or glob patterns on the blocklist.
+    #[serde(default)]
+    blocked: Vec<String>,
+    /// Method names or glob patterns forwarded in allowlist mode.
+    #[serde(default)]
+    allowed: Vec<String>,
+}
+
+/// Whether a [`RuleChange`] adds or removes methods.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Add,
+    Remove,
+}
+
+impl RuleChange {
+    /// Parse a request body, lowercasing methods like the resolver does.
+    fn parse(body: &[u8]) -> Result<Self, AdminError> {
+        let mut change: Self = serde_json::from_slice(body)
+            .map_err(|error| AdminError::bad_request(format!("invalid rule change: {error}")))?;
+        for method in change.blocked.iter_mut().chain(change.allowed.iter_mut()) {
+            *method = method.trim().to_ascii_lowercase();
+            if method.is_empty() {
+                return Err(AdminError::bad_request("method names must not be empty"));
+            }
+        }
+        if change.blocked.is_empty() && change.allowed.is_empty() {
+            return Err(AdminError::bad_request(
+                "expected at least one method in `blocked` or `allowed`",
+            ));
+        }
+        Ok(change)
+    }
+
+    /// Apply the change to the policy settings of `config`.
+    fn apply(&self, edit: Edit, config: &Config) -> Config {
+        let mut blocked = config.blocked_methods().clone();
+        let mut allowed = config.allowed_methods().clone();
+        match edit {
+            Edit::Add => {
+                blocked.extend(self.blocked.iter().cloned());
+                allowed.extend(self.allowed.iter().cloned());
+            }
+            Edit::Remove => {
+                blocked.retain(|method| !self.blocked.contains(method));
+                allowed.retain(|method| !self.allowed.contains(method));
+            }
+        }
+        config
+            .clone()
+            .with_blocked_methods(blocked)
+            .with_allowed_methods(allowed)
+    }
+
+    /// Write the change to the configuration file behind `source`.
+    ///
+    /// Removed methods that would still be blocked once the file is resolved again (defaults
+    /// and CLI overrides) are added to `unblocked_methods`.
+    fn persist(&self, edit: Edit, source: &ConfigSource) -> Result<(), ConfigError> {
+        let mut file = load_file(source.path())?.unwrap_or_default();
+        match edit {
+            Edit::Add => {
+                add_methods(&mut file.blocked_methods, &self.blocked);
+                remove_methods(&mut file.unblocked_methods, &self.blocked);
+                add_methods(&mut file.allowed_methods, &self.allowed);
+            }
+            Edit::Remove => {
+                remove_methods(&mut file.blocked_methods, &self.blocked);
+                remove_methods(&mut file.allowed_methods, &self.allowed);
+                let resolved = resolve_config(Some(file.clone()), source.overrides().clone())?;
+                let still_blocked: Vec<_> = self
+                    .blocked
+                    .iter()
+                    .filter(|method| resolved.blocked_methods().contains(*method))
+                    .cloned()
+                    .collect();
+                add_methods(&mut file.unblocked_methods, &still_blocked);
+            }
+        }
+        save_file(source.path(), &file)
+    }
+}
+
+fn add_methods(list: &mut Option<Vec<String>>, methods: &[String]) {
+    let list = list.get_or_insert_with(Vec::new);
+    for method in methods {
+        if !list.iter().any(|entry| entry.eq_ignore_ascii_case(method)) {
+            list.push(method.clone());
+        }
+    }
+}
+
+fn remove_methods(list: &mut Option<Vec<String>>, methods: &[String]) {
+    if let Some(entries) = list {
+        entries.retain(|entry| {
+            !methods
+                .iter()
+                .any(|method| entry.eq_ignore_ascii_case(method))
+        });
+        if entries.is_empty() {
+            *list = None;
+        }
+    }
+}
+
+/// Error answered by the admin API as `{"error": message}`.
+#[derive(Debug)]
+struct AdminError {
+    status: StatusCode,
+    message: String,
+}
+
+impl AdminError {
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            message: message.into(),
+        }
+    }
+
+    fn unauthorized() -> Self {
+        Self {
+            status: StatusCode::UNAUTHORIZED,
+            message: "missing or invalid bearer token".into(),
+        }
+    }
+
+    fn persist(error: &impl fmt::Display) -> Self {
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: format!("failed to persist rules: {error}"),
+        }
+    }
+}
+
+/// State of the admin listener.
+#[derive(Debug, Clone)]
+struct Admin {
+    state: AppState,
+    token: Option<Arc<str>>,
+    /// Source whose file receives rule changes, when persistence is enabled.
+    persist: Option<Arc<ConfigSource>>,
+    /// Serializes rule changes, so each is written to the file before the next one is applied.
+    edits: Arc<Mutex<()>>,
+}
+
+impl Admin {
+    /// Check the `Authorization: Bearer` header against the configured token.
+    fn authorize(&self, headers: &HeaderMap) -> Result<(), AdminError> {
+        let Some(token) = &self.token else {
+            return Ok(());
+        };
+        let given = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        if tokens_match(given.as_bytes(), token.as_bytes()) {
+            Ok(())
+        } else {
+            Err(AdminError::unauthorized())
+        }
+    }
+
+    /// Apply a rule change atomically, persisting it first when enabled.
+    ///
+    /// The change is validated against the policy in force, written to the file on a blocking
+    /// thread, and only then applied to the latest policy, so the policy lock is never held
+    /// across file I/O.
+    async fn edit(&self, edit: Edit, body: &[u8]) -> Result<Value, AdminError> {
+        let change = RuleChange::parse(body)?;
+        let _edits = self.edits.lock().await;
+        let compile = |active: &ActivePolicy| {
+            ActivePolicy::new(change.apply(edit, &active.config))
+                .map_err(|error| AdminError::bad_request(error.to_string()))
+        };
+        compile(&self.state.policy.load())?;
+        if let Some(source) = &self.persist {
+            let (change, source) = (change.clone(), Arc::clone(source));
+            tokio::task::spawn_blocking(move || change.persist(edit, &source))
+                .await
+                .map_err(|error| AdminError::persist(&error))?
+                .map_err(|error| AdminError::persist(&error))?;
+        }
+        let swap = self.state.policy.update(compile)?;
+
+        if log_policy_diff(&swap.previous.config, &swap.current.config) {
+            info!(
+                persisted = self.persist.is_some(),
+                "method rules changed through the admin API"
+            );
+            if self.persist.is_none() {
+                warn!(
+                    "admin rule changes are kept in memory only; the next reload of the configuration file discards them"
+                );
+            }
+        }
+        Ok(rules_json(&swap.current.config))
+    }
+}
+
+/// Bind the admin listener and serve the admin API in the background.
+///
+/// Rule changes are written back to the file of `source` when [`AdminApi::persist`] is set.
+pub(crate) async fn serve_admin(
+    settings: &AdminApi,
+    state: AppState,
+    source: Option<ConfigSource>,
+) -> Result<(), ProxyError> {
+    let listener = TcpListener::bind(settings.address)
+        .await
+        .map_err(ProxyError::AdminBind)?;
+    let address = listener.local_addr().map_err(ProxyError::AdminBind)?;
+    info!("serving admin API on http://{address}");
+    if settings.token.is_none() {
+        warn!("admin API has no token; anyone who can reach {address} can change the rules");
+    }
+    if settings.persist && source.is_none() {
+        warn!("admin API persistence needs the configuration file path; changes stay in memory");
+    }
+
+    let admin = Admin {
+        state,
+        token: settings.token.as_deref().map(Arc::from),
+        persist: source.filter(|_| settings.persist).map(Arc::new),
+        edits: Arc::default(),
+    };
+    tokio::spawn(async move {
+        if let Err(error) = axum::serve(listener, admin_router(admin)).await {
+            error!(%error, "admin listener failed");
+        }
+    });
+    Ok(())
+}
+
+fn admin_router(admin: Admin) -> Router {
+    Router::new()
+        .route(
+            "/rules",
+            get(get_rules).post(add_rules).delete(remove_rules),
+        )
+        .route("/config", get(get_config))
+        .with_state(admin)
+}
+
+async fn get_rules(State(admin): State<Admin>, headers: HeaderMap) -> Response {
+    respond(
+        admin
+            .authorize(&headers)
+            .map(|()| rules_json(&admin.state.policy.load().config)),
+    )
+}
+
+async fn add_rules(State(admin): State<Admin>, headers: HeaderMap, body: Bytes) -> Response {
+    respond(match admin.authorize(&headers) {
+        Ok(()) => admin.edit(Edit::Add, &body).await,
+        Err(error) => Err(error),
+    })
+}
+
+async fn remove_rules(State(admin): State<Admin>, headers: HeaderMap, body: Bytes) -> Response {
+    respond(match admin.authorize(&headers) {
+        Ok(()) => admin.edit(Edit::Remove, &body).await,
+        Err(error) => Err(error),
+    })
+}
+
+async fn get_config(State(admin): State<Admin>, headers: HeaderMap) -> Response {
+    respond(
+        admin
+            .authorize(&headers)
+            .map(|()| config_json(&admin.state.policy.load().config)),
+    )
+}
+
+fn respond(result: Result<Value, AdminError>) -> Response {
+    let (status, body) = match result {
+        Ok(body) => (StatusCode::OK, body),
+        Err(error) => (error.status, json!({ "error": error.message })),
+    };
+    let mut response = Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json");
+    if status == StatusCode::UNAUTHORIZED {
+        response = response.header(header::WWW_AUTHENTICATE, "Bearer");
+    }
+    response
+        .body(Body::from(body.to_string()))
+        .expect("valid admin response")
+}
+
+fn sorted(methods: &HashSet<String>) -> Vec<&str> {
+    let mut methods: Vec<_> = methods.iter().map(String::as_str).collect();
+    methods.sort_unstable();
+    methods
+}
+
+fn blocks(blocks: &[impl ToString]) -> Vec<String> {
+    blocks.iter().map(ToString::to_string).collect()
+}
+
+/// Method rules currently in force.
+fn rules_json(config: &Config) -> Value {
+    json!({
+        "mode": config.mode(),
+        "blocked": sorted(config.blocked_methods()),
+        "allowed": sorted(config.allowed_methods()),
+        "param_rules": config.param_rules().iter().map(|rule| rule.display_name()).collect::<Vec<_>>(),
+        "tx_rules": config.tx_rules().iter().map(|rule| rule.name.as_str()).collect::<Vec<_>>(),
+    })
+}
+
+/// Effective configuration, with the admin token left out.
+fn config_json(config: &Config) -> Value {
+    let upstreams: Vec<_> = config
+        .upstreams()
+        .iter()
+        .map(|upstream| json!({ "url": upstream.url.to_string(), "weight": upstream.weight }))
+        .collect();
+    let api_keys: Vec<_> = config
+        .api_keys()
+        .iter()
+        .map(|key| {
+            json!({
+                "name": key.name,
+                "mode": key.mode,
+                "blocked_methods": key.blocked_methods,
+                "allowed_methods": key.allowed_methods,
+                "rate_limits": key.rate_limits,
+            })
+        })
+        .collect();
+    json!({
+        "bind_address": config.bind_address(),
+        "upstreams": upstreams,
+        "balance": config.balance(),
+        "upstream_backoff_ms": config.upstream_backoff().initial().as_millis(),
+        "upstream_max_backoff_ms": config.upstream_backoff().max().as_millis(),
+        "upstream_timeout_ms": config.upstream_timeouts().default_timeout().as_millis(),
+        "upstream_timeouts": config.upstream_timeouts().methods(),
+        "upstream_retries": config.upstream_retries().retries(),
+        "upstream_retry_backoff_ms": config.upstream_retries().backoff().as_millis(),
+        "upstream_retry_max_backoff_ms": config.upstream_retries().max_backoff().as_millis(),
+        "idempotent_methods": config.idempotent_methods(),
+        "health_check": config.health_check().map(|check| json!({
+            "interval_ms": check.interval().as_millis(),
+            "timeout_ms": check.timeout().as_millis(),
+            "max_block_lag": check.max_block_lag(),
+        })),
+        "circuit_breaker": config.circuit_breaker().map(|breaker| json!({
+            "failure_threshold": breaker.failure_threshold(),
+            "open_ms": breaker.open_duration().as_millis(),
+            "half_open_requests": breaker.half_open_requests(),
+        })),
+        "response_cache": config.response_cache().map(|cache| json!({
+            "max_entries": cache.max_entries(),
+            "ttl_ms": cache.ttl().as_millis(),
+            "methods": cache.methods(),
+        })),
+        "mode": config.mode(),
+        "blocked_methods": sorted(config.blocked_methods()),
+        "allowed_methods": sorted(config.allowed_methods()),
+        "max_batch_size": config.max_batch_size(),
+        "max_request_bytes": config.max_request_bytes(),
+        "param_rules": config.param_rules(),
+        "tx_rules": config.tx_rules(),
+        "chain_id": config.chain_id(),
+        "rate_limits": config.rate_limits(),
+        "api_keys": api_keys,
+        "client_access": config.client_access().map(|access| json!({
+            "allowed": blocks(&access.allowed),
+            "denied": blocks(&access.denied),
+            "trusted_proxies": blocks(&access.trusted_proxies),
+        })),
+        "upstream_tls": {
+            "ca_path": config.upstream_tls().ca_path,
+            "client_cert_path": config.upstream_tls().client_cert_path,
+            "client_key_path": config.upstream_tls().client_key_path,
+        },
+        "listener_tls": config.listener_tls().map(|tls| json!({
+            "cert_path": tls.cert_path,
+            "key_path": tls.key_path,
+            "client_ca_path": tls.client_ca_path,
+        })),
+        "metrics_address": config.metrics_address(),
+        "audit_log": config.audit_log().map(|audit| json!({
+            "path": audit.path,
+            "max_bytes": audit.max_bytes,
+            "max_files": audit.max_files,
+        })),
+        "admin": config.admin().map(|admin| json!({
+            "address": admin.address,
+            "token_required": admin.token.is_some(),
+            "persist": admin.persist,
+        })),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jsonrpc::JsonRpcRequest;
+    use crate::rate_limit::ClientKey;
+    use http::Request;
+    use http_body_util::BodyExt;
+    use std::fs;
+    use tower::util::ServiceExt;
+    use veto_config::Overrides;
+
+    fn admin(state: AppState, token: Option<&str>, persist: Option<ConfigSource>) -> Router {
+        admin_router(Admin {
+            state,
+            token: token.map(Arc::from),
+            persist: persist.map(Arc::new),
+            edits: Arc::default(),
+        })
+    }
+
+    fn blocks(state: &AppState, method: &str) -> bool {
+        state
+            .check_request(
+                &ClientKey::from_addr(None),
+                &JsonRpcRequest {
+                    method: method.into(),
+                    params: Value::Null,
+                    id: Value::Null,
+                },
+            )
+            .is_some()
+    }
+
+    async fn send(router: &Router, request: Request<Body>) -> (StatusCode, Value) {
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    fn rules(method: http::Method, body: &str) -> Request<Body> {
+        Request::builder()
+            .method(method)
+            .uri("/rules")
+            .header("authorization", "Bearer secret")
+            .body(Body::from(body.to_owned()))
+            .unwrap()
+    }
+
+    fn state() -> AppState {
+        let config =
+            resolve_config(None, Overrides::default().with_use_defaults(Some(false))).unwrap();
+        AppState::try_from_config(config).unwrap()
+    }
+
+    #[tokio::test]
+    async fn requires_the_bearer_token() {
+        let router = admin(state(), Some("secret"), None);
+        let (status, body) = send(
+            &router,
+            Request::get("/config").body(Body::empty()).unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["error"], "missing or invalid bearer token");
+
+        let request = Request::get("/config")
+            .header("authorization", "Bearer secret")
+            .body(Body::empty())
+            .unwrap();
+        let (status, body) = send(&router, request).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["mode"], "blocklist");
+        assert!(body.get("admin").is_some());
+    }
+
+    #[tokio::test]
+    async fn adds_and_removes_rules() {
+        let state = state();
+        let router = admin(state.clone(), Some("secret"), None);
+
+        let (status, body) = send(
+            &router,
+            rules(http::Method::POST, r#"{"blocked":["ETH_sign","debug_*"]}"#),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["blocked"], json!(["debug_*", "eth_sign"]));
+        assert!(blocks(&state, "eth_sign"));
+        assert!(blocks(&state, "debug_traceCall"));
+
+        let (status, body) = send(
+            &router,
+            rules(http::Method::DELETE, r#"{"blocked":["debug_*"]}"#),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["blocked"], json!(["eth_sign"]));
+        assert!(!blocks(&state, "debug_traceCall"));
+
+        let (status, _) = send(
+            &router,
+            rules(http::Method::POST, r#"{"blocked":["debug_["]}"#),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(blocks(&state, "eth_sign"));
+        assert_eq!(state.policy.load().config.blocked_methods().len(), 1);
+
+        let (status, _) = send(&router, rules(http::Method::POST, r#"{"blocked":[]}"#)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn persists_rules_to_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".veto.toml");
+        fs::write(&path, "blocked_methods = [\"eth_sign\"]\n").unwrap();
+        let source = ConfigSource::new(path.clone(), Overrides::default());
+        let state = AppState::try_from_config(source.load().unwrap()).unwrap();
+        let router = admin(state.clone(), Some("secret"), Some(source));
+
+        let (status, _) = send(
+            &router,
+            rules(
+                http::Method::DELETE,
+                r#"{"blocked":["eth_sign","anvil_setCode"]}"#,
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(!blocks(&state, "anvil_setCode"));
+
+        let file = load_file(&path).unwrap().unwrap();
+        assert_eq!(file.blocked_methods, None);
+        assert_eq!(file.unblocked_methods, Some(vec!["anvil_setcode".into()]));
+
+        let (status, _) = send(
+            &router,
+            rules(http::Method::POST, r#"{"blocked":["anvil_setCode"]}"#),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let file = load_file(&path).unwrap().unwrap();
+        assert_eq!(file.blocked_methods, Some(vec!["anvil_setcode".into()]));
+        assert_eq!(file.unblocked_methods, None);
+    }
+}
@@ -0,0 +1,366 @@
+use crate::errors::ProxyError;
+use axum::http::StatusCode;
+use chrono::{SecondsFormat, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{Receiver, SyncSender, TrySendError, sync_channel};
+use std::thread;
+use std::time::Duration;
+use tracing::{error, warn};
+use veto_config::AuditLog;
+
+/// Audit lines buffered between the request path and the writer thread.
+const AUDIT_QUEUE: usize = 8_192;
+
+/// How the proxy handled one JSON-RPC call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Decision {
+    /// Allowed by the policy and sent upstream.
+    Forwarded,
+    /// Allowed by the policy and answered from the response cache.
+    Cached,
+    /// Refused by the named rule.
+    Blocked(String),
+    /// Rejected as malformed before the policy ran.
+    Invalid,
+    /// Refused for lacking a valid API key before the payload was read.
+    Unauthorized,
+}
+
+/// One call within a request, as recorded in the audit log.
+#[derive(Debug)]
+pub(crate) struct AuditCall {
+    /// Method name, absent when the payload could not be parsed.
+    pub(crate) method: Option<String>,
+    /// JSON-RPC request id.
+    pub(crate) id: Value,
+    /// What the proxy did with the call.
+    pub(crate) decision: Decision,
+}
+
+/// Everything learned while handling one HTTP request.
+#[derive(Debug, Default)]
+pub(crate) struct AuditTrail {
+    /// Whether the payload was a batch.
+    pub(crate) batch: bool,
+    /// Calls in payload order.
+    pub(crate) calls: Vec<AuditCall>,
+    /// Upstream that answered, if the request was forwarded.
+    pub(crate) upstream: Option<String>,
+    /// Name of the API key the client presented.
+    pub(crate) api_key: Option<Arc<str>>,
+}
+
+impl AuditTrail {
+    /// Record a call that failed to parse.
+    pub(crate) fn invalid(&mut self, id: Value) {
+        self.calls.push(AuditCall {
+            method: None,
+            id,
+            decision: Decision::Invalid,
+        });
+    }
+
+    /// Record a request refused for lacking a valid API key.
+    pub(crate) fn unauthorized(&mut self) {
+        self.calls.push(AuditCall {
+            method: None,
+            id: Value::Null,
+            decision: Decision::Unauthorized,
+        });
+    }
+
+    /// Methods of the calls sent upstream.
+    pub(crate) fn forwarded_methods(&self) -> impl Iterator<Item = &str> {
+        self.calls
+            .iter()
+            .filter(|call| call.decision == Decision::Forwarded)
+            .filter_map(|call| call.method.as_deref())
+    }
+
+    /// Method label for the whole request: `batch` for batches, `None` when nothing parsed.
+    pub(crate) fn method(&self) -> Option<&str> {
+        if self.batch {
+            return Some("batch");
+        }
+        self.calls.first().and_then(|call| call.method.as_deref())
+    }
+}
+
+/// A line of the audit log.
+#[derive(Debug, Serialize)]
+struct AuditRecord<'a> {
+    timestamp: &'a str,
+    client: Option<SocketAddr>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    api_key: Option<&'a str>,
+    method: Option<&'a str>,
+    decision: &'static str,
+    rule: Option<&'a str>,
+    id: &'a Value,
+    upstream: Option<&'a str>,
+    status: u16,
+    latency_ms: f64,
+}
+
+/// Non-blocking audit sink backed by a writer thread.
+///
+/// Lines are queued on the request path and written by a dedicated thread, so slow disks never
+/// delay requests. When the queue is full lines are dropped and the loss is logged.
+#[derive(Debug)]
+pub(crate) struct AuditSink {
+    sender: SyncSender<String>,
+    dropped: AtomicU64,
+}
+
+impl AuditSink {
+    /// Open the audit file and start the writer thread.
+    pub(crate) fn spawn(settings: &AuditLog) -> Result<Self, ProxyError> {
+        let file = RotatingFile::open(settings).map_err(|source| ProxyError::AuditLog {
+            path: settings.path.clone(),
+            source,
+        })?;
+        let (sender, receiver) = sync_channel(AUDIT_QUEUE);
+        thread::Builder::new()
+            .name("veto-audit".into())
+            .spawn(move || write_lines(receiver, file))
+            .map_err(|source| ProxyError::AuditLog {
+                path: settings.path.clone(),
+                source,
+            })?;
+
+        Ok(Self {
+            sender,
+            dropped: AtomicU64::new(0),
+        })
+    }
+
+    /// Queue one line per call of a handled request.
+    pub(crate) fn record(
+        &self,
+        client: Option<SocketAddr>,
+        trail: &AuditTrail,
+        status: StatusCode,
+        latency: Duration,
+    ) {
+        let timestamp = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+        for call in &trail.calls {
+            let (decision, rule) = match &call.decision {
+                Decision::Forwarded => ("forwarded", None),
+                Decision::Cached => ("cached", None),
+                Decision::Blocked(rule) => ("blocked", Some(rule.as_str())),
+                Decision::Invalid => ("invalid", None),
+                Decision::Unauthorized => ("unauthorized", Some("api_key")),
+            };
+            let record = AuditRecord {
+                timestamp: &timestamp,
+                client,
+                api_key: trail.api_key.as_deref(),
+                method: call.method.as_deref(),
+                decision,
+                rule,
+                id: &call.id,
+                upstream: trail
+                    .upstream
+                    .as_deref()
+                    .filter(|_| call.decision == Decision::Forwarded),
+                status: status.as_u16(),
+                latency_ms: latency.as_secs_f64() * 1_000.0,
+            };
+            let line = serde_json::to_string(&record).expect("audit record serializes");
+            self.send(line);
+        }
+    }
+
+    fn send(&self, line: String) {
+        match self.sender.try_send(line) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                if dropped == 1 || dropped.is_multiple_of(1_000) {
+                    warn!(dropped, "audit log queue full; dropping audit lines");
+                }
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                error!("audit log writer stopped; dropping audit line");
+            }
+        }
+    }
+}
+
+/// Writer thread: append queued lines, flushing whenever the queue drains.
+fn write_lines(receiver: Receiver<String>, mut file: RotatingFile) {
+    while let Ok(line) = receiver.recv() {
+        let mut next = Some(line);
+        while let Some(line) = next {
+            if let Err(error) = file.write_line(&line) {
+                error!(path = %file.path.display(), %error, "failed to write audit log");
+            }
+            next = receiver.try_recv().ok();
+        }
+        if let Err(error) = file.flush() {
+            error!(path = %file.path.display(), %error, "failed to flush audit log");
+        }
+    }
+}
+
+/// Append-only file rotated by size.
+#[derive(Debug)]
+struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    writer: BufWriter<File>,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(settings: &AuditLog) -> io::Result<Self> {
+        let (writer, size) = open_append(&settings.path)?;
+        Ok(Self {
+            path: settings.path.clone(),
+            max_bytes: settings.max_bytes,
+            max_files: settings.max_files,
+            writer,
+            size,
+        })
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let len = line.len() as u64 + 1;
+        if self.size > 0 && self.size + len > self.max_bytes {
+            self.rotate()?;
+        }
+        self.writer.write_all(line.as_bytes())?;
+        self.writer.write_all(b"\n")?;
+        self.size += len;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Shift `<path>.N` to `<path>.N+1`, dropping the oldest, and start a fresh file.
+    fn rotate(&mut self) -> io::Result<()> {
+        self.writer.flush()?;
+        for index in (1..self.max_files).rev() {
+            match fs::rename(
+                rotated_path(&self.path, index),
+                rotated_path(&self.path, index + 1),
+            ) {
+                Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+                _ => {}
+            }
+        }
+        fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        (self.writer, self.size) = open_append(&self.path)?;
+        Ok(())
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<(BufWriter<File>, u64)> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let size = file.metadata()?.len();
+    Ok((BufWriter::new(file), size))
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(format!(".{index}"));
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    fn settings(dir: &Path, max_bytes: u64, max_files: usize) -> AuditLog {
+        AuditLog {
+            path: dir.join("audit.log"),
+            max_bytes,
+            max_files,
+        }
+    }
+
+    #[rstest]
+    fn rotates_by_size_and_keeps_max_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = settings(dir.path(), 10, 2);
+        let mut file = RotatingFile::open(&settings).unwrap();
+        for line in ["first", "second", "third", "fourth"] {
+            file.write_line(line).unwrap();
+        }
+        file.flush().unwrap();
+
+        let read = |path: PathBuf| fs::read_to_string(path).unwrap();
+        assert_eq!(read(settings.path.clone()), "fourth\n");
+        assert_eq!(read(rotated_path(&settings.path, 1)), "third\n");
+        assert_eq!(read(rotated_path(&settings.path, 2)), "second\n");
+        assert!(!rotated_path(&settings.path, 3).exists());
+    }
+
+    #[tokio::test]
+    async fn writes_one_line_per_call() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = settings(dir.path(), 1 << 20, 1);
+        let sink = AuditSink::spawn(&settings).unwrap();
+
+        let trail = AuditTrail {
+            batch: true,
+            calls: vec![
+                AuditCall {
+                    method: Some("eth_call".into()),
+                    id: json!(1),
+                    decision: Decision::Forwarded,
+                },
+                AuditCall {
+                    method: Some("anvil_setCode".into()),
+                    id: json!("a"),
+                    decision: Decision::Blocked("anvil_setcode".into()),
+                },
+            ],
+            upstream: Some("http://127.0.0.1:8545/".into()),
+            api_key: Some(Arc::from("ci")),
+        };
+        sink.record(
+            Some("10.0.0.7:40000".parse().unwrap()),
+            &trail,
+            StatusCode::OK,
+            Duration::from_millis(3),
+        );
+        drop(sink);
+
+        let mut contents = String::new();
+        for _ in 0..100 {
+            contents = fs::read_to_string(&settings.path).unwrap();
+            if contents.lines().count() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let lines: Vec<Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["decision"], "forwarded");
+        assert_eq!(lines[0]["upstream"], "http://127.0.0.1:8545/");
+        assert_eq!(lines[0]["api_key"], "ci");
+        assert_eq!(lines[1]["method"], "anvil_setCode");
+        assert_eq!(lines[1]["rule"], "anvil_setcode");
+        assert_eq!(lines[1]["upstream"], Value::Null);
+        assert_eq!(lines[1]["client"], "10.0.0.7:40000");
+        assert_eq!(lines[1]["status"], 200);
+        assert!(lines[1]["timestamp"].as_str().unwrap().ends_with('Z'));
+    }
+}
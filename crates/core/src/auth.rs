@@ -0,0 +1,128 @@
+use crate::rate_limit::ClientKey;
+use crate::server::AppState;
+use axum::http::Uri;
+use axum::http::request::Parts;
+use axum::http::uri::PathAndQuery;
+use std::net::SocketAddr;
+
+/// Header carrying the API key.
+const API_KEY_HEADER: &str = "x-api-key";
+
+/// Path prefix carrying the API key, as in `/key/<token>`.
+const API_KEY_PATH: &str = "/key/";
+
+/// Query parameter carrying the API key.
+const API_KEY_QUERY: &str = "api_key";
+
+/// Identify the client of a request, returning `None` when it must be refused.
+///
+/// Without configured API keys every client is keyed by its address. Otherwise the key is read
+/// from the `x-api-key` header, a `/key/<token>` path prefix, or the `api_key` query parameter,
+/// in that order, and removed from the request so it never reaches the upstream.
+pub(crate) fn authenticate(
+    state: &AppState,
+    parts: &mut Parts,
+    address: Option<SocketAddr>,
+) -> Option<ClientKey> {
+    let active = state.policy.load();
+    if active.keys.is_empty() {
+        return Some(ClientKey::from_addr(address));
+    }
+    let token = take_api_key(parts)?;
+    active
+        .find_key(&token)
+        .map(|key| ClientKey::ApiKey(key.name.clone()))
+}
+
+/// Remove every API key from the request, returning the first one found.
+fn take_api_key(parts: &mut Parts) -> Option<String> {
+    let header = parts
+        .headers
+        .remove(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok().map(str::to_owned));
+
+    let (path_token, path) = match parts.uri.path().strip_prefix(API_KEY_PATH) {
+        Some(rest) => {
+            let (token, rest) = rest.split_once('/').unwrap_or((rest, ""));
+            (Some(token.to_owned()), format!("/{rest}"))
+        }
+        None => (None, parts.uri.path().to_owned()),
+    };
+
+    let mut query_token = None;
+    let query = parts.uri.query().map(|query| {
+        query
+            .split('&')
+            .filter(|pair| match pair.split_once('=') {
+                Some((API_KEY_QUERY, value)) => {
+                    query_token.get_or_insert_with(|| value.to_owned());
+                    false
+                }
+                _ => *pair != API_KEY_QUERY,
+            })
+            .collect::<Vec<_>>()
+            .join("&")
+    });
+
+    if path_token.is_some() || query_token.is_some() {
+        let path_and_query = match query.filter(|query| !query.is_empty()) {
+            Some(query) => format!("{path}?{query}"),
+            None => path,
+        };
+        let mut uri = parts.uri.clone().into_parts();
+        uri.path_and_query =
+            Some(PathAndQuery::try_from(path_and_query).expect("stripped path remains valid"));
+        parts.uri = Uri::from_parts(uri).expect("stripped uri remains valid");
+    }
+
+    header.or(path_token).or(query_token)
+}
+
+/// Compare tokens without exiting early on the first mismatching byte.
+pub(crate) fn tokens_match(given: &[u8], expected: &[u8]) -> bool {
+    given.len() == expected.len()
+        && given
+            .iter()
+            .zip(expected)
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::header("/", Some("secret"), Some("secret"), "/")]
+    #[case::path("/key/secret", None, Some("secret"), "/")]
+    #[case::path_with_rest("/key/secret/rpc?x=1", None, Some("secret"), "/rpc?x=1")]
+    #[case::query("/rpc?api_key=secret&x=1", None, Some("secret"), "/rpc?x=1")]
+    #[case::query_only("/?api_key=secret", None, Some("secret"), "/")]
+    #[case::header_wins("/key/path?api_key=query", Some("header"), Some("header"), "/")]
+    #[case::missing("/rpc?x=1", None, None, "/rpc?x=1")]
+    fn api_keys_are_taken_from_the_request(
+        #[case] uri: &str,
+        #[case] header: Option<&str>,
+        #[case] expected: Option<&str>,
+        #[case] forwarded: &str,
+    ) {
+        let mut request = Request::builder().uri(uri);
+        if let Some(header) = header {
+            request = request.header(API_KEY_HEADER, header);
+        }
+        let (mut parts, ()) = request.body(()).unwrap().into_parts();
+
+        assert_eq!(take_api_key(&mut parts).as_deref(), expected);
+        assert_eq!(parts.uri.to_string(), forwarded);
+        assert!(!parts.headers.contains_key(API_KEY_HEADER));
+    }
+
+    #[rstest]
+    fn tokens_compare_whole_values() {
+        assert!(tokens_match(b"secret", b"secret"));
+        assert!(!tokens_match(b"secreT", b"secret"));
+        assert!(!tokens_match(b"secret-longer", b"secret"));
+    }
+}
@@ -0,0 +1,206 @@
+use crate::upstream::Upstream;
+use std::fmt;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use veto_config::BalanceStrategy;
+
+/// Strategy that orders the upstreams in rotation for each request.
+///
+/// The first upstream is tried first; the rest are used, in order, if it fails.
+pub(crate) trait Balancer: fmt::Debug + Send + Sync {
+    /// Reorder `candidates`, which are in configured order and never empty.
+    fn order(&self, candidates: &mut Vec<&Upstream>);
+}
+
+/// Build the [`Balancer`] for a [`BalanceStrategy`].
+pub(crate) fn balancer(strategy: BalanceStrategy, upstreams: &[Upstream]) -> Box<dyn Balancer> {
+    match strategy {
+        BalanceStrategy::Failover => Box::new(Failover),
+        BalanceStrategy::RoundRobin => Box::new(RoundRobin::default()),
+        BalanceStrategy::Weighted => Box::new(Weighted::new(upstreams.len())),
+        BalanceStrategy::LeastOutstanding => Box::new(LeastOutstanding),
+        BalanceStrategy::EwmaLatency => Box::new(EwmaLatency),
+    }
+}
+
+/// Keep the configured order.
+#[derive(Debug)]
+struct Failover;
+
+impl Balancer for Failover {
+    fn order(&self, _candidates: &mut Vec<&Upstream>) {}
+}
+
+/// Start each request one upstream further along.
+#[derive(Debug, Default)]
+struct RoundRobin {
+    next: AtomicUsize,
+}
+
+impl Balancer for RoundRobin {
+    fn order(&self, candidates: &mut Vec<&Upstream>) {
+        let start = self.next.fetch_add(1, Ordering::Relaxed) % candidates.len();
+        candidates.rotate_left(start);
+    }
+}
+
+/// Smooth weighted round-robin: each upstream is picked in proportion to its weight, with picks
+/// interleaved rather than bunched.
+#[derive(Debug)]
+struct Weighted {
+    /// Running score per upstream, indexed by [`Upstream::index`].
+    current: Mutex<Vec<i64>>,
+}
+
+impl Weighted {
+    fn new(upstreams: usize) -> Self {
+        Self {
+            current: Mutex::new(vec![0; upstreams]),
+        }
+    }
+}
+
+impl Balancer for Weighted {
+    fn order(&self, candidates: &mut Vec<&Upstream>) {
+        let mut current = self
+            .current
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let total: i64 = candidates
+            .iter()
+            .map(|upstream| i64::from(upstream.weight()))
+            .sum();
+
+        let mut best = 0;
+        for (position, upstream) in candidates.iter().enumerate() {
+            current[upstream.index()] += i64::from(upstream.weight());
+            if current[upstream.index()] > current[candidates[best].index()] {
+                best = position;
+            }
+        }
+        current[candidates[best].index()] -= total;
+        drop(current);
+
+        let chosen = candidates.remove(best);
+        candidates.insert(0, chosen);
+    }
+}
+
+/// Prefer the upstream with the fewest requests in flight; ties keep the configured order.
+#[derive(Debug)]
+struct LeastOutstanding;
+
+impl Balancer for LeastOutstanding {
+    fn order(&self, candidates: &mut Vec<&Upstream>) {
+        candidates.sort_by_key(|upstream| upstream.in_flight());
+    }
+}
+
+/// Prefer the upstream with the lowest expected latency.
+///
+/// The moving average is scaled by the requests already in flight so a fast upstream does not
+/// absorb every request under load. Upstreams without a measurement are tried first.
+#[derive(Debug)]
+struct EwmaLatency;
+
+impl Balancer for EwmaLatency {
+    fn order(&self, candidates: &mut Vec<&Upstream>) {
+        candidates.sort_by_key(|upstream| {
+            upstream
+                .ewma_latency()
+                .unwrap_or(Duration::ZERO)
+                .saturating_mul(
+                    u32::try_from(upstream.in_flight())
+                        .unwrap_or(u32::MAX)
+                        .saturating_add(1),
+                )
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use veto_config::{Backoff, UpstreamTarget};
+
+    fn upstreams(weights: &[u32]) -> Vec<Upstream> {
+        weights
+            .iter()
+            .enumerate()
+            .map(|(index, weight)| {
+                let target = UpstreamTarget {
+                    url: format!("http://127.0.0.1:{}", index + 1).parse().unwrap(),
+                    weight: *weight,
+                };
+                Upstream::new(index, &target, Backoff::default())
+            })
+            .collect()
+    }
+
+    /// Indexes of the first upstream picked over `rounds` requests.
+    fn picks(balancer: &dyn Balancer, upstreams: &[Upstream], rounds: usize) -> Vec<usize> {
+        (0..rounds)
+            .map(|_| {
+                let mut candidates: Vec<_> = upstreams.iter().collect();
+                balancer.order(&mut candidates);
+                assert_eq!(candidates.len(), upstreams.len());
+                candidates[0].index()
+            })
+            .collect()
+    }
+
+    #[rstest]
+    fn failover_keeps_configured_order() {
+        let upstreams = upstreams(&[1, 1]);
+        let balancer = balancer(BalanceStrategy::Failover, &upstreams);
+        assert_eq!(picks(balancer.as_ref(), &upstreams, 3), [0, 0, 0]);
+    }
+
+    #[rstest]
+    fn round_robin_rotates() {
+        let upstreams = upstreams(&[1, 1, 1]);
+        let balancer = balancer(BalanceStrategy::RoundRobin, &upstreams);
+        assert_eq!(picks(balancer.as_ref(), &upstreams, 4), [0, 1, 2, 0]);
+    }
+
+    #[rstest]
+    fn weighted_picks_in_proportion_and_interleaves() {
+        let upstreams = upstreams(&[5, 1, 1]);
+        let balancer = balancer(BalanceStrategy::Weighted, &upstreams);
+        assert_eq!(
+            picks(balancer.as_ref(), &upstreams, 7),
+            [0, 0, 1, 0, 2, 0, 0]
+        );
+    }
+
+    #[rstest]
+    fn least_outstanding_prefers_idle_upstreams() {
+        let upstreams = upstreams(&[1, 1, 1]);
+        let balancer = balancer(BalanceStrategy::LeastOutstanding, &upstreams);
+        let _first = upstreams[0].begin();
+        let _second = upstreams[1].begin();
+        assert_eq!(picks(balancer.as_ref(), &upstreams, 1), [2]);
+    }
+
+    #[rstest]
+    fn ewma_latency_prefers_fast_and_unmeasured_upstreams() {
+        let upstreams = upstreams(&[1, 1, 1]);
+        let balancer = balancer(BalanceStrategy::EwmaLatency, &upstreams);
+        upstreams[0].record_latency(Duration::from_millis(50));
+        upstreams[1].record_latency(Duration::from_millis(5));
+        assert_eq!(picks(balancer.as_ref(), &upstreams, 1), [2]);
+
+        upstreams[2].record_latency(Duration::from_millis(20));
+        assert_eq!(picks(balancer.as_ref(), &upstreams, 1), [1]);
+
+        let _busy = [
+            upstreams[1].begin(),
+            upstreams[1].begin(),
+            upstreams[1].begin(),
+            upstreams[1].begin(),
+        ];
+        assert_eq!(picks(balancer.as_ref(), &upstreams, 1), [2]);
+    }
+}
@@ -0,0 +1,458 @@
+use crate::metrics::Metrics;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Instant;
+use tracing::info;
+use veto_config::{CacheRule, ResponseCache};
+
+/// Header telling whether a cacheable call was answered from the response cache.
+pub(crate) const X_CACHE: &str = "x-cache";
+
+/// Calls that rewind, reorganize, or rewrite the chain; forwarding one clears the cache.
+///
+/// Account setters change state in place without a new block hash, and mining or moving the
+/// clock changes which block `finalized` names.
+const CHAIN_RESET_METHODS: &[&str] = &[
+    "anvil_increaseTime",
+    "anvil_loadState",
+    "anvil_mine",
+    "anvil_mine_detailed",
+    "anvil_reorg",
+    "anvil_reset",
+    "anvil_revert",
+    "anvil_rollback",
+    "anvil_setBalance",
+    "anvil_setChainId",
+    "anvil_setCode",
+    "anvil_setNextBlockTimestamp",
+    "anvil_setNonce",
+    "anvil_setStorageAt",
+    "anvil_setTime",
+    "evm_increaseTime",
+    "evm_mine",
+    "evm_mine_detailed",
+    "evm_revert",
+    "evm_setNextBlockTimestamp",
+    "evm_setTime",
+    "hardhat_mine",
+    "hardhat_reset",
+    "hardhat_setBalance",
+    "hardhat_setCode",
+    "hardhat_setNextBlockBaseFeePerGas",
+    "hardhat_setNonce",
+    "hardhat_setStorageAt",
+];
+
+/// Block tag whose block can no longer change.
+const FINALIZED: &str = "finalized";
+
+/// Whether a cacheable call was answered from the cache, as reported in [`X_CACHE`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CacheStatus {
+    /// Answered from the cache without reaching the upstream.
+    Hit,
+    /// Forwarded upstream, and cached if the answer allows.
+    Miss,
+}
+
+impl CacheStatus {
+    /// Value of the [`X_CACHE`] header.
+    pub(crate) const fn as_str(self) -> &'static str {
+        match self {
+            Self::Hit => "HIT",
+            Self::Miss => "MISS",
+        }
+    }
+}
+
+/// Key of a cached call: the method and its params in canonical form.
+///
+/// Object keys are sorted and hex strings lowercased, so equivalent calls share an entry.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct CacheKey(Arc<str>);
+
+impl CacheKey {
+    fn new(method: &str, params: &Value) -> Self {
+        let mut key = String::from(method);
+        key.push('\n');
+        match params {
+            Value::Null => key.push_str("[]"),
+            params => write_canonical(params, &mut key),
+        }
+        Self(key.into())
+    }
+}
+
+/// In-memory LRU cache of upstream results, following the configured [`ResponseCache`].
+#[derive(Debug)]
+pub(crate) struct Cache {
+    settings: ResponseCache,
+    entries: Mutex<Lru>,
+}
+
+impl Cache {
+    pub(crate) fn new(settings: ResponseCache) -> Self {
+        Self {
+            settings,
+            entries: Mutex::new(Lru::default()),
+        }
+    }
+
+    /// Key of a call whose answer may be cached, or `None` when its [`CacheRule`] rules it out.
+    pub(crate) fn key(&self, method: &str, params: &Value) -> Option<CacheKey> {
+        let cacheable = match self.settings.rule(method) {
+            CacheRule::Always => true,
+            CacheRule::BlockParam(position) => {
+                param(params, position).is_some_and(names_immutable_block)
+            }
+            CacheRule::Never => false,
+        };
+        cacheable.then(|| CacheKey::new(method, params))
+    }
+
+    /// Look a call up, returning its key and any cached result, or `None` when it is not
+    /// cacheable. The lookup is counted in `metrics`.
+    pub(crate) fn lookup(
+        &self,
+        method: &str,
+        params: &Value,
+        metrics: Option<&Metrics>,
+    ) -> Option<(CacheKey, Option<Arc<Value>>)> {
+        let key = self.key(method, params)?;
+        let cached = self.get(&key);
+        if let Some(metrics) = metrics {
+            let status = if cached.is_some() {
+                CacheStatus::Hit
+            } else {
+                CacheStatus::Miss
+            };
+            metrics.record_cache_lookup(method, status);
+        }
+        Some((key, cached))
+    }
+
+    /// Cached result for `key`, unless missing or expired.
+    pub(crate) fn get(&self, key: &CacheKey) -> Option<Arc<Value>> {
+        self.lock().get(key, Instant::now())
+    }
+
+    /// Cache the result of a JSON-RPC `response`.
+    ///
+    /// Errors, `null` results, and objects with a `null` `blockHash` (such as a pending
+    /// transaction) are not cached, since a later call may get a different answer.
+    pub(crate) fn insert(&self, key: CacheKey, response: &Value) {
+        if response.get("error").is_some() {
+            return;
+        }
+        let Some(result) = response.get("result").filter(|result| is_final(result)) else {
+            return;
+        };
+        let expires = Instant::now() + self.settings.ttl();
+        self.lock().insert(
+            key,
+            Arc::new(result.clone()),
+            expires,
+            self.settings.max_entries(),
+        );
+    }
+
+    /// Clear the cache after a forwarded call that rewinds or rewrites the chain.
+    pub(crate) fn observe(&self, method: &str) {
+        if CHAIN_RESET_METHODS
+            .iter()
+            .any(|reset| reset.eq_ignore_ascii_case(method))
+        {
+            let mut entries = self.lock();
+            info!(%method, entries = entries.len(), "chain reset; clearing response cache");
+            entries.clear();
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Lru> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Cached results indexed by key and by last use.
+#[derive(Debug, Default)]
+struct Lru {
+    entries: HashMap<CacheKey, Entry>,
+    /// Keys by the tick of their last use, oldest first.
+    order: BTreeMap<u64, CacheKey>,
+    tick: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    result: Arc<Value>,
+    expires: Instant,
+    used: u64,
+}
+
+impl Lru {
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn get(&mut self, key: &CacheKey, now: Instant) -> Option<Arc<Value>> {
+        let entry = self.entries.get_mut(key)?;
+        if entry.expires <= now {
+            let used = entry.used;
+            self.entries.remove(key);
+            self.order.remove(&used);
+            return None;
+        }
+        self.tick += 1;
+        self.order.remove(&entry.used);
+        self.order.insert(self.tick, key.clone());
+        entry.used = self.tick;
+        Some(Arc::clone(&entry.result))
+    }
+
+    fn insert(&mut self, key: CacheKey, result: Arc<Value>, expires: Instant, max: usize) {
+        self.tick += 1;
+        let entry = Entry {
+            result,
+            expires,
+            used: self.tick,
+        };
+        if let Some(previous) = self.entries.insert(key.clone(), entry) {
+            self.order.remove(&previous.used);
+        }
+        self.order.insert(self.tick, key);
+        while self.entries.len() > max {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+}
+
+/// JSON-RPC response answering a call from the cache.
+#[derive(Debug, Serialize)]
+pub(crate) struct CachedResponse<'a> {
+    jsonrpc: &'static str,
+    id: &'a Value,
+    result: &'a Value,
+}
+
+impl<'a> CachedResponse<'a> {
+    pub(crate) const fn new(id: &'a Value, result: &'a Value) -> Self {
+        Self {
+            jsonrpc: "2.0",
+            id,
+            result,
+        }
+    }
+}
+
+fn param(params: &Value, position: usize) -> Option<&Value> {
+    match params {
+        Value::Array(params) => params.get(position),
+        _ => None,
+    }
+}
+
+/// Whether a block parameter names a block by hash (also as an EIP-1898 object) or is
+/// `finalized`. Other tags and block numbers may still be reorganized away.
+fn names_immutable_block(block: &Value) -> bool {
+    match block {
+        Value::String(tag) => tag == FINALIZED || is_block_hash(tag),
+        Value::Object(object) => {
+            object.get("blockHash").is_some()
+                || object.get("blockNumber").and_then(Value::as_str) == Some(FINALIZED)
+        }
+        _ => false,
+    }
+}
+
+fn is_block_hash(value: &str) -> bool {
+    value.len() == 66
+        && value.starts_with("0x")
+        && value[2..].bytes().all(|byte| byte.is_ascii_hexdigit())
+}
+
+fn is_final(result: &Value) -> bool {
+    match result {
+        Value::Null => false,
+        Value::Object(object) => !object.get("blockHash").is_some_and(Value::is_null),
+        _ => true,
+    }
+}
+
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::String(string) if string.starts_with("0x") || string.starts_with("0X") => {
+            let _ = write!(out, "{}", Value::String(string.to_ascii_lowercase()));
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        Value::Object(object) => {
+            let mut fields: Vec<_> = object.iter().collect();
+            fields.sort_unstable_by_key(|(name, _)| *name);
+            out.push('{');
+            for (index, (name, item)) in fields.into_iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                let _ = write!(out, "{}:", Value::String(name.clone()));
+                write_canonical(item, out);
+            }
+            out.push('}');
+        }
+        other => {
+            let _ = write!(out, "{other}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+    use std::time::Duration;
+    use veto_config::default_cache_rules;
+
+    const HASH: &str = "0x88df016429689c079f3b2f6ad39fa052532c56795b733da78a91ebe6a713944b";
+
+    fn cache(max_entries: usize, ttl: Duration) -> Cache {
+        Cache::new(ResponseCache::new(max_entries, ttl, default_cache_rules()).unwrap())
+    }
+
+    fn result(value: Value) -> Value {
+        json!({"jsonrpc": "2.0", "id": 1, "result": value})
+    }
+
+    #[rstest]
+    #[case::chain_id("eth_chainId", json!([]), true)]
+    #[case::block_by_hash("eth_getBlockByHash", json!([HASH, false]), true)]
+    #[case::finalized("eth_getBlockByNumber", json!(["finalized", false]), true)]
+    #[case::latest("eth_getBlockByNumber", json!(["latest", false]), false)]
+    #[case::number("eth_getBalance", json!(["0x1", "0x10"]), false)]
+    #[case::missing_block("eth_getBalance", json!(["0x1"]), false)]
+    #[case::eip_1898("eth_call", json!([{}, {"blockHash": HASH}]), true)]
+    #[case::eip_1898_finalized("eth_getCode", json!(["0x1", {"blockNumber": "finalized"}]), true)]
+    #[case::unlisted("eth_blockNumber", json!([]), false)]
+    fn block_params_decide_cacheability(
+        #[case] method: &str,
+        #[case] params: Value,
+        #[case] cacheable: bool,
+    ) {
+        let cache = cache(10, Duration::from_secs(60));
+        assert_eq!(cache.key(method, &params).is_some(), cacheable);
+    }
+
+    #[rstest]
+    fn keys_are_canonical() {
+        let cache = cache(10, Duration::from_secs(60));
+        let key = |params: Value| cache.key("eth_call", &params).unwrap();
+        assert_eq!(
+            key(json!([{"to": "0xAB", "data": "0x01"}, HASH.to_uppercase().replace("0X", "0x")])),
+            key(json!([{"data": "0x01", "to": "0xab"}, HASH])),
+        );
+        assert_ne!(
+            key(json!([{"to": "0xab"}, HASH])),
+            key(json!([{"to": "0xac"}, HASH]))
+        );
+    }
+
+    #[rstest]
+    fn pending_and_null_results_are_not_cached() {
+        let cache = cache(10, Duration::from_secs(60));
+        let key = cache
+            .key("eth_getTransactionByHash", &json!([HASH]))
+            .unwrap();
+        cache.insert(key.clone(), &result(Value::Null));
+        cache.insert(
+            key.clone(),
+            &result(json!({"hash": HASH, "blockHash": null})),
+        );
+        cache.insert(
+            key.clone(),
+            &json!({"jsonrpc": "2.0", "id": 1, "error": {"code": -32000}}),
+        );
+        assert_eq!(cache.get(&key), None);
+
+        cache.insert(
+            key.clone(),
+            &result(json!({"hash": HASH, "blockHash": HASH})),
+        );
+        assert_eq!(cache.get(&key).unwrap()["blockHash"], HASH);
+    }
+
+    #[rstest]
+    fn least_recently_used_entry_is_evicted() {
+        let cache = cache(2, Duration::from_secs(60));
+        let key = |index: u64| {
+            cache
+                .key("eth_getTransactionReceipt", &json!([format!("0x{index}")]))
+                .unwrap()
+        };
+        cache.insert(key(1), &result(json!(1)));
+        cache.insert(key(2), &result(json!(2)));
+        assert!(cache.get(&key(1)).is_some());
+        cache.insert(key(3), &result(json!(3)));
+
+        assert!(cache.get(&key(1)).is_some());
+        assert_eq!(cache.get(&key(2)), None);
+        assert!(cache.get(&key(3)).is_some());
+    }
+
+    #[rstest]
+    #[case::revert("evm_revert")]
+    #[case::reset("anvil_reset")]
+    #[case::reorg("anvil_reorg")]
+    #[case::case_insensitive("ANVIL_REORG")]
+    #[case::set_balance("anvil_setBalance")]
+    #[case::set_code("hardhat_setCode")]
+    #[case::set_storage("anvil_setStorageAt")]
+    #[case::set_nonce("hardhat_setNonce")]
+    #[case::mine("evm_mine")]
+    #[case::mine_blocks("anvil_mine")]
+    #[case::next_timestamp("evm_setNextBlockTimestamp")]
+    fn chain_resets_clear_the_cache(#[case] method: &str) {
+        let cache = cache(10, Duration::from_secs(60));
+        let key = cache
+            .key("eth_getBlockByHash", &json!([HASH, false]))
+            .unwrap();
+        cache.insert(key.clone(), &result(json!({"hash": HASH})));
+        cache.observe(method);
+        assert_eq!(cache.get(&key), None);
+    }
+
+    #[rstest]
+    fn entries_expire_and_chain_resets_clear() {
+        let cache = cache(10, Duration::from_millis(20));
+        let key = cache.key("eth_chainId", &Value::Null).unwrap();
+        cache.insert(key.clone(), &result(json!("0x1")));
+        assert!(cache.get(&key).is_some());
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(cache.get(&key), None);
+
+        cache.insert(key.clone(), &result(json!("0x1")));
+        cache.observe("eth_sendRawTransaction");
+        assert!(cache.get(&key).is_some());
+        cache.observe("evm_revert");
+        assert_eq!(cache.get(&key), None);
+    }
+}
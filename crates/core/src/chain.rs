@@ -0,0 +1,135 @@
+use crate::errors::ProxyError;
+use crate::server::{call_upstream, upstream_client};
+use serde_json::Value;
+use tracing::{debug, info};
+use veto_config::{ChainIdSetting, Config, Quantity};
+
+/// Replace `chain_id = "upstream"` with the chain id reported by the upstream `eth_chainId`.
+///
+/// Configurations without a chain id, or with a fixed one, are returned unchanged. The
+/// upstream must answer within the default upstream timeout.
+pub async fn resolve_chain_id(config: Config) -> Result<Config, ProxyError> {
+    if config.chain_id() != Some(ChainIdSetting::Upstream) {
+        return Ok(config);
+    }
+
+    let chain_id = fetch_chain_id(&config).await?;
+    info!(
+        chain_id,
+        upstream = %config.upstream_url(),
+        "pinned chain id learned from upstream"
+    );
+    Ok(config.with_chain_id(Some(ChainIdSetting::Fixed(chain_id))))
+}
+
+async fn fetch_chain_id(config: &Config) -> Result<u64, ProxyError> {
+    debug!(upstream = %config.upstream_url(), "querying upstream chain id");
+    #[cfg(feature = "tls")]
+    let client = upstream_client(crate::tls::client_config(config.upstream_tls())?);
+    #[cfg(not(feature = "tls"))]
+    let client = upstream_client(config)?;
+    let timeout = config.upstream_timeouts().default_timeout();
+    let bytes = tokio::time::timeout(
+        timeout,
+        call_upstream(&client, config.upstream_url(), "eth_chainId"),
+    )
+    .await
+    .map_err(|_| {
+        ProxyError::ChainId(format!(
+            "{} did not answer eth_chainId within {}ms",
+            config.upstream_url(),
+            timeout.as_millis()
+        ))
+    })??;
+
+    let value: Value = serde_json::from_slice(&bytes)
+        .map_err(|error| ProxyError::ChainId(format!("invalid JSON response: {error}")))?;
+    value
+        .get("result")
+        .and_then(Value::as_str)
+        .and_then(Quantity::parse)
+        .and_then(|quantity| u64::try_from(quantity.0).ok())
+        .ok_or_else(|| ProxyError::ChainId(format!("unexpected eth_chainId response: {value}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::body::Body;
+    use axum::response::Response;
+    use axum::routing::any;
+    use serde_json::json;
+    use std::collections::HashSet;
+    use std::time::Duration;
+    use veto_config::UpstreamTimeouts;
+
+    async fn spawn_upstream(result: Value) -> http::Uri {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let app = Router::new().fallback(any(move || {
+            let result = result.clone();
+            async move {
+                Response::new(Body::from(
+                    json!({"jsonrpc": "2.0", "id": 1, "result": result}).to_string(),
+                ))
+            }
+        }));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{address}").parse().unwrap()
+    }
+
+    fn config(upstream: http::Uri, chain_id: Option<ChainIdSetting>) -> Config {
+        Config::new("127.0.0.1:0".parse().unwrap(), upstream, HashSet::new())
+            .with_chain_id(chain_id)
+    }
+
+    #[tokio::test]
+    async fn learns_chain_id_from_upstream() {
+        let upstream = spawn_upstream(json!("0x7a69")).await;
+        let config = resolve_chain_id(config(upstream, Some(ChainIdSetting::Upstream)))
+            .await
+            .unwrap();
+        assert_eq!(config.chain_id(), Some(ChainIdSetting::Fixed(31337)));
+    }
+
+    #[tokio::test]
+    async fn fixed_chain_id_skips_the_upstream() {
+        let upstream = "http://127.0.0.1:1".parse().unwrap();
+        let config = resolve_chain_id(config(upstream, Some(ChainIdSetting::Fixed(1))))
+            .await
+            .unwrap();
+        assert_eq!(config.chain_id(), Some(ChainIdSetting::Fixed(1)));
+    }
+
+    #[tokio::test]
+    async fn hung_upstream_times_out() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let app = Router::new().fallback(any(std::future::pending::<Response>));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let timeouts = UpstreamTimeouts::new(Duration::from_millis(50), Vec::new()).unwrap();
+        let config = config(
+            format!("http://{address}").parse().unwrap(),
+            Some(ChainIdSetting::Upstream),
+        )
+        .with_upstream_timeouts(timeouts);
+        let error = resolve_chain_id(config).await.unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("did not answer eth_chainId within 50ms"),
+            "{error}"
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_chain_id() {
+        let upstream = spawn_upstream(json!("mainnet")).await;
+        let error = resolve_chain_id(config(upstream, Some(ChainIdSetting::Upstream)))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, ProxyError::ChainId(_)));
+    }
+}
@@ -0,0 +1,332 @@
+use crate::metrics::Metrics;
+use axum::http::Uri;
+use serde::Serialize;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Instant;
+use tracing::{info, warn};
+use veto_config::CircuitBreaker;
+
+/// State of an upstream's circuit breaker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// Calls flow to the upstream.
+    Closed,
+    /// Calls fail fast without reaching the upstream.
+    Open,
+    /// A limited number of trial calls probe whether the upstream recovered.
+    HalfOpen,
+}
+
+impl CircuitState {
+    /// Stable name of the state, used as a metrics label.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Closed => "closed",
+            Self::Open => "open",
+            Self::HalfOpen => "half_open",
+        }
+    }
+
+    /// Value exported by the circuit state gauge.
+    pub(crate) const fn gauge_value(self) -> i64 {
+        match self {
+            Self::Closed => 0,
+            Self::HalfOpen => 1,
+            Self::Open => 2,
+        }
+    }
+}
+
+impl fmt::Display for CircuitState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Circuit breaker guarding one upstream.
+///
+/// Outcomes are reported through the [`CircuitPermit`] handed out for each call. Only trial
+/// calls decide whether a half-open circuit closes or opens again; calls admitted before the
+/// circuit opened are ignored once it has.
+#[derive(Debug)]
+pub(crate) struct Circuit {
+    upstream: Uri,
+    settings: CircuitBreaker,
+    state: Mutex<State>,
+    metrics: Option<Arc<Metrics>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    HalfOpen { trials: u32, successes: u32 },
+}
+
+impl State {
+    const fn kind(self) -> CircuitState {
+        match self {
+            Self::Closed { .. } => CircuitState::Closed,
+            Self::Open { .. } => CircuitState::Open,
+            Self::HalfOpen { .. } => CircuitState::HalfOpen,
+        }
+    }
+}
+
+impl Circuit {
+    pub(crate) fn new(
+        upstream: Uri,
+        settings: CircuitBreaker,
+        metrics: Option<Arc<Metrics>>,
+    ) -> Self {
+        if let Some(metrics) = &metrics {
+            metrics.set_circuit_state(&upstream, CircuitState::Closed);
+        }
+        Self {
+            upstream,
+            settings,
+            state: Mutex::new(State::Closed { failures: 0 }),
+            metrics,
+        }
+    }
+
+    /// Current state, reporting an open circuit whose wait has elapsed as half-open.
+    pub(crate) fn state(&self, now: Instant) -> CircuitState {
+        match *self.lock() {
+            State::Open { until } if until <= now => CircuitState::HalfOpen,
+            state => state.kind(),
+        }
+    }
+
+    /// Whether [`Circuit::admit`] would currently let a call through.
+    pub(crate) fn allows(&self, now: Instant) -> bool {
+        match *self.lock() {
+            State::Closed { .. } => true,
+            State::Open { until } => until <= now,
+            State::HalfOpen { trials, .. } => trials < self.settings.half_open_requests(),
+        }
+    }
+
+    /// Let a call through, or return `None` while the circuit is open or every trial slot of
+    /// a half-open circuit is taken.
+    pub(crate) fn admit(&self, now: Instant) -> Option<CircuitPermit<'_>> {
+        let mut state = self.lock();
+        let trial = match *state {
+            State::Closed { .. } => false,
+            State::Open { until } if until <= now => {
+                self.transition(
+                    &mut state,
+                    State::HalfOpen {
+                        trials: 0,
+                        successes: 0,
+                    },
+                );
+                true
+            }
+            State::Open { .. } => return None,
+            State::HalfOpen { trials, .. } if trials >= self.settings.half_open_requests() => {
+                return None;
+            }
+            State::HalfOpen { .. } => true,
+        };
+        if let State::HalfOpen { trials, .. } = &mut *state {
+            *trials += 1;
+        }
+        Some(CircuitPermit {
+            circuit: Some(self),
+            trial,
+        })
+    }
+
+    fn record_success(&self, trial: bool) {
+        let mut state = self.lock();
+        match *state {
+            State::Closed { .. } => *state = State::Closed { failures: 0 },
+            State::HalfOpen { trials, successes } if trial => {
+                let successes = successes + 1;
+                if successes >= self.settings.half_open_requests() {
+                    self.transition(&mut state, State::Closed { failures: 0 });
+                } else {
+                    *state = State::HalfOpen { trials, successes };
+                }
+            }
+            State::HalfOpen { .. } | State::Open { .. } => {}
+        }
+    }
+
+    fn record_failure(&self, trial: bool) {
+        let mut state = self.lock();
+        let open = State::Open {
+            until: Instant::now() + self.settings.open_duration(),
+        };
+        match *state {
+            State::Closed { failures } => {
+                let failures = failures.saturating_add(1);
+                if failures >= self.settings.failure_threshold() {
+                    self.transition(&mut state, open);
+                } else {
+                    *state = State::Closed { failures };
+                }
+            }
+            State::HalfOpen { .. } if trial => self.transition(&mut state, open),
+            State::HalfOpen { .. } | State::Open { .. } => {}
+        }
+    }
+
+    /// Free the slot of a trial call that ended without a verdict, such as one whose client went
+    /// away before the upstream answered.
+    fn release_trial(&self) {
+        if let State::HalfOpen { trials, .. } = &mut *self.lock() {
+            *trials = trials.saturating_sub(1);
+        }
+    }
+
+    fn transition(&self, state: &mut State, next: State) {
+        let from = state.kind();
+        *state = next;
+        let to = next.kind();
+        match to {
+            CircuitState::Open => warn!(
+                upstream = %self.upstream,
+                %from,
+                open_for = ?self.settings.open_duration(),
+                "circuit opened; failing calls fast"
+            ),
+            CircuitState::HalfOpen => info!(
+                upstream = %self.upstream,
+                trials = self.settings.half_open_requests(),
+                "circuit half-open; letting trial calls through"
+            ),
+            CircuitState::Closed => info!(upstream = %self.upstream, "circuit closed"),
+        }
+        if let Some(metrics) = &self.metrics {
+            metrics.record_circuit_transition(&self.upstream, to);
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Permission for one call to reach an upstream, reporting its outcome to the [`Circuit`].
+///
+/// A permit dropped without an outcome frees its trial slot without counting as a success or a
+/// failure. Upstreams without a circuit breaker hand out permits that record nothing.
+#[derive(Debug)]
+pub(crate) struct CircuitPermit<'a> {
+    circuit: Option<&'a Circuit>,
+    trial: bool,
+}
+
+impl CircuitPermit<'_> {
+    /// Permit for an upstream without a circuit breaker.
+    pub(crate) const fn unguarded() -> Self {
+        Self {
+            circuit: None,
+            trial: false,
+        }
+    }
+
+    /// Report that the call succeeded.
+    pub(crate) fn succeed(mut self) {
+        if let Some(circuit) = self.circuit.take() {
+            circuit.record_success(self.trial);
+        }
+    }
+
+    /// Report that the call failed with a connection error, `5xx` response, or timeout.
+    pub(crate) fn fail(mut self) {
+        if let Some(circuit) = self.circuit.take() {
+            circuit.record_failure(self.trial);
+        }
+    }
+}
+
+impl Drop for CircuitPermit<'_> {
+    fn drop(&mut self) {
+        if let Some(circuit) = self.circuit
+            && self.trial
+        {
+            circuit.release_trial();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use std::time::Duration;
+
+    fn circuit(failure_threshold: u32, half_open_requests: u32) -> Circuit {
+        let settings = CircuitBreaker::new(
+            failure_threshold,
+            Duration::from_secs(10),
+            half_open_requests,
+        )
+        .unwrap();
+        Circuit::new("http://127.0.0.1:1".parse().unwrap(), settings, None)
+    }
+
+    #[rstest]
+    fn consecutive_failures_open_the_circuit() {
+        let circuit = circuit(2, 1);
+        let now = Instant::now();
+        circuit.admit(now).unwrap().fail();
+        circuit.admit(now).unwrap().succeed();
+        circuit.admit(now).unwrap().fail();
+        assert_eq!(circuit.state(now), CircuitState::Closed);
+
+        circuit.admit(now).unwrap().fail();
+        assert_eq!(circuit.state(now), CircuitState::Open);
+        assert!(circuit.admit(Instant::now()).is_none());
+        assert!(!circuit.allows(Instant::now()));
+    }
+
+    #[rstest]
+    fn half_open_circuit_closes_after_successful_trials() {
+        let circuit = circuit(1, 2);
+        circuit.admit(Instant::now()).unwrap().fail();
+        let later = Instant::now() + Duration::from_secs(11);
+        assert_eq!(circuit.state(later), CircuitState::HalfOpen);
+
+        let first = circuit.admit(later).unwrap();
+        let second = circuit.admit(later).unwrap();
+        assert!(circuit.admit(later).is_none());
+        first.succeed();
+        assert_eq!(circuit.state(later), CircuitState::HalfOpen);
+        second.succeed();
+        assert_eq!(circuit.state(later), CircuitState::Closed);
+    }
+
+    #[rstest]
+    fn failed_trial_reopens_the_circuit() {
+        let circuit = circuit(1, 1);
+        let stale = circuit.admit(Instant::now()).unwrap();
+        circuit.admit(Instant::now()).unwrap().fail();
+        let later = Instant::now() + Duration::from_secs(11);
+
+        let trial = circuit.admit(later).unwrap();
+        stale.succeed();
+        assert_eq!(circuit.state(later), CircuitState::HalfOpen);
+        trial.fail();
+        assert_eq!(circuit.state(Instant::now()), CircuitState::Open);
+    }
+
+    #[rstest]
+    fn abandoned_trial_frees_its_slot() {
+        let circuit = circuit(1, 1);
+        circuit.admit(Instant::now()).unwrap().fail();
+        let later = Instant::now() + Duration::from_secs(11);
+
+        drop(circuit.admit(later).unwrap());
+        assert!(circuit.allows(later));
+        circuit.admit(later).unwrap().succeed();
+        assert_eq!(circuit.state(later), CircuitState::Closed);
+    }
+}
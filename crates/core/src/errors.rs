@@ -6,6 +6,12 @@ pub enum ProxyError {
     /// Failed to bind to the requested socket.
     #[error("failed to bind proxy socket: {0}")]
     Bind(std::io::Error),
+    /// Failed to bind the metrics listener.
+    #[error("failed to bind metrics socket: {0}")]
+    MetricsBind(std::io::Error),
+    /// Failed to bind the admin API listener.
+    #[error("failed to bind admin socket: {0}")]
+    AdminBind(std::io::Error),
     /// Axum server error.
     #[error("server error: {0}")]
     Server(std::io::Error),
@@ -15,7 +21,98 @@ pub enum ProxyError {
     /// Failed to reach upstream.
     #[error("upstream request failed: {0}")]
     Upstream(hyper_util::client::legacy::Error),
+    /// The upstream did not answer within the deadline of the call.
+    #[error("upstream request timed out after {}ms", .0.as_millis())]
+    UpstreamTimeout(std::time::Duration),
+    /// Every upstream circuit breaker is open, so the call was not sent.
+    #[error("upstream unavailable: every upstream circuit is open")]
+    UpstreamUnavailable,
+    /// Failed to read the upstream response body.
+    #[error("failed to read upstream response body: {0}")]
+    UpstreamBody(Box<dyn std::error::Error + Send + Sync>),
+    /// Failed to open a WebSocket connection to the upstream.
+    #[error("upstream websocket connection failed: {0}")]
+    UpstreamWebSocket(tokio_tungstenite::tungstenite::Error),
+    /// The configured method rules could not be compiled.
+    #[error("invalid method policy: {0}")]
+    Policy(veto_config::ConfigError),
+    /// The upstream did not report a usable chain id.
+    #[error("failed to learn chain id from upstream: {0}")]
+    ChainId(String),
+    /// `chain_id = "upstream"` was not resolved before building the proxy state.
+    #[error("chain id must be learned from the upstream before starting the proxy")]
+    UnresolvedChainId,
+    /// TLS was configured but the crate was built without the `tls` feature.
+    #[error("TLS requires veto-core to be built with the `tls` feature")]
+    TlsUnsupported,
+    /// A PEM certificate or key file could not be loaded.
+    #[cfg(feature = "tls")]
+    #[error("failed to load PEM file {}: {source}", path.display())]
+    TlsPem {
+        /// Path of the PEM file.
+        path: std::path::PathBuf,
+        /// Error returned while reading or parsing the file.
+        source: rustls::pki_types::pem::Error,
+    },
+    /// The TLS configuration was rejected by rustls.
+    #[cfg(feature = "tls")]
+    #[error("invalid TLS configuration: {0}")]
+    Tls(rustls::Error),
+    /// The client certificate authorities for mutual TLS could not be used.
+    #[cfg(feature = "tls")]
+    #[error("invalid TLS client CA: {0}")]
+    TlsClientVerifier(rustls::server::VerifierBuilderError),
+    /// The audit log file could not be opened.
+    #[error("failed to open audit log {}: {source}", path.display())]
+    AuditLog {
+        /// Path of the audit log.
+        path: std::path::PathBuf,
+        /// Underlying IO error.
+        source: std::io::Error,
+    },
+    /// Failed to install a signal handler.
+    #[error("failed to install signal handler: {0}")]
+    Signal(std::io::Error),
+    /// A reloaded configuration file could not be resolved.
+    #[error("invalid configuration: {0}")]
+    Config(veto_config::ConfigError),
+    /// Failed to watch the configuration file for changes.
+    #[error("failed to watch configuration file: {0}")]
+    Watch(notify::Error),
     /// Failed to construct upstream URI for forwarding.
     #[error("failed to construct upstream URI: {0}")]
     BadUpstreamUri(http::uri::InvalidUriParts),
 }
+
+impl ProxyError {
+    /// Stable name of the variant, used as a metrics label.
+    pub const fn kind(&self) -> &'static str {
+        match self {
+            Self::Bind(_) => "bind",
+            Self::MetricsBind(_) => "metrics_bind",
+            Self::AdminBind(_) => "admin_bind",
+            Self::Server(_) => "server",
+            Self::Body(_) => "body",
+            Self::Upstream(_) => "upstream",
+            Self::UpstreamTimeout(_) => "upstream_timeout",
+            Self::UpstreamUnavailable => "upstream_unavailable",
+            Self::UpstreamBody(_) => "upstream_body",
+            Self::UpstreamWebSocket(_) => "upstream_websocket",
+            Self::Policy(_) => "policy",
+            Self::ChainId(_) => "chain_id",
+            Self::UnresolvedChainId => "unresolved_chain_id",
+            Self::TlsUnsupported => "tls_unsupported",
+            #[cfg(feature = "tls")]
+            Self::TlsPem { .. } => "tls_pem",
+            #[cfg(feature = "tls")]
+            Self::Tls(_) => "tls",
+            #[cfg(feature = "tls")]
+            Self::TlsClientVerifier(_) => "tls_client_verifier",
+            Self::AuditLog { .. } => "audit_log",
+            Self::Signal(_) => "signal",
+            Self::Config(_) => "config",
+            Self::Watch(_) => "watch",
+            Self::BadUpstreamUri(_) => "bad_upstream_uri",
+        }
+    }
+}
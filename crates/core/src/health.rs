@@ -0,0 +1,210 @@
+use crate::server::{AppState, call_upstream};
+use crate::upstream::{HealthCheckReport, Upstream};
+use futures_util::future::join_all;
+use serde_json::Value;
+use tokio::time::{MissedTickBehavior, interval, timeout};
+use tracing::debug;
+use veto_config::{HealthCheck, Quantity};
+
+/// Probe every upstream on the configured interval for as long as the runtime runs.
+pub(crate) fn spawn_health_checks(state: AppState, settings: HealthCheck) {
+    tokio::spawn(async move {
+        let mut ticker = interval(settings.interval());
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            check_upstreams(&state, &settings).await;
+        }
+    });
+}
+
+/// Probe every upstream once and update which of them stay in rotation.
+///
+/// An upstream fails the check when it cannot be queried, reports that it is syncing, or is
+/// more than `max_block_lag` blocks behind the highest block reported by a synced upstream.
+pub(crate) async fn check_upstreams(state: &AppState, settings: &HealthCheck) {
+    let upstreams = state.upstreams.upstreams();
+    let probes = join_all(upstreams.iter().map(|upstream| async move {
+        timeout(settings.timeout(), probe(state, upstream))
+            .await
+            .unwrap_or_else(|_| {
+                Err(format!(
+                    "timed out after {}ms",
+                    settings.timeout().as_millis()
+                ))
+            })
+    }))
+    .await;
+
+    let best = probes
+        .iter()
+        .filter_map(|probe| probe.as_ref().ok())
+        .filter(|probe| !probe.syncing)
+        .map(|probe| probe.block_number)
+        .max();
+    debug!(best_block = ?best, upstreams = upstreams.len(), "upstream health checks complete");
+
+    for (upstream, probe) in upstreams.iter().zip(probes) {
+        let report = match probe {
+            Ok(probe) => {
+                let lag = best.map(|best| best.saturating_sub(probe.block_number));
+                HealthCheckReport {
+                    passing: !probe.syncing
+                        && lag.is_none_or(|lag| lag <= settings.max_block_lag()),
+                    block_number: Some(probe.block_number),
+                    block_lag: lag,
+                    syncing: probe.syncing,
+                    error: None,
+                }
+            }
+            Err(error) => HealthCheckReport {
+                passing: false,
+                block_number: None,
+                block_lag: None,
+                syncing: false,
+                error: Some(error),
+            },
+        };
+        upstream.record_check(report);
+    }
+}
+
+/// What a single upstream reported.
+#[derive(Debug)]
+struct Probe {
+    syncing: bool,
+    block_number: u64,
+}
+
+async fn probe(state: &AppState, upstream: &Upstream) -> Result<Probe, String> {
+    let syncing = query(state, upstream, "eth_syncing").await?;
+    let syncing = match syncing {
+        Value::Bool(syncing) => syncing,
+        Value::Object(_) => true,
+        other => return Err(format!("unexpected eth_syncing result: {other}")),
+    };
+
+    let block_number = query(state, upstream, "eth_blockNumber").await?;
+    let block_number = block_number
+        .as_str()
+        .and_then(Quantity::parse)
+        .and_then(|quantity| u64::try_from(quantity.0).ok())
+        .ok_or_else(|| format!("unexpected eth_blockNumber result: {block_number}"))?;
+
+    Ok(Probe {
+        syncing,
+        block_number,
+    })
+}
+
+/// Call `method` on the upstream and return its `result`.
+async fn query(state: &AppState, upstream: &Upstream, method: &str) -> Result<Value, String> {
+    let bytes = call_upstream(&state.client, upstream.uri(), method)
+        .await
+        .map_err(|error| error.to_string())?;
+    let mut value: Value = serde_json::from_slice(&bytes)
+        .map_err(|error| format!("invalid {method} response: {error}"))?;
+    value
+        .get_mut("result")
+        .map(Value::take)
+        .ok_or_else(|| format!("{method} returned no result: {value}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::body::{Body, Bytes};
+    use axum::response::Response;
+    use axum::routing::any;
+    use serde_json::json;
+    use std::collections::HashSet;
+    use std::time::Duration;
+    use veto_config::Config;
+
+    /// Upstream answering `eth_syncing` and `eth_blockNumber` with fixed results.
+    async fn spawn_node(syncing: Value, block_number: u64) -> http::Uri {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let app = Router::new().fallback(any(move |body: Bytes| {
+            let syncing = syncing.clone();
+            async move {
+                let request: Value = serde_json::from_slice(&body).unwrap();
+                let result = match request["method"].as_str() {
+                    Some("eth_syncing") => syncing,
+                    _ => json!(format!("{block_number:#x}")),
+                };
+                Response::new(Body::from(
+                    json!({"jsonrpc": "2.0", "id": 1, "result": result}).to_string(),
+                ))
+            }
+        }));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{address}").parse().unwrap()
+    }
+
+    fn state(upstreams: Vec<http::Uri>) -> AppState {
+        let config = Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            upstreams[0].clone(),
+            HashSet::new(),
+        )
+        .with_upstreams(upstreams);
+        AppState::try_from_config(config).unwrap()
+    }
+
+    fn settings() -> HealthCheck {
+        HealthCheck::new(Duration::from_secs(1), Duration::from_millis(500), 5).unwrap()
+    }
+
+    #[tokio::test]
+    async fn lagging_and_syncing_upstreams_leave_rotation() {
+        let state = state(vec![
+            spawn_node(json!(false), 100).await,
+            spawn_node(json!(false), 94).await,
+            spawn_node(json!({"currentBlock": "0x10", "highestBlock": "0x64"}), 16).await,
+            spawn_node(json!(false), 96).await,
+        ]);
+        check_upstreams(&state, &settings()).await;
+
+        let stats = state.upstream_stats();
+        let in_rotation: Vec<_> = stats.iter().map(|stats| stats.in_rotation).collect();
+        assert_eq!(in_rotation, [true, false, false, true]);
+        let lagging = stats[1].health_check.as_ref().unwrap();
+        assert_eq!(lagging.block_lag, Some(6));
+        assert_eq!(lagging.reason(), "6 blocks behind");
+        assert!(stats[2].health_check.as_ref().unwrap().syncing);
+
+        let candidates: Vec<_> = state
+            .upstreams
+            .candidates()
+            .iter()
+            .map(|upstream| upstream.index())
+            .collect();
+        assert_eq!(candidates, [0, 3]);
+    }
+
+    #[tokio::test]
+    async fn unreachable_upstream_fails_and_recovers() {
+        let state = state(vec![
+            spawn_node(json!(false), 7).await,
+            "http://127.0.0.1:1".parse().unwrap(),
+        ]);
+        check_upstreams(&state, &settings()).await;
+
+        let stats = state.upstream_stats();
+        assert!(stats[0].in_rotation);
+        let unreachable = stats[1].health_check.as_ref().unwrap();
+        assert!(!unreachable.passing);
+        assert!(unreachable.error.is_some());
+
+        state.upstreams.upstreams()[1].record_check(HealthCheckReport {
+            passing: true,
+            block_number: Some(7),
+            block_lag: Some(0),
+            syncing: false,
+            error: None,
+        });
+        assert!(state.upstream_stats()[1].in_rotation);
+    }
+}
@@ -1,8 +1,11 @@
-use axum::body::Body;
+use axum::body::{Body, Bytes};
 use axum::http::StatusCode;
 use axum::response::Response;
-use serde::Deserialize;
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Deserializer};
+use serde_json::value::RawValue;
 use serde_json::{Value, json};
+use std::borrow::Cow;
 
 /// Inbound JSON-RPC request payload.
 #[derive(Debug, Deserialize)]
@@ -10,6 +13,9 @@ pub struct JsonRpcRequest {
     /// Method invoked by the request.
     #[serde(default)]
     pub method: String,
+    /// Parameters passed to the method, [`Value::Null`] when omitted.
+    #[serde(default)]
+    pub params: Value,
     /// Identifier echoed back to the caller as a [`Value`].
     #[serde(default)]
     pub id: Value,
@@ -22,62 +28,301 @@ pub enum JsonRpcError {
     InvalidJson(serde_json::Error),
     /// The request is not a valid JSON-RPC request.
     InvalidRequest(String),
-    /// The request is a batch request, which is not supported.
-    Unsupported(String),
+    /// The batch holds more entries than the configured maximum.
+    BatchTooLarge {
+        /// Number of entries in the rejected batch.
+        size: usize,
+        /// Maximum number of entries accepted per batch.
+        max: usize,
+    },
+    /// The request body is larger than the configured maximum.
+    BodyTooLarge {
+        /// Maximum number of bytes accepted per request.
+        max: usize,
+    },
 }
 
-/// Parse bytes into a [`JsonRpcRequest`], rejecting batch payloads and empty methods.
-pub(crate) fn parse_json_rpc(body: &[u8]) -> Result<JsonRpcRequest, JsonRpcError> {
-    if body.is_empty() {
-        return Err(JsonRpcError::InvalidRequest("empty body".into()));
+impl JsonRpcError {
+    /// Stable name of the variant, used as a metrics label.
+    pub const fn kind(&self) -> &'static str {
+        match self {
+            Self::InvalidJson(_) => "invalid_json",
+            Self::InvalidRequest(_) => "invalid_request",
+            Self::BatchTooLarge { .. } => "batch_too_large",
+            Self::BodyTooLarge { .. } => "body_too_large",
+        }
     }
 
-    let value: Value = serde_json::from_slice(body).map_err(JsonRpcError::InvalidJson)?;
+    /// HTTP status of the error response: `413` for oversized bodies, `400` otherwise.
+    pub const fn status(&self) -> StatusCode {
+        match self {
+            Self::BodyTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
 
-    let request: JsonRpcRequest = match value {
-        Value::Object(_) => serde_json::from_value(value).map_err(JsonRpcError::InvalidJson)?,
-        Value::Array(_) => {
-            return Err(JsonRpcError::Unsupported(
-                "batch requests not supported".into(),
-            ));
+/// Fields of a request object, borrowing the method name from the payload when it can.
+#[derive(Debug, Deserialize)]
+struct RequestFields<'a> {
+    #[serde(default, borrow)]
+    method: Cow<'a, str>,
+    #[serde(default)]
+    params: Value,
+    #[serde(default, deserialize_with = "present")]
+    id: Option<Value>,
+}
+
+/// Deserialize a member that is present, so an explicit `null` is told apart from a missing one.
+fn present<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Value>, D::Error> {
+    Value::deserialize(deserializer).map(Some)
+}
+
+/// A decoded JSON-RPC payload: either a single call or a batch of calls.
+#[derive(Debug)]
+pub(crate) enum JsonRpcPayload<'a> {
+    /// A single JSON-RPC request object.
+    Single(JsonRpcRequest),
+    /// A JSON-RPC batch, preserving the order of its entries.
+    Batch(Vec<BatchEntry<'a>>),
+}
+
+/// A single entry of a JSON-RPC batch.
+#[derive(Debug)]
+pub(crate) enum BatchEntry<'a> {
+    /// A well-formed request alongside the original JSON to forward upstream.
+    Request {
+        /// Decoded request.
+        request: JsonRpcRequest,
+        /// Original entry, borrowed from the body and forwarded verbatim when allowed.
+        raw: &'a RawValue,
+        /// Whether the entry omitted its `id` and therefore expects no response.
+        notification: bool,
+    },
+    /// An entry that failed validation and must be answered with an error.
+    Invalid(JsonRpcError),
+}
+
+/// Parse bytes into a [`JsonRpcPayload`], rejecting empty methods and oversized batches.
+///
+/// The payload is decoded in one pass: a single request straight into its fields, and a batch
+/// into entries borrowed from `body`, so the original bytes of each entry can be forwarded.
+pub(crate) fn parse_json_rpc(
+    body: &[u8],
+    max_batch_size: usize,
+) -> Result<JsonRpcPayload<'_>, JsonRpcError> {
+    match body.iter().find(|byte| !byte.is_ascii_whitespace()) {
+        None => Err(JsonRpcError::InvalidRequest("empty body".into())),
+        Some(b'{') => parse_request(body).map(|(request, _)| JsonRpcPayload::Single(request)),
+        Some(b'[') => {
+            let entries: Vec<&RawValue> =
+                serde_json::from_slice(body).map_err(JsonRpcError::InvalidJson)?;
+            if entries.is_empty() {
+                return Err(JsonRpcError::InvalidRequest(
+                    "JSON-RPC batch must not be empty".into(),
+                ));
+            }
+            if entries.len() > max_batch_size {
+                return Err(JsonRpcError::BatchTooLarge {
+                    size: entries.len(),
+                    max: max_batch_size,
+                });
+            }
+
+            let entries = entries.into_iter().map(parse_batch_entry).collect();
+            Ok(JsonRpcPayload::Batch(entries))
         }
-        _ => {
-            return Err(JsonRpcError::InvalidRequest(
-                "JSON-RPC payload must be an object".into(),
-            ));
+        Some(_) => {
+            serde_json::from_slice::<IgnoredAny>(body).map_err(JsonRpcError::InvalidJson)?;
+            Err(JsonRpcError::InvalidRequest(
+                "JSON-RPC payload must be an object or an array".into(),
+            ))
         }
-    };
+    }
+}
+
+/// Decode one batch entry, keeping the raw JSON so it can be forwarded untouched.
+fn parse_batch_entry(raw: &RawValue) -> BatchEntry<'_> {
+    if !raw.get().starts_with('{') {
+        return BatchEntry::Invalid(JsonRpcError::InvalidRequest(
+            "JSON-RPC batch entry must be an object".into(),
+        ));
+    }
+
+    match parse_request(raw.get().as_bytes()) {
+        Ok((request, notification)) => BatchEntry::Request {
+            request,
+            raw,
+            notification,
+        },
+        Err(error) => BatchEntry::Invalid(error),
+    }
+}
+
+/// Decode a single JSON-RPC request object, reporting whether it is a notification.
+fn parse_request(body: &[u8]) -> Result<(JsonRpcRequest, bool), JsonRpcError> {
+    let fields: RequestFields<'_> =
+        serde_json::from_slice(body).map_err(JsonRpcError::InvalidJson)?;
 
-    if request.method.trim().is_empty() {
+    if fields.method.trim().is_empty() {
         return Err(JsonRpcError::InvalidRequest(
             "JSON-RPC method is required".into(),
         ));
     }
 
-    Ok(request)
+    let notification = fields.id.is_none();
+    let request = JsonRpcRequest {
+        method: fields.method.into_owned(),
+        params: fields.params,
+        id: fields.id.unwrap_or_default(),
+    };
+    Ok((request, notification))
 }
 
-/// Convert a [`JsonRpcError`] into a JSON-RPC response payload.
-pub(crate) fn error_response(error: JsonRpcError) -> Response {
-    let message = match &error {
+/// Join batch entries back into a JSON array, keeping the original bytes of each entry.
+pub(crate) fn batch_body(entries: &[&RawValue]) -> Bytes {
+    let size = entries
+        .iter()
+        .map(|entry| entry.get().len() + 1)
+        .sum::<usize>()
+        + 1;
+    let mut body = Vec::with_capacity(size);
+    body.push(b'[');
+    for (index, entry) in entries.iter().enumerate() {
+        if index > 0 {
+            body.push(b',');
+        }
+        body.extend_from_slice(entry.get().as_bytes());
+    }
+    body.push(b']');
+    Bytes::from(body)
+}
+
+/// Build the JSON-RPC error object describing a [`JsonRpcError`] for the given `id`.
+pub(crate) fn error_payload(error: &JsonRpcError, id: Value) -> Value {
+    let message = match error {
         JsonRpcError::InvalidJson(err) => err.to_string(),
-        JsonRpcError::InvalidRequest(message) | JsonRpcError::Unsupported(message) => {
-            message.clone()
+        JsonRpcError::InvalidRequest(message) => message.clone(),
+        JsonRpcError::BatchTooLarge { size, max } => {
+            format!("batch of {size} requests exceeds the maximum of {max}")
+        }
+        JsonRpcError::BodyTooLarge { max } => {
+            format!("request body exceeds the maximum of {max} bytes")
         }
     };
 
-    let payload = json!({
+    json!({
         "jsonrpc": "2.0",
         "error": {
             "code": -32600,
             "message": message,
         },
-        "id": Value::Null,
-    });
+        "id": id,
+    })
+}
+
+/// Convert a [`JsonRpcError`] into a JSON-RPC response payload.
+pub(crate) fn error_response(error: JsonRpcError) -> Response {
+    let payload = error_payload(&error, Value::Null);
 
     Response::builder()
-        .status(StatusCode::BAD_REQUEST)
+        .status(error.status())
         .header("content-type", "application/json")
         .body(Body::from(payload.to_string()))
         .expect("valid json error response")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    fn single_request_is_parsed() {
+        let body = br#"{"jsonrpc":"2.0","id":7,"method":"eth_chainId"}"#;
+        match parse_json_rpc(body, 10).unwrap() {
+            JsonRpcPayload::Single(request) => {
+                assert_eq!(request.method, "eth_chainId");
+                assert_eq!(request.id, 7);
+            }
+            other => panic!("unexpected payload: {other:?}"),
+        }
+    }
+
+    #[rstest]
+    fn batch_entries_keep_order_and_flag_invalid_entries() {
+        let body = br#"[
+            {"jsonrpc":"2.0","id":1,"method":"eth_chainId"},
+            {"jsonrpc":"2.0","method":"eth_subscribe"},
+            1,
+            {"jsonrpc":"2.0","id":2,"method":""}
+        ]"#;
+        let JsonRpcPayload::Batch(entries) = parse_json_rpc(body, 10).unwrap() else {
+            panic!("expected batch payload");
+        };
+
+        assert_eq!(entries.len(), 4);
+        let BatchEntry::Request {
+            request,
+            notification,
+            ..
+        } = &entries[0]
+        else {
+            panic!("expected request entry");
+        };
+        assert_eq!(request.method, "eth_chainId");
+        assert!(!notification);
+        assert!(matches!(
+            &entries[1],
+            BatchEntry::Request {
+                notification: true,
+                ..
+            }
+        ));
+        assert!(matches!(&entries[2], BatchEntry::Invalid(_)));
+        assert!(matches!(&entries[3], BatchEntry::Invalid(_)));
+    }
+
+    #[rstest]
+    #[case::empty(b"[]".as_slice())]
+    #[case::scalar(b"42".as_slice())]
+    fn malformed_payloads_are_rejected(#[case] body: &[u8]) {
+        assert!(matches!(
+            parse_json_rpc(body, 10),
+            Err(JsonRpcError::InvalidRequest(_))
+        ));
+    }
+
+    #[rstest]
+    fn batch_entries_are_forwarded_verbatim() {
+        let body = br#"[ {"id": 1, "method": "eth_chainId", "params": [ "0x01" ]}, {"id":null,"method":"a"} ]"#;
+        let JsonRpcPayload::Batch(entries) = parse_json_rpc(body, 10).unwrap() else {
+            panic!("expected batch payload");
+        };
+        let raw: Vec<_> = entries
+            .iter()
+            .map(|entry| match entry {
+                BatchEntry::Request {
+                    raw, notification, ..
+                } => {
+                    assert!(!notification);
+                    *raw
+                }
+                BatchEntry::Invalid(error) => panic!("unexpected invalid entry: {error:?}"),
+            })
+            .collect();
+        assert_eq!(
+            batch_body(&raw),
+            r#"[{"id": 1, "method": "eth_chainId", "params": [ "0x01" ]},{"id":null,"method":"a"}]"#
+        );
+    }
+
+    #[rstest]
+    fn oversized_batches_are_rejected() {
+        let body = br#"[{"id":1,"method":"a"},{"id":2,"method":"b"},{"id":3,"method":"c"}]"#;
+        assert!(matches!(
+            parse_json_rpc(body, 2),
+            Err(JsonRpcError::BatchTooLarge { size: 3, max: 2 })
+        ));
+    }
+}
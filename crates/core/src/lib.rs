@@ -7,14 +7,59 @@
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 #![cfg_attr(not(test), warn(unused_crate_dependencies))]
 
+mod access;
+
+mod admin;
+
+mod audit;
+
+mod auth;
+
+mod balancer;
+
+mod cache;
+
+mod chain;
+pub use chain::resolve_chain_id;
+
+mod circuit;
+pub use circuit::CircuitState;
+
 mod errors;
 pub use errors::ProxyError;
 
+mod health;
+
 mod jsonrpc;
 pub use jsonrpc::{JsonRpcError, JsonRpcRequest};
 
+mod metrics;
+
+mod policy;
+
+mod rate_limit;
+
+mod reload;
+
+mod retry;
+
+mod rules;
+
 mod runtime;
-pub use runtime::run;
+pub use runtime::{run, run_with_reload};
 
 mod server;
 pub use server::{AppState, router};
+
+mod timeout;
+
+#[cfg(feature = "tls")]
+mod tls;
+
+mod tx;
+pub use tx::{DecodedTransaction, TxDecodeError, TxType, decode_raw_transaction};
+
+mod upstream;
+pub use upstream::{HealthCheckReport, UpstreamStats};
+
+mod ws;
@@ -0,0 +1,392 @@
+use crate::cache::CacheStatus;
+use crate::circuit::CircuitState;
+use crate::errors::ProxyError;
+use crate::jsonrpc::JsonRpcError;
+use crate::policy::Rejection;
+use axum::Router;
+use axum::body::Body;
+use axum::extract::State;
+use axum::http::{StatusCode, Uri};
+use axum::response::Response;
+use axum::routing::get;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tracing::{error, info};
+
+/// Longest method name recorded as-is; longer names share one label to bound cardinality.
+const MAX_METHOD_LABEL_LEN: usize = 64;
+
+/// Prometheus metrics recorded by the proxy and served on the metrics listener.
+#[derive(Debug)]
+pub(crate) struct Metrics {
+    registry: Registry,
+    forwarded: IntCounterVec,
+    blocked: IntCounterVec,
+    parse_errors: IntCounterVec,
+    upstream_failures: IntCounterVec,
+    upstream_retries: IntCounterVec,
+    request_duration: HistogramVec,
+    upstream_duration: HistogramVec,
+    in_flight: IntGauge,
+    upstream_in_flight: IntGaugeVec,
+    circuit_state: IntGaugeVec,
+    circuit_transitions: IntCounterVec,
+    cache_lookups: IntCounterVec,
+}
+
+impl Metrics {
+    /// Create the metrics and register them with a fresh registry.
+    pub(crate) fn new() -> Self {
+        let registry = Registry::new();
+        let forwarded = IntCounterVec::new(
+            Opts::new(
+                "veto_requests_forwarded_total",
+                "JSON-RPC calls allowed by the policy and forwarded upstream.",
+            ),
+            &["method"],
+        )
+        .expect("valid metric");
+        let blocked = IntCounterVec::new(
+            Opts::new(
+                "veto_requests_blocked_total",
+                "JSON-RPC calls refused by the policy.",
+            ),
+            &["method", "rule"],
+        )
+        .expect("valid metric");
+        let parse_errors = IntCounterVec::new(
+            Opts::new(
+                "veto_parse_errors_total",
+                "Payloads or batch entries rejected as invalid JSON-RPC.",
+            ),
+            &["error"],
+        )
+        .expect("valid metric");
+        let upstream_failures = IntCounterVec::new(
+            Opts::new(
+                "veto_upstream_failures_total",
+                "Failed upstream requests; `server_error` counts 5xx responses.",
+            ),
+            &["error"],
+        )
+        .expect("valid metric");
+        let upstream_retries = IntCounterVec::new(
+            Opts::new(
+                "veto_upstream_retries_total",
+                "Idempotent calls sent again after an upstream connection error.",
+            ),
+            &["method"],
+        )
+        .expect("valid metric");
+        let request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "veto_request_duration_seconds",
+                "Time to answer a request, by method (`batch` for batches).",
+            ),
+            &["method"],
+        )
+        .expect("valid metric");
+        let upstream_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "veto_upstream_duration_seconds",
+                "Time for an upstream to answer a forwarded request.",
+            ),
+            &["upstream"],
+        )
+        .expect("valid metric");
+        let in_flight = IntGauge::new(
+            "veto_requests_in_flight",
+            "Requests currently being handled by the proxy.",
+        )
+        .expect("valid metric");
+        let upstream_in_flight = IntGaugeVec::new(
+            Opts::new(
+                "veto_upstream_requests_in_flight",
+                "Requests currently awaiting an upstream response.",
+            ),
+            &["upstream"],
+        )
+        .expect("valid metric");
+        let circuit_state = IntGaugeVec::new(
+            Opts::new(
+                "veto_upstream_circuit_state",
+                "Circuit breaker state of each upstream: 0 closed, 1 half-open, 2 open.",
+            ),
+            &["upstream"],
+        )
+        .expect("valid metric");
+        let circuit_transitions = IntCounterVec::new(
+            Opts::new(
+                "veto_upstream_circuit_transitions_total",
+                "Circuit breaker state changes, by upstream and state entered.",
+            ),
+            &["upstream", "state"],
+        )
+        .expect("valid metric");
+        let cache_lookups = IntCounterVec::new(
+            Opts::new(
+                "veto_cache_lookups_total",
+                "Cacheable calls answered from the response cache (`hit`) or forwarded (`miss`).",
+            ),
+            &["method", "result"],
+        )
+        .expect("valid metric");
+
+        for collector in [
+            Box::new(forwarded.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(blocked.clone()),
+            Box::new(parse_errors.clone()),
+            Box::new(upstream_failures.clone()),
+            Box::new(upstream_retries.clone()),
+            Box::new(request_duration.clone()),
+            Box::new(upstream_duration.clone()),
+            Box::new(in_flight.clone()),
+            Box::new(upstream_in_flight.clone()),
+            Box::new(circuit_state.clone()),
+            Box::new(circuit_transitions.clone()),
+            Box::new(cache_lookups.clone()),
+        ] {
+            registry.register(collector).expect("unique metric names");
+        }
+
+        Self {
+            registry,
+            forwarded,
+            blocked,
+            parse_errors,
+            upstream_failures,
+            upstream_retries,
+            request_duration,
+            upstream_duration,
+            in_flight,
+            upstream_in_flight,
+            circuit_state,
+            circuit_transitions,
+            cache_lookups,
+        }
+    }
+
+    /// Count a call allowed by the policy.
+    pub(crate) fn record_forwarded(&self, method: &str) {
+        self.forwarded
+            .with_label_values(&[method_label(method)])
+            .inc();
+    }
+
+    /// Count a call refused by the policy.
+    pub(crate) fn record_blocked(&self, method: &str, rejection: &Rejection) {
+        self.blocked
+            .with_label_values(&[method_label(method), rejection.rule()])
+            .inc();
+    }
+
+    /// Count a payload or batch entry that failed to parse.
+    pub(crate) fn record_parse_error(&self, error: &JsonRpcError) {
+        self.parse_errors.with_label_values(&[error.kind()]).inc();
+    }
+
+    /// Count a failed upstream request.
+    pub(crate) fn record_upstream_error(&self, error: &ProxyError) {
+        self.upstream_failures
+            .with_label_values(&[error.kind()])
+            .inc();
+    }
+
+    /// Count an upstream `5xx` response.
+    pub(crate) fn record_upstream_server_error(&self) {
+        self.upstream_failures
+            .with_label_values(&["server_error"])
+            .inc();
+    }
+
+    /// Count a retry of an idempotent call (`batch` for batches).
+    pub(crate) fn record_upstream_retry(&self, method: &str) {
+        self.upstream_retries
+            .with_label_values(&[method_label(method)])
+            .inc();
+    }
+
+    /// Record how long the proxy took to answer a request.
+    pub(crate) fn observe_request(&self, method: &str, elapsed: Duration) {
+        self.request_duration
+            .with_label_values(&[method_label(method)])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Record how long an upstream took to answer.
+    pub(crate) fn observe_upstream(&self, upstream: &Uri, elapsed: Duration) {
+        self.upstream_duration
+            .with_label_values(&[upstream.to_string()])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Track a request handled by the proxy until the returned guard is dropped.
+    pub(crate) fn request_started(&self) -> GaugeGuard {
+        GaugeGuard::new(self.in_flight.clone())
+    }
+
+    /// Track a request awaiting `upstream` until the returned guard is dropped.
+    pub(crate) fn upstream_started(&self, upstream: &Uri) -> GaugeGuard {
+        GaugeGuard::new(
+            self.upstream_in_flight
+                .with_label_values(&[upstream.to_string()]),
+        )
+    }
+
+    /// Export the circuit breaker state of `upstream`.
+    pub(crate) fn set_circuit_state(&self, upstream: &Uri, state: CircuitState) {
+        self.circuit_state
+            .with_label_values(&[upstream.to_string()])
+            .set(state.gauge_value());
+    }
+
+    /// Count a circuit breaker state change of `upstream` and export the new state.
+    pub(crate) fn record_circuit_transition(&self, upstream: &Uri, state: CircuitState) {
+        self.set_circuit_state(upstream, state);
+        self.circuit_transitions
+            .with_label_values(&[upstream.to_string().as_str(), state.as_str()])
+            .inc();
+    }
+
+    /// Count a response cache lookup for a cacheable call.
+    pub(crate) fn record_cache_lookup(&self, method: &str, status: CacheStatus) {
+        let result = match status {
+            CacheStatus::Hit => "hit",
+            CacheStatus::Miss => "miss",
+        };
+        self.cache_lookups
+            .with_label_values(&[method_label(method), result])
+            .inc();
+    }
+
+    /// Encode every metric in the Prometheus text format.
+    pub(crate) fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("text encoding succeeds");
+        String::from_utf8(buffer).expect("text encoding is UTF-8")
+    }
+}
+
+/// Label for a client-supplied method name.
+const fn method_label(method: &str) -> &str {
+    if method.len() <= MAX_METHOD_LABEL_LEN {
+        method
+    } else {
+        "oversized"
+    }
+}
+
+/// Decrements an in-flight gauge when dropped.
+#[derive(Debug)]
+pub(crate) struct GaugeGuard(IntGauge);
+
+impl GaugeGuard {
+    fn new(gauge: IntGauge) -> Self {
+        gauge.inc();
+        Self(gauge)
+    }
+}
+
+impl Drop for GaugeGuard {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+/// Bind the metrics listener and serve `GET /metrics` in the background.
+pub(crate) async fn serve_metrics(
+    address: SocketAddr,
+    metrics: Arc<Metrics>,
+) -> Result<(), ProxyError> {
+    let listener = TcpListener::bind(address)
+        .await
+        .map_err(ProxyError::MetricsBind)?;
+    let address = listener.local_addr().map_err(ProxyError::MetricsBind)?;
+    info!("serving metrics on http://{address}/metrics");
+
+    tokio::spawn(async move {
+        if let Err(error) = axum::serve(listener, metrics_router(metrics)).await {
+            error!(%error, "metrics listener failed");
+        }
+    });
+    Ok(())
+}
+
+fn metrics_router(metrics: Arc<Metrics>) -> Router {
+    Router::new()
+        .route("/metrics", get(metrics_handler))
+        .with_state(metrics)
+}
+
+async fn metrics_handler(State(metrics): State<Arc<Metrics>>) -> Response {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", prometheus::TEXT_FORMAT)
+        .body(Body::from(metrics.render()))
+        .expect("valid metrics response")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::Request;
+    use http_body_util::BodyExt;
+    use rstest::rstest;
+    use tower::util::ServiceExt;
+
+    #[rstest]
+    fn in_flight_gauges_follow_guards() {
+        let metrics = Metrics::new();
+        let upstream: Uri = "http://127.0.0.1:8545".parse().unwrap();
+        let request = metrics.request_started();
+        let first = metrics.upstream_started(&upstream);
+        let second = metrics.upstream_started(&upstream);
+        assert_eq!(metrics.in_flight.get(), 1);
+        assert_eq!(
+            metrics
+                .upstream_in_flight
+                .with_label_values(&[upstream.to_string()])
+                .get(),
+            2
+        );
+
+        drop((request, first, second));
+        assert_eq!(metrics.in_flight.get(), 0);
+        assert!(
+            metrics.render().contains(
+                r#"veto_upstream_requests_in_flight{upstream="http://127.0.0.1:8545/"} 0"#
+            )
+        );
+    }
+
+    #[rstest]
+    fn oversized_methods_share_a_label() {
+        assert_eq!(method_label("eth_call"), "eth_call");
+        assert_eq!(method_label(&"x".repeat(65)), "oversized");
+    }
+
+    #[tokio::test]
+    async fn metrics_route_serves_text_format() {
+        let metrics = Arc::new(Metrics::new());
+        metrics.record_forwarded("eth_call");
+        metrics.record_parse_error(&JsonRpcError::InvalidRequest("empty body".into()));
+
+        let response = metrics_router(metrics)
+            .oneshot(Request::get("/metrics").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains(r#"veto_requests_forwarded_total{method="eth_call"} 1"#));
+        assert!(body.contains(r#"veto_parse_errors_total{error="invalid_request"} 1"#));
+    }
+}
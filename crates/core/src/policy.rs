@@ -0,0 +1,601 @@
+use crate::auth::tokens_match;
+use crate::errors::ProxyError;
+use crate::rate_limit::ClientKey;
+use crate::rate_limit::LimitExceeded;
+use crate::rules::{ParamRuleSet, TxRuleSet};
+use crate::tx::{DecodedTransaction, TxDecodeError, decode_raw_transaction};
+use arc_swap::{ArcSwap, Guard};
+use axum::http::StatusCode;
+use serde_json::{Value, json};
+use std::sync::{Arc, Mutex, PoisonError};
+use veto_config::{ApiKey, ChainIdSetting, Config, MethodMatcher, PolicyMode};
+
+/// Lowercase name fragment shared by every raw transaction submission method.
+const RAW_TRANSACTION_METHOD: &str = "sendrawtransaction";
+
+/// Method filtering policy compiled from the resolved [`Config`].
+#[derive(Debug, Clone)]
+pub(crate) struct MethodPolicy {
+    mode: PolicyMode,
+    blocked: MethodMatcher,
+    allowed: MethodMatcher,
+    param_rules: ParamRuleSet,
+    tx_rules: TxRuleSet,
+    chain_id: Option<u64>,
+}
+
+/// Reason a JSON-RPC method was refused by the [`MethodPolicy`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Rejection {
+    /// The method matched the given blocklist rule (an exact name or a pattern).
+    Blocked(String),
+    /// The proxy runs in allowlist mode and the method is not listed.
+    NotAllowed,
+    /// The request parameters matched the named parameter rule.
+    Params(String),
+    /// The decoded raw transaction matched the named transaction rule.
+    Transaction {
+        /// Name of the matching rule.
+        rule: String,
+        /// Decoded transaction, reported back in the error data.
+        tx: Box<DecodedTransaction>,
+    },
+    /// The raw transaction was signed for a different chain than the pinned one.
+    ChainIdMismatch {
+        /// Pinned chain id.
+        expected: u64,
+        /// Decoded transaction, reported back in the error data.
+        tx: Box<DecodedTransaction>,
+    },
+    /// The raw transaction is a legacy transaction without EIP-155 replay protection.
+    UnprotectedTransaction {
+        /// Pinned chain id.
+        expected: u64,
+        /// Decoded transaction, reported back in the error data.
+        tx: Box<DecodedTransaction>,
+    },
+    /// The raw transaction could not be decoded, so transaction rules cannot be enforced.
+    InvalidTransaction(TxDecodeError),
+    /// The client used up a rate limit.
+    RateLimited(LimitExceeded),
+    /// API keys are required and the client presented none, or one that is not configured.
+    Unauthorized,
+}
+
+impl Rejection {
+    /// Human-readable message returned in the JSON-RPC error payload.
+    pub(crate) fn message(&self, method: &str) -> String {
+        match self {
+            Self::Blocked(_) => format!("Method '{method}' blocked by veto proxy"),
+            Self::NotAllowed => format!(
+                "Method '{method}' blocked by veto proxy: {} mode only forwards listed methods",
+                PolicyMode::Allowlist
+            ),
+            Self::Params(rule) => {
+                format!("Method '{method}' blocked by veto proxy: parameters matched rule '{rule}'")
+            }
+            Self::Transaction { rule, tx } => {
+                format!("Transaction blocked by veto proxy: rule '{rule}' matched {tx}")
+            }
+            Self::ChainIdMismatch { expected, tx } => format!(
+                "Transaction blocked by veto proxy: chain id {:#x} does not match pinned chain id {expected:#x}",
+                tx.chain_id.unwrap_or_default()
+            ),
+            Self::UnprotectedTransaction { expected, .. } => format!(
+                "Transaction blocked by veto proxy: legacy transaction without EIP-155 chain id, expected chain id {expected:#x}"
+            ),
+            Self::InvalidTransaction(error) => {
+                format!("Method '{method}' blocked by veto proxy: {error}")
+            }
+            Self::RateLimited(_) => "limit exceeded".into(),
+            Self::Unauthorized => "missing or invalid API key".into(),
+        }
+    }
+
+    /// Name of the rule that refused the request, used as a metrics label.
+    ///
+    /// Named rules report their name; built-in checks report a fixed label.
+    pub(crate) fn rule(&self) -> &str {
+        match self {
+            Self::Blocked(rule) | Self::Params(rule) | Self::Transaction { rule, .. } => rule,
+            Self::NotAllowed => "allowlist",
+            Self::ChainIdMismatch { .. } => "chain_id",
+            Self::UnprotectedTransaction { .. } => "replay_protection",
+            Self::InvalidTransaction(_) => "invalid_transaction",
+            Self::RateLimited(_) => "rate_limit",
+            Self::Unauthorized => "api_key",
+        }
+    }
+
+    /// JSON-RPC error code:`-32003` (transaction rejected) for transaction checks, `-32602`
+    /// for undecodable transactions, `-32005` (limit exceeded) for rate limits, `-32001` for
+    /// missing API keys, and `-32601` otherwise.
+    pub(crate) const fn code(&self) -> i64 {
+        match self {
+            Self::Transaction { .. }
+            | Self::ChainIdMismatch { .. }
+            | Self::UnprotectedTransaction { .. } => -32003,
+            Self::InvalidTransaction(_) => -32602,
+            Self::RateLimited(_) => -32005,
+            Self::Unauthorized => -32001,
+            Self::Blocked(_) | Self::NotAllowed | Self::Params(_) => -32601,
+        }
+    }
+
+    /// HTTP status of a response refused for this reason: `401` for missing API keys, `429` for
+    /// rate limits, and `200` otherwise.
+    pub(crate) const fn status(&self) -> StatusCode {
+        match self {
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            _ => StatusCode::OK,
+        }
+    }
+
+    /// How long the client should wait before retrying, for rate limit rejections.
+    pub(crate) const fn retry_after(&self) -> Option<u64> {
+        match self {
+            Self::RateLimited(exceeded) => Some(exceeded.retry_after_secs()),
+            _ => None,
+        }
+    }
+
+    /// Optional `data` member of the JSON-RPC error.
+    pub(crate) fn data(&self) -> Option<Value> {
+        match self {
+            Self::Transaction { rule, tx } => Some(json!({
+                "rule": rule,
+                "transaction": tx.to_json(),
+            })),
+            Self::ChainIdMismatch { expected, tx }
+            | Self::UnprotectedTransaction { expected, tx } => Some(json!({
+                "expectedChainId": format!("{expected:#x}"),
+                "transaction": tx.to_json(),
+            })),
+            Self::RateLimited(exceeded) => Some(json!({
+                "limit": exceeded.limit,
+                "retryAfter": exceeded.retry_after_secs(),
+            })),
+            _ => None,
+        }
+    }
+}
+
+impl MethodPolicy {
+    /// Compile the method rules of the resolved [`Config`] into a policy.
+    pub(crate) fn from_config(config: &Config) -> Result<Self, ProxyError> {
+        Ok(Self {
+            mode: config.mode(),
+            blocked: MethodMatcher::new(config.blocked_methods()).map_err(ProxyError::Policy)?,
+            allowed: MethodMatcher::new(config.allowed_methods()).map_err(ProxyError::Policy)?,
+            param_rules: ParamRuleSet::new(config.param_rules())?,
+            tx_rules: TxRuleSet::new(config.tx_rules())?,
+            chain_id: match config.chain_id() {
+                None => None,
+                Some(ChainIdSetting::Fixed(chain_id)) => Some(chain_id),
+                Some(ChainIdSetting::Upstream) => return Err(ProxyError::UnresolvedChainId),
+            },
+        })
+    }
+
+    /// Check a method name (case-insensitive) and its `params`, returning the [`Rejection`]
+    /// when the call is refused.
+    ///
+    /// Method rules are evaluated first; parameter rules then apply in either mode, followed by
+    /// transaction rules for raw transaction submissions.
+    pub(crate) fn check(&self, method: &str, params: &Value) -> Option<Rejection> {
+        let rejection = match self.mode {
+            PolicyMode::Blocklist => self
+                .blocked
+                .find(method)
+                .map(|rule| Rejection::Blocked(rule.to_string())),
+            PolicyMode::Allowlist => {
+                (!self.allowed.matches(method)).then_some(Rejection::NotAllowed)
+            }
+        };
+
+        rejection
+            .or_else(|| {
+                self.param_rules
+                    .find(method, params)
+                    .map(|rule| Rejection::Params(rule.to_string()))
+            })
+            .or_else(|| self.check_transaction(method, params))
+    }
+
+    /// Decode raw transaction payloads, enforce the pinned chain id and apply the transaction
+    /// rules.
+    ///
+    /// Every method whose name contains `sendRawTransaction` counts as a submission carrying the
+    /// payload in its first param, so variants such as `eth_sendRawTransactionSync` and
+    /// `eth_sendRawTransactionConditional` are checked too. Undecodable payloads are refused
+    /// while any check is configured, so an encoding the decoder does not understand cannot slip
+    /// past them.
+    fn check_transaction(&self, method: &str, params: &Value) -> Option<Rejection> {
+        if (self.tx_rules.is_empty() && self.chain_id.is_none())
+            || !method.to_ascii_lowercase().contains(RAW_TRANSACTION_METHOD)
+        {
+            return None;
+        }
+
+        let decoded = params
+            .get(0)
+            .and_then(Value::as_str)
+            .ok_or(TxDecodeError::InvalidHex)
+            .and_then(decode_raw_transaction);
+        let tx = match decoded {
+            Ok(tx) => tx,
+            Err(error) => return Some(Rejection::InvalidTransaction(error)),
+        };
+
+        if let Some(expected) = self.chain_id {
+            match tx.chain_id {
+                None => {
+                    return Some(Rejection::UnprotectedTransaction {
+                        expected,
+                        tx: Box::new(tx),
+                    });
+                }
+                Some(found) if found != expected => {
+                    return Some(Rejection::ChainIdMismatch {
+                        expected,
+                        tx: Box::new(tx),
+                    });
+                }
+                Some(_) => {}
+            }
+        }
+
+        self.tx_rules.find(&tx).map(|rule| Rejection::Transaction {
+            rule: rule.to_string(),
+            tx: Box::new(tx),
+        })
+    }
+
+    /// Number of method rules enforced by the active mode.
+    pub(crate) fn rule_count(&self) -> usize {
+        match self.mode {
+            PolicyMode::Blocklist => self.blocked.len(),
+            PolicyMode::Allowlist => self.allowed.len(),
+        }
+    }
+
+    /// Number of parameter-aware rules.
+    pub(crate) const fn param_rule_count(&self) -> usize {
+        self.param_rules.len()
+    }
+
+    /// Number of transaction rules.
+    pub(crate) const fn tx_rule_count(&self) -> usize {
+        self.tx_rules.len()
+    }
+
+    /// Chain id raw transactions are pinned to, if any.
+    pub(crate) const fn chain_id(&self) -> Option<u64> {
+        self.chain_id
+    }
+
+    /// Active [`PolicyMode`].
+    pub(crate) const fn mode(&self) -> PolicyMode {
+        self.mode
+    }
+}
+
+/// The [`MethodPolicy`] of calls made with an API key.
+#[derive(Debug)]
+pub(crate) struct KeyPolicy {
+    /// Name of the key.
+    pub(crate) name: Arc<str>,
+    token: String,
+    /// Policy compiled from the key's settings over the top-level ones.
+    pub(crate) policy: MethodPolicy,
+}
+
+impl KeyPolicy {
+    fn new(config: &Config, key: &ApiKey) -> Result<Self, ProxyError> {
+        let mut config = config.clone().with_mode(key.mode.unwrap_or(config.mode()));
+        if let Some(blocked) = &key.blocked_methods {
+            config = config.with_blocked_methods(blocked.iter().cloned().collect());
+        }
+        if let Some(allowed) = &key.allowed_methods {
+            config = config.with_allowed_methods(allowed.iter().cloned().collect());
+        }
+        Ok(Self {
+            name: Arc::from(key.name.as_str()),
+            token: key.key.clone(),
+            policy: MethodPolicy::from_config(&config)?,
+        })
+    }
+}
+
+/// A compiled [`MethodPolicy`] together with the [`Config`] it was compiled from.
+#[derive(Debug)]
+pub(crate) struct ActivePolicy {
+    /// Effective configuration; only its policy settings can change after start-up.
+    pub(crate) config: Config,
+    /// Policy compiled from `config`.
+    pub(crate) policy: MethodPolicy,
+    /// Policies of the configured API keys; empty when keys are not required.
+    pub(crate) keys: Vec<KeyPolicy>,
+}
+
+impl ActivePolicy {
+    /// Compile the policy of `config` and of each of its API keys.
+    pub(crate) fn new(config: Config) -> Result<Self, ProxyError> {
+        Ok(Self {
+            policy: MethodPolicy::from_config(&config)?,
+            keys: config
+                .api_keys()
+                .iter()
+                .map(|key| KeyPolicy::new(&config, key))
+                .collect::<Result<_, _>>()?,
+            config,
+        })
+    }
+
+    /// Key whose token is `token`, comparing each token in constant time.
+    pub(crate) fn find_key(&self, token: &str) -> Option<&KeyPolicy> {
+        self.keys
+            .iter()
+            .find(|key| tokens_match(token.as_bytes(), key.token.as_bytes()))
+    }
+
+    /// Policy applying to `client`, or `None` when its API key is no longer configured.
+    pub(crate) fn policy_for(&self, client: &ClientKey) -> Option<&MethodPolicy> {
+        match client {
+            ClientKey::Ip(_) => Some(&self.policy),
+            ClientKey::ApiKey(name) => self
+                .keys
+                .iter()
+                .find(|key| key.name == *name)
+                .map(|key| &key.policy),
+        }
+    }
+
+    /// Keep the current configuration but take every policy setting from `rules`.
+    pub(crate) fn with_rules_from(&self, rules: &Config) -> Result<Self, ProxyError> {
+        Self::new(
+            self.config
+                .clone()
+                .with_mode(rules.mode())
+                .with_blocked_methods(rules.blocked_methods().clone())
+                .with_allowed_methods(rules.allowed_methods().clone())
+                .with_param_rules(rules.param_rules().to_vec())
+                .with_tx_rules(rules.tx_rules().to_vec())
+                .with_chain_id(rules.chain_id())
+                .with_api_keys(rules.api_keys().to_vec())
+                .with_client_access(rules.client_access().cloned()),
+        )
+    }
+}
+
+/// The [`ActivePolicy`] shared by every request handler.
+///
+/// Readers never block; updates are serialized so concurrent read-modify-write changes cannot
+/// overwrite each other.
+#[derive(Debug)]
+pub(crate) struct PolicyStore {
+    active: ArcSwap<ActivePolicy>,
+    update: Mutex<()>,
+}
+
+/// Policies before and after an update.
+#[derive(Debug)]
+pub(crate) struct PolicySwap {
+    /// Policy replaced by the update.
+    pub(crate) previous: Arc<ActivePolicy>,
+    /// Policy now in force.
+    pub(crate) current: Arc<ActivePolicy>,
+}
+
+impl PolicyStore {
+    /// Create a store around the start-up policy.
+    pub(crate) fn new(active: ActivePolicy) -> Self {
+        Self {
+            active: ArcSwap::from_pointee(active),
+            update: Mutex::new(()),
+        }
+    }
+
+    /// Policy in force.
+    pub(crate) fn load(&self) -> Guard<Arc<ActivePolicy>> {
+        self.active.load()
+    }
+
+    /// Derive a new policy from the current one and swap it in.
+    ///
+    /// Nothing changes when `update` fails. Requests already being checked finish against the
+    /// previous policy.
+    pub(crate) fn update<E>(
+        &self,
+        update: impl FnOnce(&ActivePolicy) -> Result<ActivePolicy, E>,
+    ) -> Result<PolicySwap, E> {
+        let _guard = self.update.lock().unwrap_or_else(PoisonError::into_inner);
+        let previous = self.active.load_full();
+        let current = Arc::new(update(&previous)?);
+        self.active.store(Arc::clone(&current));
+        Ok(PolicySwap { previous, current })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+    use std::collections::HashSet;
+    use veto_config::{ParamRule, TxRule};
+
+    fn config(mode: PolicyMode) -> Config {
+        Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            "http://127.0.0.1:8545".parse().unwrap(),
+            HashSet::from(["anvil_mine".to_string(), "debug_*".to_string()]),
+        )
+        .with_mode(mode)
+        .with_allowed_methods(HashSet::from([
+            "eth_chainid".to_string(),
+            "eth_get*".to_string(),
+        ]))
+    }
+
+    #[rstest]
+    #[case::blocked("anvil_mine", Some("anvil_mine"))]
+    #[case::case_insensitive("ANVIL_MINE", Some("anvil_mine"))]
+    #[case::pattern("debug_traceCall", Some("debug_*"))]
+    #[case::unlisted("eth_call", None)]
+    fn blocklist_mode(#[case] method: &str, #[case] rule: Option<&str>) {
+        let policy = MethodPolicy::from_config(&config(PolicyMode::Blocklist)).unwrap();
+        let expected = rule.map(|rule| Rejection::Blocked(rule.to_string()));
+        assert_eq!(policy.check(method, &Value::Null), expected);
+    }
+
+    #[rstest]
+    #[case::allowed("eth_chainId", None)]
+    #[case::pattern("eth_getBalance", None)]
+    #[case::unlisted("eth_call", Some(Rejection::NotAllowed))]
+    #[case::blocklist_is_ignored("anvil_mine", Some(Rejection::NotAllowed))]
+    fn allowlist_mode(#[case] method: &str, #[case] expected: Option<Rejection>) {
+        let policy = MethodPolicy::from_config(&config(PolicyMode::Allowlist)).unwrap();
+        assert_eq!(policy.check(method, &Value::Null), expected);
+    }
+
+    #[rstest]
+    #[case::blocklist(PolicyMode::Blocklist)]
+    #[case::allowlist(PolicyMode::Allowlist)]
+    fn param_rules_apply_in_both_modes(#[case] mode: PolicyMode) {
+        let rule: ParamRule = serde_json::from_value(json!({
+            "name": "pending-only",
+            "method": "eth_getBalance",
+            "when": [{ "path": "$[1]", "equals": "pending" }]
+        }))
+        .unwrap();
+        let policy = MethodPolicy::from_config(&config(mode).with_param_rules(vec![rule])).unwrap();
+
+        assert_eq!(
+            policy.check("eth_getBalance", &json!(["0x01", "pending"])),
+            Some(Rejection::Params("pending-only".to_string()))
+        );
+        assert_eq!(
+            policy.check("eth_getBalance", &json!(["0x01", "latest"])),
+            None
+        );
+        assert_eq!(policy.param_rule_count(), 1);
+    }
+
+    #[rstest]
+    fn tx_rules_police_raw_transactions() {
+        let rule = TxRule {
+            name: "chain-one".into(),
+            tx_type: Some(vec![0]),
+            ..TxRule::default()
+        };
+        let policy =
+            MethodPolicy::from_config(&config(PolicyMode::Blocklist).with_tx_rules(vec![rule]))
+                .unwrap();
+
+        let rejection = policy
+            .check(
+                "eth_sendRawTransaction",
+                &json!([crate::tx::tests::EIP155_EXAMPLE]),
+            )
+            .unwrap();
+        assert!(matches!(&rejection, Rejection::Transaction { rule, .. } if rule == "chain-one"));
+        assert_eq!(rejection.code(), -32003);
+        assert_eq!(rejection.data().unwrap()["transaction"]["chainId"], "0x1");
+
+        let rejection = policy
+            .check("eth_sendRawTransaction", &json!(["0xdeadbeef"]))
+            .unwrap();
+        assert!(matches!(rejection, Rejection::InvalidTransaction(_)));
+        assert_eq!(rejection.code(), -32602);
+        assert_eq!(policy.tx_rule_count(), 1);
+    }
+
+    #[rstest]
+    #[case::sync("eth_sendRawTransactionSync")]
+    #[case::conditional("eth_sendRawTransactionConditional")]
+    #[case::alias("anvil_sendRawTransaction")]
+    #[case::case_insensitive("ETH_SENDRAWTRANSACTION")]
+    fn tx_rules_police_raw_transaction_variants(#[case] method: &str) {
+        let rule = TxRule {
+            name: "legacy".into(),
+            tx_type: Some(vec![0]),
+            ..TxRule::default()
+        };
+        let policy =
+            MethodPolicy::from_config(&config(PolicyMode::Blocklist).with_tx_rules(vec![rule]))
+                .unwrap();
+
+        let params = json!([crate::tx::tests::EIP155_EXAMPLE, {}]);
+        assert!(matches!(
+            policy.check(method, &params),
+            Some(Rejection::Transaction { .. })
+        ));
+        assert!(matches!(
+            policy.check(method, &json!(["0xdeadbeef"])),
+            Some(Rejection::InvalidTransaction(_))
+        ));
+    }
+
+    #[rstest]
+    #[case::matching(1, None)]
+    #[case::mismatched(10, Some(-32003))]
+    fn chain_id_is_pinned(#[case] pinned: u64, #[case] code: Option<i64>) {
+        let policy = MethodPolicy::from_config(
+            &config(PolicyMode::Blocklist).with_chain_id(Some(ChainIdSetting::Fixed(pinned))),
+        )
+        .unwrap();
+        let rejection = policy.check(
+            "eth_sendRawTransaction",
+            &json!([crate::tx::tests::EIP155_EXAMPLE]),
+        );
+        assert_eq!(rejection.as_ref().map(Rejection::code), code);
+        if let Some(rejection) = rejection {
+            assert!(matches!(
+                rejection,
+                Rejection::ChainIdMismatch { expected: 10, .. }
+            ));
+            assert_eq!(rejection.data().unwrap()["expectedChainId"], "0xa");
+        }
+    }
+
+    #[rstest]
+    fn unprotected_transactions_are_rejected_when_pinned() {
+        let policy = MethodPolicy::from_config(
+            &config(PolicyMode::Blocklist).with_chain_id(Some(ChainIdSetting::Fixed(1))),
+        )
+        .unwrap();
+        let rejection = policy
+            .check(
+                "eth_sendRawTransaction",
+                &json!([crate::tx::tests::unprotected_legacy_tx()]),
+            )
+            .unwrap();
+        assert!(matches!(
+            rejection,
+            Rejection::UnprotectedTransaction { expected: 1, .. }
+        ));
+        assert!(
+            rejection
+                .message("eth_sendRawTransaction")
+                .contains("EIP-155")
+        );
+    }
+
+    #[rstest]
+    fn unresolved_upstream_chain_id_is_an_error() {
+        let config = config(PolicyMode::Blocklist).with_chain_id(Some(ChainIdSetting::Upstream));
+        assert!(matches!(
+            MethodPolicy::from_config(&config),
+            Err(ProxyError::UnresolvedChainId)
+        ));
+    }
+
+    #[rstest]
+    fn allowlist_message_names_the_mode() {
+        let message = Rejection::NotAllowed.message("eth_call");
+        assert!(message.contains("allowlist mode"));
+        assert!(message.contains("eth_call"));
+    }
+}
@@ -0,0 +1,340 @@
+use crate::errors::ProxyError;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use veto_config::{Config, MethodMatcher, RateLimit};
+
+/// Clients tracked before idle buckets are pruned.
+const PRUNE_THRESHOLD: usize = 1_024;
+
+/// Identity a client is rate limited by.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum ClientKey {
+    /// Remote IP address of the connection, used when API keys are disabled.
+    Ip(IpAddr),
+    /// Name of the API key the client presented.
+    ApiKey(Arc<str>),
+}
+
+impl ClientKey {
+    /// Key for a connection, grouping clients without a known address together.
+    pub(crate) fn from_addr(address: Option<SocketAddr>) -> Self {
+        Self::Ip(address.map_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED), |address| address.ip()))
+    }
+}
+
+/// A call refused because a limit's bucket was empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LimitExceeded {
+    /// Method (or `global`) of the limit that tripped.
+    pub(crate) limit: String,
+    /// Time until the client may call again.
+    pub(crate) retry_after: Duration,
+}
+
+impl LimitExceeded {
+    /// Whole seconds to wait, rounded up, as sent in `Retry-After`.
+    pub(crate) const fn retry_after_secs(&self) -> u64 {
+        let secs = self.retry_after.as_secs() + (self.retry_after.subsec_nanos() > 0) as u64;
+        if secs == 0 { 1 } else { secs }
+    }
+}
+
+/// A configured limit with its method matcher compiled.
+#[derive(Debug)]
+struct Limit {
+    name: String,
+    methods: Option<MethodMatcher>,
+    rate: f64,
+    burst: f64,
+}
+
+impl Limit {
+    fn applies_to(&self, method: &str) -> bool {
+        self.methods
+            .as_ref()
+            .is_none_or(|methods| methods.matches(method))
+    }
+}
+
+/// Tokens left for one client under one limit.
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    const fn full(limit: &Limit, now: Instant) -> Self {
+        Self {
+            tokens: limit.burst,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, limit: &Limit, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.rate).min(limit.burst);
+        self.updated = now;
+    }
+}
+
+#[derive(Debug)]
+struct Buckets {
+    clients: HashMap<ClientKey, Vec<Bucket>>,
+    prune_at: usize,
+}
+
+/// Token-bucket rate limiter keyed by [`ClientKey`].
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    limits: Vec<Limit>,
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimiter {
+    /// Compile the configured limits, returning `None` when there are none.
+    pub(crate) fn from_limits(limits: &[RateLimit]) -> Result<Option<Self>, ProxyError> {
+        if limits.is_empty() {
+            return Ok(None);
+        }
+        let limits = limits
+            .iter()
+            .map(|limit| {
+                Ok(Limit {
+                    name: limit.display_name().to_owned(),
+                    methods: limit
+                        .method
+                        .as_ref()
+                        .map(|method| MethodMatcher::new([method]))
+                        .transpose()
+                        .map_err(ProxyError::Policy)?,
+                    rate: f64::from(limit.requests_per_second),
+                    burst: f64::from(limit.burst()),
+                })
+            })
+            .collect::<Result<_, ProxyError>>()?;
+
+        Ok(Some(Self {
+            limits,
+            buckets: Mutex::new(Buckets {
+                clients: HashMap::new(),
+                prune_at: PRUNE_THRESHOLD,
+            }),
+        }))
+    }
+
+    /// Take one token from every limit that applies to `method`.
+    ///
+    /// Nothing is taken when any of them is empty; the error names the limit that frees up
+    /// last.
+    pub(crate) fn check(&self, client: &ClientKey, method: &str) -> Result<(), LimitExceeded> {
+        self.check_at(client, method, Instant::now())
+    }
+
+    fn check_at(
+        &self,
+        client: &ClientKey,
+        method: &str,
+        now: Instant,
+    ) -> Result<(), LimitExceeded> {
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        if buckets.clients.len() >= buckets.prune_at {
+            self.prune(&mut buckets, now);
+        }
+        let client_buckets = buckets.clients.entry(client.clone()).or_insert_with(|| {
+            self.limits
+                .iter()
+                .map(|limit| Bucket::full(limit, now))
+                .collect()
+        });
+
+        let mut exceeded: Option<LimitExceeded> = None;
+        for (limit, bucket) in self.limits.iter().zip(client_buckets.iter_mut()) {
+            if !limit.applies_to(method) {
+                continue;
+            }
+            bucket.refill(limit, now);
+            if bucket.tokens < 1.0 {
+                let retry_after = Duration::from_secs_f64((1.0 - bucket.tokens) / limit.rate);
+                if exceeded
+                    .as_ref()
+                    .is_none_or(|exceeded| retry_after > exceeded.retry_after)
+                {
+                    exceeded = Some(LimitExceeded {
+                        limit: limit.name.clone(),
+                        retry_after,
+                    });
+                }
+            }
+        }
+        if let Some(exceeded) = exceeded {
+            return Err(exceeded);
+        }
+
+        for (limit, bucket) in self.limits.iter().zip(client_buckets.iter_mut()) {
+            if limit.applies_to(method) {
+                bucket.tokens -= 1.0;
+            }
+        }
+        Ok(())
+    }
+
+    /// Forget clients whose buckets have refilled, since a fresh bucket is identical.
+    fn prune(&self, buckets: &mut Buckets, now: Instant) {
+        buckets.clients.retain(|_, client| {
+            self.limits
+                .iter()
+                .zip(client.iter_mut())
+                .any(|(limit, bucket)| {
+                    bucket.refill(limit, now);
+                    bucket.tokens < limit.burst
+                })
+        });
+        buckets.prune_at = PRUNE_THRESHOLD.max(buckets.clients.len() * 2);
+    }
+}
+
+/// The shared [`RateLimiter`] and those of API keys with limits of their own.
+#[derive(Debug, Default)]
+pub(crate) struct RateLimits {
+    shared: Option<RateLimiter>,
+    keys: HashMap<String, Option<RateLimiter>>,
+}
+
+impl RateLimits {
+    /// Compile the top-level limits and the limits of every API key that sets them.
+    ///
+    /// Keys added by a later reload share the top-level limits until the proxy restarts.
+    pub(crate) fn from_config(config: &Config) -> Result<Self, ProxyError> {
+        let keys = config
+            .api_keys()
+            .iter()
+            .filter_map(|key| Some((key.name.clone(), key.rate_limits.as_deref()?)))
+            .map(|(name, limits)| Ok((name, RateLimiter::from_limits(limits)?)))
+            .collect::<Result<_, ProxyError>>()?;
+        Ok(Self {
+            shared: RateLimiter::from_limits(config.rate_limits())?,
+            keys,
+        })
+    }
+
+    /// Take a token for `method` from the limiter that applies to `client`.
+    pub(crate) fn check(&self, client: &ClientKey, method: &str) -> Result<(), LimitExceeded> {
+        let limiter = match client {
+            ClientKey::ApiKey(name) => self
+                .keys
+                .get(name.as_ref())
+                .map_or(self.shared.as_ref(), Option::as_ref),
+            ClientKey::Ip(_) => self.shared.as_ref(),
+        };
+        limiter.map_or(Ok(()), |limiter| limiter.check(client, method))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn build(limits: &[(Option<&str>, u32, Option<u32>)]) -> RateLimiter {
+        let limits: Vec<_> = limits
+            .iter()
+            .map(|(method, requests_per_second, burst)| RateLimit {
+                method: method.map(Into::into),
+                requests_per_second: *requests_per_second,
+                burst: *burst,
+            })
+            .collect();
+        RateLimiter::from_limits(&limits).unwrap().unwrap()
+    }
+
+    fn client(last: u8) -> ClientKey {
+        ClientKey::Ip(IpAddr::V4(Ipv4Addr::new(10, 0, 0, last)))
+    }
+
+    #[rstest]
+    fn buckets_refill_per_client() {
+        let limiter = build(&[(None, 2, Some(2))]);
+        let now = Instant::now();
+        assert!(limiter.check_at(&client(1), "eth_call", now).is_ok());
+        assert!(limiter.check_at(&client(1), "eth_call", now).is_ok());
+        let exceeded = limiter.check_at(&client(1), "eth_call", now).unwrap_err();
+        assert_eq!(exceeded.limit, "global");
+        assert_eq!(exceeded.retry_after, Duration::from_millis(500));
+
+        assert!(limiter.check_at(&client(2), "eth_call", now).is_ok());
+        let later = now + Duration::from_millis(500);
+        assert!(limiter.check_at(&client(1), "eth_call", later).is_ok());
+    }
+
+    #[rstest]
+    fn method_limits_only_count_matching_calls() {
+        let limiter = build(&[(None, 10, None), (Some("eth_*"), 1, None)]);
+        let now = Instant::now();
+        assert!(limiter.check_at(&client(1), "eth_call", now).is_ok());
+        let exceeded = limiter
+            .check_at(&client(1), "eth_getLogs", now)
+            .unwrap_err();
+        assert_eq!(exceeded.limit, "eth_*");
+        assert!(limiter.check_at(&client(1), "net_version", now).is_ok());
+
+        // A refused call takes no tokens from the other limits.
+        let limiter = build(&[(None, 2, None), (Some("eth_call"), 1, None)]);
+        assert!(limiter.check_at(&client(1), "eth_call", now).is_ok());
+        assert!(limiter.check_at(&client(1), "eth_call", now).is_err());
+        assert!(limiter.check_at(&client(1), "net_version", now).is_ok());
+        assert!(limiter.check_at(&client(1), "net_version", now).is_err());
+    }
+
+    #[rstest]
+    fn keys_without_limits_share_the_top_level_ones() {
+        let key = |name: &str, rate_limits: Option<Vec<RateLimit>>| veto_config::ApiKey {
+            name: name.into(),
+            key: format!("{name}-secret"),
+            mode: None,
+            blocked_methods: None,
+            allowed_methods: None,
+            rate_limits,
+        };
+        let shared = RateLimit {
+            method: None,
+            requests_per_second: 1,
+            burst: None,
+        };
+        let config = Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            "http://127.0.0.1:8545".parse().unwrap(),
+            Default::default(),
+        )
+        .with_rate_limits(vec![shared])
+        .with_api_keys(vec![key("ci", Some(Vec::new())), key("tooling", None)]);
+        let limits = RateLimits::from_config(&config).unwrap();
+
+        let ci = ClientKey::ApiKey(Arc::from("ci"));
+        let tooling = ClientKey::ApiKey(Arc::from("tooling"));
+        let added = ClientKey::ApiKey(Arc::from("added"));
+        for _ in 0..3 {
+            assert!(limits.check(&ci, "eth_call").is_ok());
+        }
+        assert!(limits.check(&tooling, "eth_call").is_ok());
+        assert!(limits.check(&tooling, "eth_call").is_err());
+        assert!(limits.check(&added, "eth_call").is_ok());
+    }
+
+    #[rstest]
+    fn idle_clients_are_pruned() {
+        let limiter = build(&[(None, 1, None)]);
+        let now = Instant::now();
+        for last in 0..=u8::MAX {
+            for third in 0..4 {
+                let key = ClientKey::Ip(IpAddr::V4(Ipv4Addr::new(10, 0, third, last)));
+                limiter.check_at(&key, "eth_call", now).unwrap();
+            }
+        }
+        let later = now + Duration::from_secs(1);
+        limiter.check_at(&client(1), "eth_call", later).unwrap();
+        assert_eq!(limiter.buckets.lock().unwrap().clients.len(), 1);
+    }
+}
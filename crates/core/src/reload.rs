@@ -0,0 +1,438 @@
+use crate::chain::resolve_chain_id;
+use crate::errors::ProxyError;
+use crate::server::AppState;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tracing::{info, warn};
+use veto_config::{Config, ConfigError, ConfigSource, RateLimit};
+
+/// Quiet period after a file event before reloading, so editors that write in several steps
+/// trigger a single reload.
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// What asked for a reload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Trigger {
+    /// The configuration file was written, replaced or removed.
+    FileChanged,
+    /// The process received `SIGHUP`.
+    Hangup,
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::FileChanged => "file change",
+            Self::Hangup => "SIGHUP",
+        })
+    }
+}
+
+/// Reload the method policy whenever the configuration file changes or the process receives
+/// `SIGHUP`.
+///
+/// A reload that fails to parse or validate is logged and the running policy is kept.
+pub(crate) fn spawn_reload(state: AppState, source: ConfigSource) -> Result<(), ProxyError> {
+    let (sender, mut triggers) = mpsc::unbounded_channel();
+
+    let name = source.path().file_name().map(ToOwned::to_owned);
+    let file_changes = sender.clone();
+    let mut watcher =
+        notify::recommended_watcher(move |event: notify::Result<Event>| match event {
+            Ok(event)
+                if !matches!(event.kind, EventKind::Access(_))
+                    && event
+                        .paths
+                        .iter()
+                        .any(|path| path.file_name() == name.as_deref()) =>
+            {
+                let _ = file_changes.send(Trigger::FileChanged);
+            }
+            Ok(_) => {}
+            Err(error) => warn!(%error, "configuration file watcher failed"),
+        })
+        .map_err(ProxyError::Watch)?;
+    // Watch the directory rather than the file: editors often replace the file on save.
+    watcher
+        .watch(watch_dir(source.path()), RecursiveMode::NonRecursive)
+        .map_err(ProxyError::Watch)?;
+
+    #[cfg(unix)]
+    forward_sighup(sender)?;
+    #[cfg(not(unix))]
+    drop(sender);
+
+    info!(
+        path = %source.path().display(),
+        "reloading configuration on file change or SIGHUP"
+    );
+    tokio::spawn(async move {
+        let _watcher = watcher;
+        let reloader = Reloader::new(state, source);
+        while let Some(trigger) = triggers.recv().await {
+            if trigger == Trigger::FileChanged {
+                tokio::time::sleep(RELOAD_DEBOUNCE).await;
+            }
+            while triggers.try_recv().is_ok() {}
+            reloader.reload(trigger).await;
+        }
+    });
+    Ok(())
+}
+
+#[cfg(unix)]
+fn forward_sighup(sender: UnboundedSender<Trigger>) -> Result<(), ProxyError> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut hangup = signal(SignalKind::hangup()).map_err(ProxyError::Signal)?;
+    tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            if sender.send(Trigger::Hangup).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Directory holding the configuration file.
+fn watch_dir(path: &Path) -> &Path {
+    path.parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."))
+}
+
+/// Re-resolves the configuration and swaps the policy of the running proxy.
+#[derive(Debug)]
+struct Reloader {
+    state: AppState,
+    source: ConfigSource,
+}
+
+impl Reloader {
+    const fn new(state: AppState, source: ConfigSource) -> Self {
+        Self { state, source }
+    }
+
+    async fn reload(&self, trigger: Trigger) {
+        let next = match self.load().await {
+            Ok(next) => next,
+            Err(error) => {
+                warn!(%trigger, %error, "configuration reload failed; keeping the previous policy");
+                return;
+            }
+        };
+        let swap = match self
+            .state
+            .policy
+            .update(|active| active.with_rules_from(&next))
+        {
+            Ok(swap) => swap,
+            Err(error) => {
+                warn!(%trigger, %error, "configuration reload failed; keeping the previous policy");
+                return;
+            }
+        };
+
+        if log_policy_diff(&swap.previous.config, &swap.current.config) {
+            info!(%trigger, "configuration reloaded");
+        } else {
+            info!(%trigger, "configuration reloaded; method policy unchanged");
+        }
+        let pending = restart_required(&swap.current.config, &next);
+        if !pending.is_empty() {
+            warn!(
+                settings = pending.join(", "),
+                "changed settings take effect after a restart"
+            );
+        }
+    }
+
+    /// Resolve the file again, learning the chain id from the upstream if asked to.
+    async fn load(&self) -> Result<Config, ProxyError> {
+        let path = self.source.path();
+        if !path.exists() {
+            return Err(ProxyError::Config(ConfigError::Io {
+                path: path.to_path_buf(),
+                source: io::ErrorKind::NotFound.into(),
+            }));
+        }
+        let config = self.source.load().map_err(ProxyError::Config)?;
+        resolve_chain_id(config).await
+    }
+}
+
+/// Log what changed between two policies, returning whether anything did.
+pub(crate) fn log_policy_diff(old: &Config, new: &Config) -> bool {
+    let mut changed = false;
+    if old.mode() != new.mode() {
+        info!(from = %old.mode(), to = %new.mode(), "policy mode changed");
+        changed = true;
+    }
+    changed |= diff(
+        methods(old.blocked_methods()),
+        methods(new.blocked_methods()),
+    )
+    .log("blocked methods");
+    changed |= diff(
+        methods(old.allowed_methods()),
+        methods(new.allowed_methods()),
+    )
+    .log("allowed methods");
+    changed |= diff(
+        old.param_rules()
+            .iter()
+            .map(|rule| (rule.display_name(), rule)),
+        new.param_rules()
+            .iter()
+            .map(|rule| (rule.display_name(), rule)),
+    )
+    .log("parameter rules");
+    changed |= diff(
+        old.tx_rules().iter().map(|rule| (rule.name.as_str(), rule)),
+        new.tx_rules().iter().map(|rule| (rule.name.as_str(), rule)),
+    )
+    .log("transaction rules");
+    changed |= diff(
+        old.api_keys().iter().map(|key| (key.name.as_str(), key)),
+        new.api_keys().iter().map(|key| (key.name.as_str(), key)),
+    )
+    .log("API keys");
+    if old.client_access() != new.client_access() {
+        info!(
+            from = ?old.client_access(),
+            to = ?new.client_access(),
+            "client access lists changed"
+        );
+        changed = true;
+    }
+    if old.chain_id() != new.chain_id() {
+        info!(from = ?old.chain_id(), to = ?new.chain_id(), "pinned chain id changed");
+        changed = true;
+    }
+    changed
+}
+
+fn methods(methods: &HashSet<String>) -> impl Iterator<Item = (&str, &String)> {
+    methods.iter().map(|method| (method.as_str(), method))
+}
+
+/// Names added, removed and modified between two named collections, in sorted order.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Diff<'a> {
+    added: Vec<&'a str>,
+    removed: Vec<&'a str>,
+    modified: Vec<&'a str>,
+}
+
+impl Diff<'_> {
+    /// Log each non-empty list, returning whether anything changed.
+    fn log(&self, what: &str) -> bool {
+        if !self.added.is_empty() {
+            info!("{what} added: {}", self.added.join(", "));
+        }
+        if !self.removed.is_empty() {
+            info!("{what} removed: {}", self.removed.join(", "));
+        }
+        if !self.modified.is_empty() {
+            info!("{what} modified: {}", self.modified.join(", "));
+        }
+        !(self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty())
+    }
+}
+
+fn diff<'a, T: PartialEq + 'a>(
+    old: impl IntoIterator<Item = (&'a str, &'a T)>,
+    new: impl IntoIterator<Item = (&'a str, &'a T)>,
+) -> Diff<'a> {
+    let old: BTreeMap<_, _> = old.into_iter().collect();
+    let new: BTreeMap<_, _> = new.into_iter().collect();
+    let mut diff = Diff::default();
+    for (&name, &item) in &new {
+        match old.get(name) {
+            None => diff.added.push(name),
+            Some(&previous) if previous != item => diff.modified.push(name),
+            Some(_) => {}
+        }
+    }
+    diff.removed = old
+        .keys()
+        .filter(|name| !new.contains_key(*name))
+        .copied()
+        .collect();
+    diff
+}
+
+/// Settings that differ from the running proxy but are only read at startup.
+fn restart_required(running: &Config, new: &Config) -> Vec<&'static str> {
+    [
+        ("bind_address", running.bind_address() != new.bind_address()),
+        ("upstreams", running.upstreams() != new.upstreams()),
+        ("balance", running.balance() != new.balance()),
+        (
+            "upstream_backoff",
+            running.upstream_backoff() != new.upstream_backoff(),
+        ),
+        (
+            "upstream_timeouts",
+            running.upstream_timeouts() != new.upstream_timeouts(),
+        ),
+        (
+            "upstream_retries",
+            running.upstream_retries() != new.upstream_retries(),
+        ),
+        (
+            "idempotent_methods",
+            running.idempotent_methods() != new.idempotent_methods(),
+        ),
+        ("health_check", running.health_check() != new.health_check()),
+        (
+            "circuit_breaker",
+            running.circuit_breaker() != new.circuit_breaker(),
+        ),
+        (
+            "response_cache",
+            running.response_cache() != new.response_cache(),
+        ),
+        (
+            "max_batch_size",
+            running.max_batch_size() != new.max_batch_size(),
+        ),
+        (
+            "max_request_bytes",
+            running.max_request_bytes() != new.max_request_bytes(),
+        ),
+        ("upstream_tls", running.upstream_tls() != new.upstream_tls()),
+        ("listener_tls", running.listener_tls() != new.listener_tls()),
+        (
+            "metrics_address",
+            running.metrics_address() != new.metrics_address(),
+        ),
+        ("audit_log", running.audit_log() != new.audit_log()),
+        (
+            "rate_limits",
+            running.rate_limits() != new.rate_limits()
+                || key_rate_limits(running) != key_rate_limits(new),
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(name, changed)| changed.then_some(name))
+    .collect()
+}
+
+/// Rate limits of the API keys that set their own, by key name.
+fn key_rate_limits(config: &Config) -> BTreeMap<&str, &[RateLimit]> {
+    config
+        .api_keys()
+        .iter()
+        .filter_map(|key| Some((key.name.as_str(), key.rate_limits.as_deref()?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jsonrpc::JsonRpcRequest;
+    use crate::rate_limit::ClientKey;
+    use rstest::rstest;
+    use serde_json::Value;
+    use std::fs;
+    use veto_config::Overrides;
+
+    fn request(method: &str) -> JsonRpcRequest {
+        JsonRpcRequest {
+            method: method.into(),
+            params: Value::Null,
+            id: Value::Null,
+        }
+    }
+
+    #[rstest]
+    fn diff_reports_sorted_changes() {
+        let old = ["a", "b", "c"].map(|name| (name, &1));
+        let new = [("c", &2), ("d", &1), ("b", &1)];
+        assert_eq!(
+            diff(old, new),
+            Diff {
+                added: vec!["d"],
+                removed: vec!["a"],
+                modified: vec!["c"],
+            }
+        );
+    }
+
+    #[rstest]
+    fn restart_required_lists_startup_settings() {
+        let config = Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            "http://127.0.0.1:8545".parse().unwrap(),
+            HashSet::new(),
+        );
+        let next = config
+            .clone()
+            .with_max_batch_size(1)
+            .with_metrics_address(Some("127.0.0.1:9100".parse().unwrap()))
+            .with_chain_id(Some(veto_config::ChainIdSetting::Fixed(1)));
+        assert_eq!(
+            restart_required(&config, &next),
+            ["max_batch_size", "metrics_address"]
+        );
+    }
+
+    #[tokio::test]
+    async fn reload_swaps_policy_and_survives_invalid_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".veto.toml");
+        fs::write(&path, "use_defaults = false\n").unwrap();
+        let source = ConfigSource::new(path.clone(), Overrides::default());
+        let config = source.load().unwrap();
+        let state = AppState::try_from_config(config).unwrap();
+        let reloader = Reloader::new(state.clone(), source);
+        assert!(
+            state
+                .check_request(&ClientKey::from_addr(None), &request("eth_sign"))
+                .is_none()
+        );
+
+        fs::write(
+            &path,
+            "use_defaults = false\nblocked_methods = [\"eth_sign\"]\n",
+        )
+        .unwrap();
+        reloader.reload(Trigger::FileChanged).await;
+        assert!(
+            state
+                .check_request(&ClientKey::from_addr(None), &request("eth_sign"))
+                .is_some()
+        );
+
+        fs::write(&path, "blocked_methods = [\"debug_[\"]\n").unwrap();
+        reloader.reload(Trigger::Hangup).await;
+        assert!(
+            state
+                .check_request(&ClientKey::from_addr(None), &request("eth_sign"))
+                .is_some()
+        );
+        assert!(
+            state
+                .policy
+                .load()
+                .config
+                .blocked_methods()
+                .contains("eth_sign")
+        );
+
+        fs::remove_file(&path).unwrap();
+        reloader.reload(Trigger::Hangup).await;
+        assert!(
+            state
+                .check_request(&ClientKey::from_addr(None), &request("eth_sign"))
+                .is_some()
+        );
+    }
+}
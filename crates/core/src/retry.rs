@@ -0,0 +1,84 @@
+use crate::errors::ProxyError;
+use std::time::Duration;
+use veto_config::{Config, MethodMatcher, RetryPolicy};
+
+/// Which calls may be sent again after an upstream connection error, and how long to wait.
+#[derive(Debug)]
+pub(crate) struct Retries {
+    policy: RetryPolicy,
+    idempotent: MethodMatcher,
+    non_idempotent: MethodMatcher,
+}
+
+impl Retries {
+    /// Compile the idempotency table of the configuration.
+    pub(crate) fn from_config(config: &Config) -> Result<Self, ProxyError> {
+        let methods = config.idempotent_methods();
+        let matcher = |idempotent: bool| {
+            MethodMatcher::new(
+                methods
+                    .iter()
+                    .filter(|(_, value)| **value == idempotent)
+                    .map(|(method, _)| method),
+            )
+            .map_err(ProxyError::Policy)
+        };
+        Ok(Self {
+            policy: config.upstream_retries(),
+            idempotent: matcher(true)?,
+            non_idempotent: matcher(false)?,
+        })
+    }
+
+    /// Whether `method` is marked idempotent and not also marked otherwise.
+    pub(crate) fn is_idempotent(&self, method: &str) -> bool {
+        self.idempotent.matches(method) && !self.non_idempotent.matches(method)
+    }
+
+    /// Retries allowed after the first attempt of an idempotent call.
+    pub(crate) const fn retries(&self) -> u32 {
+        self.policy.retries()
+    }
+
+    /// Delay before retry number `retry`, drawn between half and all of the policy's delay.
+    pub(crate) fn delay(&self, retry: u32) -> Duration {
+        let delay = self.policy.delay(retry);
+        delay / 2 + delay.mul_f64(fastrand::f64() / 2.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn retries() -> Retries {
+        let config = Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            "http://127.0.0.1:8545".parse().unwrap(),
+            Default::default(),
+        );
+        Retries::from_config(&config).unwrap()
+    }
+
+    #[rstest]
+    #[case::read("eth_call", true)]
+    #[case::pattern("eth_getBalance", true)]
+    #[case::case_insensitive("ETH_BLOCKNUMBER", true)]
+    #[case::excluded_from_pattern("eth_getFilterChanges", false)]
+    #[case::state_changing("eth_sendRawTransaction", false)]
+    #[case::unlisted("anvil_mine", false)]
+    fn classifies_methods(#[case] method: &str, #[case] idempotent: bool) {
+        assert_eq!(retries().is_idempotent(method), idempotent);
+    }
+
+    #[rstest]
+    fn delays_are_jittered_within_the_backoff() {
+        let retries = retries();
+        let ceiling = retries.policy.delay(2);
+        for _ in 0..100 {
+            let delay = retries.delay(2);
+            assert!(delay >= ceiling / 2 && delay <= ceiling);
+        }
+    }
+}
@@ -0,0 +1,332 @@
+use crate::errors::ProxyError;
+use crate::tx::{DecodedTransaction, decode_hex};
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashSet;
+use veto_config::{
+    ConfigError, MethodMatcher, ParamCondition, ParamPath, ParamRule, QuantityRange, TxRule,
+};
+
+/// Parameter-aware rules compiled from [`ParamRule`]s.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ParamRuleSet {
+    rules: Vec<CompiledRule>,
+}
+
+#[derive(Debug, Clone)]
+struct CompiledRule {
+    name: String,
+    method: MethodMatcher,
+    predicates: Vec<(ParamPath, Condition)>,
+}
+
+/// [`ParamCondition`] with its regex compiled and addresses normalized.
+#[derive(Debug, Clone)]
+enum Condition {
+    Equals(Value),
+    In(Vec<Value>),
+    Range(QuantityRange),
+    Regex(Regex),
+    Address(HashSet<String>),
+    Exists(bool),
+}
+
+impl ParamRuleSet {
+    /// Compile the configured rules, rejecting invalid patterns and regexes.
+    pub(crate) fn new(rules: &[ParamRule]) -> Result<Self, ProxyError> {
+        rules
+            .iter()
+            .map(CompiledRule::new)
+            .collect::<Result<_, _>>()
+            .map(|rules| Self { rules })
+            .map_err(ProxyError::Policy)
+    }
+
+    /// Returns the name of the first rule whose method and predicates match the request.
+    pub(crate) fn find(&self, method: &str, params: &Value) -> Option<&str> {
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(method, params))
+            .map(|rule| rule.name.as_str())
+    }
+
+    /// Number of compiled rules.
+    pub(crate) const fn len(&self) -> usize {
+        self.rules.len()
+    }
+}
+
+impl CompiledRule {
+    fn new(rule: &ParamRule) -> Result<Self, ConfigError> {
+        rule.validate()?;
+
+        let predicates = rule
+            .when
+            .iter()
+            .map(|predicate| {
+                let condition = match &predicate.condition {
+                    ParamCondition::Equals(value) => Condition::Equals(value.clone()),
+                    ParamCondition::In(values) => Condition::In(values.clone()),
+                    ParamCondition::Range(range) => Condition::Range(*range),
+                    ParamCondition::Regex(pattern) => {
+                        Condition::Regex(Regex::new(pattern).map_err(|source| {
+                            ConfigError::ParamRegex {
+                                rule: rule.display_name().to_string(),
+                                source,
+                            }
+                        })?)
+                    }
+                    ParamCondition::Address(addresses) => Condition::Address(
+                        addresses
+                            .iter()
+                            .map(|address| address.to_ascii_lowercase())
+                            .collect(),
+                    ),
+                    ParamCondition::Exists(exists) => Condition::Exists(*exists),
+                };
+                Ok((predicate.path.clone(), condition))
+            })
+            .collect::<Result<_, ConfigError>>()?;
+
+        Ok(Self {
+            name: rule.display_name().to_string(),
+            method: MethodMatcher::new([rule.method.to_ascii_lowercase()])?,
+            predicates,
+        })
+    }
+
+    fn matches(&self, method: &str, params: &Value) -> bool {
+        self.method.matches(method)
+            && self
+                .predicates
+                .iter()
+                .all(|(path, condition)| condition.holds(path.resolve(params)))
+    }
+}
+
+impl Condition {
+    fn holds(&self, value: Option<&Value>) -> bool {
+        let value = value.filter(|value| !value.is_null());
+        match (self, value) {
+            (Self::Exists(expected), value) => value.is_some() == *expected,
+            (_, None) => false,
+            (Self::Equals(expected), Some(value)) => value == expected,
+            (Self::In(expected), Some(value)) => expected.contains(value),
+            (Self::Range(range), Some(value)) => range.contains_json(value),
+            (Self::Regex(regex), Some(value)) => value.as_str().is_some_and(|s| regex.is_match(s)),
+            (Self::Address(addresses), Some(value)) => value
+                .as_str()
+                .is_some_and(|address| addresses.contains(&address.to_ascii_lowercase())),
+        }
+    }
+}
+
+/// [`TxRule`]s compiled for matching against decoded raw transactions.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TxRuleSet {
+    rules: Vec<CompiledTxRule>,
+}
+
+#[derive(Debug, Clone)]
+struct CompiledTxRule {
+    name: String,
+    to: Option<HashSet<[u8; 20]>>,
+    value: Option<QuantityRange>,
+    selector: Option<HashSet<[u8; 4]>>,
+    gas_limit: Option<QuantityRange>,
+    max_fee_per_gas: Option<QuantityRange>,
+    max_priority_fee_per_gas: Option<QuantityRange>,
+    tx_type: Option<Vec<u8>>,
+}
+
+impl TxRuleSet {
+    /// Compile the configured transaction rules.
+    pub(crate) fn new(rules: &[TxRule]) -> Result<Self, ProxyError> {
+        rules
+            .iter()
+            .map(CompiledTxRule::new)
+            .collect::<Result<_, _>>()
+            .map(|rules| Self { rules })
+            .map_err(ProxyError::Policy)
+    }
+
+    /// Returns the name of the first rule matching the transaction.
+    pub(crate) fn find(&self, tx: &DecodedTransaction) -> Option<&str> {
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(tx))
+            .map(|rule| rule.name.as_str())
+    }
+
+    /// Number of compiled rules.
+    pub(crate) const fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Returns `true` if no rules are configured.
+    pub(crate) const fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+impl CompiledTxRule {
+    fn new(rule: &TxRule) -> Result<Self, ConfigError> {
+        rule.validate()?;
+
+        Ok(Self {
+            name: rule.name.clone(),
+            to: rule
+                .to
+                .as_ref()
+                .map(|addresses| addresses.iter().filter_map(|to| hex_array(to)).collect()),
+            value: rule.value,
+            selector: rule.selector.as_ref().map(|selectors| {
+                selectors
+                    .iter()
+                    .filter_map(|selector| hex_array(selector))
+                    .collect()
+            }),
+            gas_limit: rule.gas_limit,
+            max_fee_per_gas: rule.max_fee_per_gas,
+            max_priority_fee_per_gas: rule.max_priority_fee_per_gas,
+            tx_type: rule.tx_type.clone(),
+        })
+    }
+
+    fn matches(&self, tx: &DecodedTransaction) -> bool {
+        let in_range = |range: Option<QuantityRange>, value: u128| {
+            range.is_none_or(|range| range.contains(value))
+        };
+
+        self.to
+            .as_ref()
+            .is_none_or(|to| tx.to.is_some_and(|address| to.contains(&address)))
+            && self.selector.as_ref().is_none_or(|selectors| {
+                tx.selector
+                    .is_some_and(|selector| selectors.contains(&selector))
+            })
+            && self
+                .tx_type
+                .as_ref()
+                .is_none_or(|types| types.contains(&tx.tx_type.as_u8()))
+            && in_range(self.value, tx.value)
+            && in_range(self.gas_limit, tx.gas_limit.into())
+            && in_range(self.max_fee_per_gas, tx.max_fee_per_gas)
+            && in_range(self.max_priority_fee_per_gas, tx.max_priority_fee_per_gas)
+    }
+}
+
+/// Decode validated `0x`-prefixed hex into a fixed-size array.
+fn hex_array<const N: usize>(value: &str) -> Option<[u8; N]> {
+    decode_hex(value.get(2..)?)?.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    fn rules() -> ParamRuleSet {
+        let rules: Vec<ParamRule> = serde_json::from_value(json!([
+            {
+                "name": "no-fork-reset",
+                "method": "anvil_reset",
+                "when": [{ "path": "$[0].forking.jsonRpcUrl", "exists": true }]
+            },
+            {
+                "name": "treasury",
+                "method": "anvil_set*",
+                "when": [
+                    { "path": "$[0]", "address": ["0x00000000219ab540356cBB839Cbe05303d7705Fa"] },
+                    { "path": "$[1]", "range": { "min": "0xde0b6b3a7640000" } }
+                ]
+            },
+            {
+                "method": "eth_call",
+                "when": [{ "path": "$[0].data", "regex": "^0xa9059cbb" }]
+            },
+            {
+                "name": "pending",
+                "method": "eth_getBlockByNumber",
+                "when": [{ "path": "$[0]", "in": ["pending", "safe"] }]
+            }
+        ]))
+        .unwrap();
+        ParamRuleSet::new(&rules).unwrap()
+    }
+
+    #[rstest]
+    #[case::fork_reset(
+        "anvil_reset",
+        json!([{"forking": {"jsonRpcUrl": "https://rpc"}}]),
+        Some("no-fork-reset")
+    )]
+    #[case::plain_reset("anvil_reset", json!([]), None)]
+    #[case::null_url("anvil_reset", json!([{"forking": {"jsonRpcUrl": null}}]), None)]
+    #[case::treasury_large(
+        "anvil_setBalance",
+        json!(["0x00000000219AB540356CBB839CBE05303D7705FA", "0x1000000000000000000"]),
+        Some("treasury")
+    )]
+    #[case::treasury_above_u128(
+        "anvil_setBalance",
+        json!(["0x00000000219ab540356cbb839cbe05303d7705fa", format!("0x1{}", "0".repeat(32))]),
+        Some("treasury")
+    )]
+    #[case::treasury_large_number(
+        "anvil_setBalance",
+        json!(["0x00000000219ab540356cbb839cbe05303d7705fa", 1e30]),
+        Some("treasury")
+    )]
+    #[case::treasury_small(
+        "anvil_setBalance",
+        json!(["0x00000000219ab540356cbb839cbe05303d7705fa", "0x1"]),
+        None
+    )]
+    #[case::other_address(
+        "anvil_setBalance",
+        json!(["0x0000000000000000000000000000000000000001", "0x1000000000000000000"]),
+        None
+    )]
+    #[case::regex("eth_call", json!([{"data": "0xa9059cbb0000"}, "latest"]), Some("eth_call"))]
+    #[case::regex_miss("eth_call", json!([{"data": "0x70a08231"}, "latest"]), None)]
+    #[case::membership("eth_getBlockByNumber", json!(["pending", false]), Some("pending"))]
+    #[case::membership_miss("eth_getBlockByNumber", json!(["latest", false]), None)]
+    fn evaluates_rules(
+        #[case] method: &str,
+        #[case] params: Value,
+        #[case] expected: Option<&str>,
+    ) {
+        assert_eq!(rules().find(method, &params), expected);
+    }
+
+    fn tx_rules() -> TxRuleSet {
+        let rules: Vec<TxRule> = serde_json::from_value(json!([
+            {
+                "name": "big-transfers",
+                "to": ["0xAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA"],
+                "value": { "min": 100 }
+            },
+            { "name": "no-approvals", "selector": ["0x095ea7b3"], "tx_type": [2] },
+            { "name": "fee-cap", "max_fee_per_gas": { "min": "0x174876e800" } }
+        ]))
+        .unwrap();
+        TxRuleSet::new(&rules).unwrap()
+    }
+
+    #[rstest]
+    #[case::large_value(crate::tx::tests::dynamic_fee_tx(1, [0xaa; 20], 100), Some("big-transfers"))]
+    #[case::approval(crate::tx::tests::dynamic_fee_tx(1, [0xbb; 20], 0), Some("no-approvals"))]
+    #[case::legacy_fees(crate::tx::tests::EIP155_EXAMPLE.to_string(), None)]
+    fn evaluates_tx_rules(#[case] raw: String, #[case] expected: Option<&str>) {
+        let tx = crate::tx::decode_raw_transaction(&raw).unwrap();
+        assert_eq!(tx_rules().find(&tx), expected);
+    }
+
+    #[rstest]
+    fn method_matching_is_case_insensitive() {
+        let params = json!([{"forking": {"jsonRpcUrl": "https://rpc"}}]);
+        assert_eq!(rules().find("ANVIL_RESET", &params), Some("no-fork-reset"));
+    }
+}
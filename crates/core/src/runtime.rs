@@ -1,23 +1,84 @@
+use crate::admin::serve_admin;
+use crate::chain::resolve_chain_id;
 use crate::errors::ProxyError;
-use crate::server::{AppState, router};
+use crate::health::spawn_health_checks;
+use crate::metrics::serve_metrics;
+use crate::reload::spawn_reload;
+use crate::server::{AppState, ClientAddr, router};
 use axum::serve;
 use tokio::net::TcpListener;
 use tracing::{debug, error, info};
-use veto_config::Config;
+use veto_config::{Config, ConfigSource};
 
 /// Run the proxy server with the provided [`Config`] until shutdown.
 pub async fn run(config: Config) -> Result<(), ProxyError> {
+    serve_proxy(config, None).await
+}
+
+/// Run the proxy server like [`run`], reloading the method policy from `source` whenever its
+/// file changes or the process receives `SIGHUP`.
+///
+/// `config` should be the result of [`ConfigSource::load`]. Invalid reloads are logged and the
+/// running policy is kept.
+pub async fn run_with_reload(config: Config, source: ConfigSource) -> Result<(), ProxyError> {
+    serve_proxy(config, Some(source)).await
+}
+
+async fn serve_proxy(config: Config, source: Option<ConfigSource>) -> Result<(), ProxyError> {
+    let config = resolve_chain_id(config).await?;
+    let listener_tls = config.listener_tls().cloned();
+    #[cfg(not(feature = "tls"))]
+    if listener_tls.is_some() {
+        return Err(ProxyError::TlsUnsupported);
+    }
+    let health_check = config.health_check();
+    let metrics_address = config.metrics_address();
+    let admin = config.admin().cloned();
     let state = AppState::try_from_config(config)?;
     let bind_address = state.bind_address();
 
+    if let (Some(address), Some(metrics)) = (metrics_address, state.metrics.clone()) {
+        serve_metrics(address, metrics).await?;
+    }
+
+    if let Some(settings) = health_check {
+        info!(
+            interval_ms = settings.interval().as_millis(),
+            max_block_lag = settings.max_block_lag(),
+            "active upstream health checks enabled"
+        );
+        spawn_health_checks(state.clone(), settings);
+    }
+
+    if let Some(settings) = admin {
+        serve_admin(&settings, state.clone(), source.clone()).await?;
+    }
+
+    if let Some(source) = source {
+        spawn_reload(state.clone(), source)?;
+    }
+
     debug!(%bind_address, "binding proxy listener");
     let listener = TcpListener::bind(bind_address)
         .await
         .map_err(ProxyError::Bind)?;
-    info!("veto proxy listening on http://{bind_address}");
+    let service = router(state).into_make_service_with_connect_info::<ClientAddr>();
+
+    #[cfg(feature = "tls")]
+    if let Some(settings) = listener_tls {
+        let certificates = crate::tls::ServerCertificates::load(settings)?;
+        #[cfg(unix)]
+        certificates.reload_on_sighup()?;
+        let listener = crate::tls::TlsListener::new(listener, certificates)?;
+        info!("veto proxy listening on https://{bind_address}");
+        return serve(listener, service)
+            .with_graceful_shutdown(shutdown_signal())
+            .await
+            .map_err(ProxyError::Server);
+    }
 
-    let router = router(state);
-    serve(listener, router)
+    info!("veto proxy listening on http://{bind_address}");
+    serve(listener, service)
         .with_graceful_shutdown(shutdown_signal())
         .await
         .map_err(ProxyError::Server)
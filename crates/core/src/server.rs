@@ -1,19 +1,42 @@
+use crate::access::filter_clients;
+use crate::audit::{AuditCall, AuditSink, AuditTrail, Decision};
+use crate::auth::authenticate;
+use crate::cache::{Cache, CacheKey, CacheStatus, CachedResponse, X_CACHE};
 use crate::errors::ProxyError;
-use crate::jsonrpc::{JsonRpcError, error_response, parse_json_rpc};
+use crate::jsonrpc::{
+    BatchEntry, JsonRpcError, JsonRpcPayload, JsonRpcRequest, batch_body, error_payload,
+    error_response, parse_json_rpc,
+};
+use crate::metrics::Metrics;
+use crate::policy::{ActivePolicy, PolicyStore, Rejection};
+use crate::rate_limit::{ClientKey, RateLimits};
+use crate::retry::Retries;
+use crate::timeout::Timeouts;
+use crate::upstream::{UpstreamSet, UpstreamStats};
+use crate::ws::{is_websocket_upgrade, websocket_handler};
 use axum::Router;
-use axum::body::Body;
-use axum::extract::State;
-use axum::http::{HeaderMap, Request, StatusCode, Uri};
+use axum::body::{Body, Bytes};
+use axum::extract::connect_info::Connected;
+use axum::extract::{ConnectInfo, State};
+use axum::http::header::{ACCEPT_ENCODING, CONTENT_LENGTH, RETRY_AFTER, TRANSFER_ENCODING};
+use axum::http::request::Parts;
+use axum::http::{HeaderMap, HeaderValue, Request, StatusCode, Uri};
+use axum::middleware;
 use axum::response::Response;
-use axum::routing::any;
-use http_body_util::BodyExt;
+use axum::routing::{any, get};
+use axum::serve::IncomingStream;
+use http_body_util::{BodyExt, LengthLimitError, Limited};
 use hyper_util::client::legacy::Client;
 use hyper_util::client::legacy::connect::HttpConnector;
 use hyper_util::rt::TokioExecutor;
+use serde_json::value::RawValue;
 use serde_json::{Value, json};
-use std::collections::HashSet;
+use std::collections::{HashMap, VecDeque};
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::TcpListener;
+use tokio::time::{sleep, timeout_at};
 use tracing::{debug, error, warn};
 use veto_config::Config;
 
@@ -21,34 +44,90 @@ use veto_config::Config;
 #[derive(Debug, Clone)]
 pub struct AppState {
     bind_address: SocketAddr,
-    upstream: Uri,
-    blocked_methods: Arc<HashSet<String>>,
-    client: Client<HttpConnector, Body>,
+    pub(crate) upstreams: Arc<UpstreamSet>,
+    pub(crate) policy: Arc<PolicyStore>,
+    pub(crate) max_batch_size: usize,
+    pub(crate) max_request_bytes: usize,
+    pub(crate) upstream_timeouts: Arc<Timeouts>,
+    pub(crate) retries: Arc<Retries>,
+    pub(crate) cache: Option<Arc<Cache>>,
+    pub(crate) client: Client<UpstreamConnector, Body>,
+    pub(crate) metrics: Option<Arc<Metrics>>,
+    rate_limits: Arc<RateLimits>,
+    pub(crate) audit: Option<Arc<AuditSink>>,
+    #[cfg(feature = "tls")]
+    pub(crate) tls: Arc<rustls::ClientConfig>,
 }
 
 impl AppState {
     /// Create a new [`AppState`] from the resolved [`Config`].
     pub fn try_from_config(config: Config) -> Result<Self, ProxyError> {
-        let mut connector = HttpConnector::new();
-        connector.enforce_http(false);
-        let client = Client::builder(TokioExecutor::new()).build(connector);
+        #[cfg(feature = "tls")]
+        let tls = crate::tls::client_config(config.upstream_tls())?;
+        #[cfg(feature = "tls")]
+        let client = upstream_client(tls.clone());
+        #[cfg(not(feature = "tls"))]
+        let client = upstream_client(&config)?;
 
         let bind_address = config.bind_address();
-        let upstream = config.upstream_url().clone();
-        let blocked_methods = Arc::new(config.blocked_methods().clone());
+        let metrics = config.metrics_address().map(|_| Arc::new(Metrics::new()));
+        let upstreams = Arc::new(UpstreamSet::from_config(&config, metrics.as_ref()));
+        let max_batch_size = config.max_batch_size();
+        let max_request_bytes = config.max_request_bytes();
+        let upstream_timeouts = Arc::new(Timeouts::from_config(config.upstream_timeouts())?);
+        let retries = Arc::new(Retries::from_config(&config)?);
+        let cache = config
+            .response_cache()
+            .cloned()
+            .map(|settings| Arc::new(Cache::new(settings)));
+        let rate_limits = Arc::new(RateLimits::from_config(&config)?);
+        let audit = config
+            .audit_log()
+            .map(AuditSink::spawn)
+            .transpose()?
+            .map(Arc::new);
+        let active = ActivePolicy::new(config)?;
+        let (config, policy) = (&active.config, &active.policy);
 
         debug!(
             %bind_address,
             upstream = %config.upstream_url(),
-            blocked_methods = blocked_methods.len(),
+            upstreams = config.upstreams().len(),
+            balance = %config.balance(),
+            mode = %policy.mode(),
+            method_rules = policy.rule_count(),
+            param_rules = policy.param_rule_count(),
+            tx_rules = policy.tx_rule_count(),
+            chain_id = ?policy.chain_id(),
+            max_batch_size,
+            max_request_bytes,
+            upstream_timeout = ?config.upstream_timeouts().default_timeout(),
+            upstream_timeout_overrides = config.upstream_timeouts().methods().len(),
+            upstream_retries = config.upstream_retries().retries(),
+            response_cache = ?config.response_cache().map(|cache| cache.max_entries()),
+            metrics = metrics.is_some(),
+            rate_limits = config.rate_limits().len(),
+            api_keys = config.api_keys().len(),
+            audit_log = ?config.audit_log().map(|audit| &audit.path),
             "initializing app state"
         );
+        let policy = Arc::new(PolicyStore::new(active));
 
         Ok(Self {
             bind_address,
-            upstream,
-            blocked_methods,
+            upstreams,
+            policy,
+            max_batch_size,
+            max_request_bytes,
+            upstream_timeouts,
+            retries,
+            cache,
             client,
+            metrics,
+            rate_limits,
+            audit,
+            #[cfg(feature = "tls")]
+            tls,
         })
     }
 
@@ -56,14 +135,176 @@ impl AppState {
     pub const fn bind_address(&self) -> SocketAddr {
         self.bind_address
     }
+
+    /// Snapshot the health and request counters of every upstream, for debugging.
+    pub fn upstream_stats(&self) -> Vec<UpstreamStats> {
+        self.upstreams.stats()
+    }
+
+    /// Deadline and retry eligibility of a call to `method`.
+    pub(crate) fn call_limits(&self, method: &str) -> CallLimits {
+        CallLimits {
+            timeout: self.upstream_timeouts.for_method(method),
+            idempotent: self.retries.is_idempotent(method),
+        }
+    }
+
+    /// Check a request against the policy of `client` and then its rate limits, returning why
+    /// it is refused (if it is).
+    ///
+    /// Clients whose API key was removed since they connected are refused. Calls refused by the
+    /// policy do not count against the rate limits. The decision is counted in the forwarded or
+    /// blocked metrics.
+    pub(crate) fn check_request(
+        &self,
+        client: &ClientKey,
+        request: &JsonRpcRequest,
+    ) -> Option<Rejection> {
+        let rejection = self
+            .policy
+            .load()
+            .policy_for(client)
+            .map_or(Some(Rejection::Unauthorized), |policy| {
+                policy.check(&request.method, &request.params)
+            })
+            .or_else(|| {
+                self.rate_limits
+                    .check(client, &request.method)
+                    .err()
+                    .map(Rejection::RateLimited)
+            });
+        if let Some(metrics) = &self.metrics {
+            match &rejection {
+                Some(rejection) => metrics.record_blocked(&request.method, rejection),
+                None => metrics.record_forwarded(&request.method),
+            }
+        }
+        rejection
+    }
+}
+
+/// Address of the connected client, captured by the plain and TLS listeners alike.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ClientAddr(pub(crate) SocketAddr);
+
+impl Connected<IncomingStream<'_, TcpListener>> for ClientAddr {
+    fn connect_info(stream: IncomingStream<'_, TcpListener>) -> Self {
+        Self(*stream.remote_addr())
+    }
+}
+
+#[cfg(feature = "tls")]
+impl Connected<IncomingStream<'_, crate::tls::TlsListener>> for ClientAddr {
+    fn connect_info(stream: IncomingStream<'_, crate::tls::TlsListener>) -> Self {
+        Self(*stream.remote_addr())
+    }
 }
 
+/// Connector used to reach the upstream: plain HTTP, plus HTTPS with the `tls` feature.
+#[cfg(feature = "tls")]
+pub(crate) type UpstreamConnector = hyper_rustls::HttpsConnector<HttpConnector>;
+/// Connector used to reach the upstream: plain HTTP, plus HTTPS with the `tls` feature.
+#[cfg(not(feature = "tls"))]
+pub(crate) type UpstreamConnector = HttpConnector;
+
+/// Build the client used to reach the upstream over HTTP or HTTPS.
+#[cfg(feature = "tls")]
+pub(crate) fn upstream_client(tls: Arc<rustls::ClientConfig>) -> Client<UpstreamConnector, Body> {
+    let mut http = HttpConnector::new();
+    http.enforce_http(false);
+    let connector = hyper_rustls::HttpsConnectorBuilder::new()
+        .with_tls_config(Arc::unwrap_or_clone(tls))
+        .https_or_http()
+        .enable_http1()
+        .wrap_connector(http);
+    Client::builder(TokioExecutor::new()).build(connector)
+}
+
+/// Build the client used to reach the upstream, refusing TLS settings this build cannot honor.
+#[cfg(not(feature = "tls"))]
+pub(crate) fn upstream_client(
+    config: &Config,
+) -> Result<Client<UpstreamConnector, Body>, ProxyError> {
+    let needs_tls = config
+        .upstreams()
+        .iter()
+        .any(|upstream| matches!(upstream.url.scheme_str(), Some("https" | "wss")));
+    if needs_tls || !config.upstream_tls().is_empty() {
+        return Err(ProxyError::TlsUnsupported);
+    }
+
+    let mut connector = HttpConnector::new();
+    connector.enforce_http(false);
+    Ok(Client::builder(TokioExecutor::new()).build(connector))
+}
+
+/// Send a parameterless JSON-RPC request to `uri` and return the raw response body.
+///
+/// Used for the proxy's own queries, such as `eth_chainId` and health checks.
+pub(crate) async fn call_upstream(
+    client: &Client<UpstreamConnector, Body>,
+    uri: &Uri,
+    method: &str,
+) -> Result<Bytes, ProxyError> {
+    let payload = json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": []});
+    let request = Request::post(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(payload.to_string()))
+        .expect("valid json-rpc request");
+
+    let response = client
+        .request(request)
+        .await
+        .map_err(ProxyError::Upstream)?;
+    Ok(response
+        .into_body()
+        .collect()
+        .await
+        .map_err(|error| ProxyError::UpstreamBody(Box::new(error)))?
+        .to_bytes())
+}
+
+/// Path serving the upstream status report.
+pub(crate) const STATUS_PATH: &str = "/_veto/status";
+
 /// Constructs a new Axum [`Router`] with the provided application state.
 pub fn router(state: AppState) -> Router {
-    Router::new().fallback(any(proxy_handler)).with_state(state)
+    Router::new()
+        .route(STATUS_PATH, get(status_handler))
+        .fallback(any(proxy_handler))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            filter_clients,
+        ))
+        .with_state(state)
+}
+
+/// Report which upstreams are in rotation, answering `503` when none are.
+async fn status_handler(State(state): State<AppState>) -> Response {
+    let upstreams = state.upstream_stats();
+    let in_rotation = upstreams
+        .iter()
+        .filter(|upstream| upstream.in_rotation)
+        .count();
+    let (status, code) = match in_rotation {
+        0 => ("down", StatusCode::SERVICE_UNAVAILABLE),
+        count if count < upstreams.len() => ("degraded", StatusCode::OK),
+        _ => ("ok", StatusCode::OK),
+    };
+
+    let body = json!({"status": status, "upstreams": upstreams});
+    Response::builder()
+        .status(code)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .expect("valid response")
 }
 
 async fn proxy_handler(State(state): State<AppState>, req: Request<Body>) -> Response {
+    if is_websocket_upgrade(req.headers()) {
+        return websocket_handler(state, req).await;
+    }
+
     match process_request(&state, req).await {
         Ok(response) => response,
         Err(HandlerError::JsonRpc(response)) => response,
@@ -78,96 +319,849 @@ async fn proxy_handler(State(state): State<AppState>, req: Request<Body>) -> Res
 }
 
 /// Validate the JSON-RPC payload, blocking or forwarding it upstream as needed.
+///
+/// Every request is timed here, then recorded in the metrics and the audit log.
 async fn process_request(state: &AppState, req: Request<Body>) -> Result<Response, HandlerError> {
-    let (parts, body) = req.into_parts();
-    let collected = body.collect().await.map_err(|error| {
-        error!(error = ?error, "failed to read request body");
-        HandlerError::from(ProxyError::Body(Box::new(error)))
-    })?;
-    let bytes = collected.to_bytes();
-
-    let json_rpc = match parse_json_rpc(&bytes) {
-        Ok(request) => {
-            debug!(method = %request.method, "received json-rpc request");
-            request
+    let _in_flight = state
+        .metrics
+        .as_ref()
+        .map(|metrics| metrics.request_started());
+    let started = Instant::now();
+    let client = req
+        .extensions()
+        .get::<ConnectInfo<ClientAddr>>()
+        .map(|ConnectInfo(ClientAddr(address))| *address);
+
+    let mut trail = AuditTrail::default();
+    let result = handle_payload(state, req, client, &mut trail).await;
+    let elapsed = started.elapsed();
+
+    if let Some(metrics) = &state.metrics
+        && let Some(method) = trail.method()
+    {
+        metrics.observe_request(method, elapsed);
+    }
+    if let Some(audit) = &state.audit {
+        let status = match &result {
+            Ok(response) | Err(HandlerError::JsonRpc(response)) => response.status(),
+            Err(HandlerError::Internal(_)) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        audit.record(client, &trail, status, elapsed);
+    }
+    result
+}
+
+/// Parse the request body and dispatch it as a single call or a batch.
+async fn handle_payload(
+    state: &AppState,
+    req: Request<Body>,
+    address: Option<SocketAddr>,
+    trail: &mut AuditTrail,
+) -> Result<Response, HandlerError> {
+    let (mut parts, body) = req.into_parts();
+    let Some(client) = authenticate(state, &mut parts, address) else {
+        debug!("rejecting request without a valid api key");
+        trail.unauthorized();
+        return Err(HandlerError::JsonRpc(blocked_method_response(
+            &Value::Null,
+            "",
+            &Rejection::Unauthorized,
+        )));
+    };
+    if let ClientKey::ApiKey(name) = &client {
+        trail.api_key = Some(Arc::clone(name));
+    }
+
+    let max = state.max_request_bytes;
+    let declared = parts
+        .headers
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok()?.parse::<u64>().ok());
+    if declared.is_some_and(|declared| declared > max as u64) {
+        return Err(reject_payload(
+            state,
+            JsonRpcError::BodyTooLarge { max },
+            trail,
+        ));
+    }
+
+    let bytes = match Limited::new(body, max).collect().await {
+        Ok(collected) => collected.to_bytes(),
+        Err(error) if error.is::<LengthLimitError>() => {
+            return Err(reject_payload(
+                state,
+                JsonRpcError::BodyTooLarge { max },
+                trail,
+            ));
         }
         Err(error) => {
-            debug!(error = ?error, "rejecting json-rpc payload");
-            return Err(HandlerError::from(error));
+            error!(error = ?error, "failed to read request body");
+            trail.invalid(Value::Null);
+            return Err(HandlerError::from(ProxyError::Body(error)));
         }
     };
-    let normalized_method = json_rpc.method.to_ascii_lowercase();
 
-    if state.blocked_methods.contains(&normalized_method) {
-        warn!(method = %json_rpc.method, "blocked json-rpc method");
-        let error_payload = blocked_method_response(&json_rpc.id, &json_rpc.method);
+    match parse_json_rpc(&bytes, state.max_batch_size) {
+        Ok(JsonRpcPayload::Single(request)) => {
+            process_single(state, parts, bytes.clone(), &client, request, trail).await
+        }
+        Ok(JsonRpcPayload::Batch(entries)) => {
+            trail.batch = true;
+            process_batch(state, parts, bytes.clone(), &client, entries, trail).await
+        }
+        Err(error) => Err(reject_payload(state, error, trail)),
+    }
+}
+
+/// Count and audit a payload that could not be read as JSON-RPC, returning its error response.
+fn reject_payload(state: &AppState, error: JsonRpcError, trail: &mut AuditTrail) -> HandlerError {
+    debug!(error = ?error, "rejecting json-rpc payload");
+    if let Some(metrics) = &state.metrics {
+        metrics.record_parse_error(&error);
+    }
+    trail.invalid(Value::Null);
+    HandlerError::from(error)
+}
+
+/// Block or forward a single JSON-RPC request.
+async fn process_single(
+    state: &AppState,
+    parts: Parts,
+    bytes: Bytes,
+    client: &ClientKey,
+    json_rpc: JsonRpcRequest,
+    trail: &mut AuditTrail,
+) -> Result<Response, HandlerError> {
+    debug!(method = %json_rpc.method, "received json-rpc request");
+
+    let rejection = state.check_request(client, &json_rpc);
+    trail.calls.push(AuditCall {
+        method: Some(json_rpc.method.clone()),
+        id: json_rpc.id.clone(),
+        decision: rejection.as_ref().map_or(Decision::Forwarded, |rejection| {
+            Decision::Blocked(rejection.rule().to_owned())
+        }),
+    });
+
+    if let Some(rejection) = rejection {
+        warn!(method = %json_rpc.method, ?rejection, "blocked json-rpc method");
+        let error_payload = blocked_method_response(&json_rpc.id, &json_rpc.method, &rejection);
         return Ok(error_payload);
     }
 
-    let target_uri = match build_target_uri(&state.upstream, &parts.uri) {
-        Ok(uri) => uri,
-        Err(error) => {
-            error!(
-                error = ?error,
-                incoming = %parts.uri,
-                upstream = %state.upstream,
-                "failed to construct upstream uri"
-            );
-            return Err(HandlerError::from(error));
+    let cached = state.cache.as_deref().and_then(|cache| {
+        cache.lookup(&json_rpc.method, &json_rpc.params, state.metrics.as_deref())
+    });
+    let cache_key = match cached {
+        Some((_, Some(result))) => {
+            debug!(method = %json_rpc.method, "answering json-rpc request from the cache");
+            if let Some(call) = trail.calls.last_mut() {
+                call.decision = Decision::Cached;
+            }
+            return Ok(cached_response(&json_rpc.id, &result));
+        }
+        Some((key, None)) => Some(key),
+        None => None,
+    };
+
+    let mut parts = parts;
+    if cache_key.is_some() {
+        parts
+            .headers
+            .insert(ACCEPT_ENCODING, HeaderValue::from_static("identity"));
+    }
+    let limits = state.call_limits(&json_rpc.method);
+    let result = forward(state, parts, bytes, &json_rpc.method, limits, trail).await;
+    if let Some(cache) = &state.cache {
+        cache.observe(&json_rpc.method);
+    }
+    match result {
+        Err(HandlerError::Internal(error)) => match upstream_failure(&error) {
+            Some((status, failure)) => Ok(upstream_failure_response(status, &json_rpc.id, failure)),
+            None => Err(HandlerError::Internal(error)),
+        },
+        Ok(response) => match cache_key {
+            Some(key) => cache_response(state, key, response).await,
+            None => Ok(response),
+        },
+        result => result,
+    }
+}
+
+/// Filter a JSON-RPC batch entry by entry, forwarding the allowed calls as a single batch.
+///
+/// Blocked and invalid entries are answered locally and merged with the upstream responses,
+/// preserving the order of the original batch. Notifications never produce a response entry.
+async fn process_batch(
+    state: &AppState,
+    parts: Parts,
+    bytes: Bytes,
+    client: &ClientKey,
+    entries: Vec<BatchEntry<'_>>,
+    trail: &mut AuditTrail,
+) -> Result<Response, HandlerError> {
+    debug!(entries = entries.len(), "received json-rpc batch");
+
+    let plan = plan_batch(state, client, entries, state.cache.as_deref());
+    let cache_status = plan.cache_status();
+    let mark = |mut response: Response| {
+        if let Some(status) = cache_status {
+            response
+                .headers_mut()
+                .insert(X_CACHE, HeaderValue::from_static(status.as_str()));
         }
+        response
     };
+    let BatchPlan {
+        slots,
+        forwarded,
+        filtered,
+        calls,
+        retry_after,
+        limits,
+        cache_misses,
+        ..
+    } = plan;
+    trail.calls = calls;
 
-    debug!(method = %json_rpc.method, upstream = %target_uri, "forwarding json-rpc request");
+    if forwarded.is_empty() {
+        let Some(retry_after) = retry_after else {
+            return Ok(mark(batch_response(StatusCode::OK, local_responses(slots))));
+        };
+        let mut response = batch_response(StatusCode::TOO_MANY_REQUESTS, local_responses(slots));
+        response
+            .headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(retry_after));
+        return Ok(mark(response));
+    }
 
-    let mut forward_parts = parts;
-    forward_parts.uri = target_uri.clone();
+    let mut parts = parts;
+    let body = if filtered {
+        rewrite_request_body(&mut parts.headers);
+        batch_body(&forwarded)
+    } else {
+        bytes
+    };
+    if filtered || !cache_misses.is_empty() {
+        parts
+            .headers
+            .insert(ACCEPT_ENCODING, HeaderValue::from_static("identity"));
+    }
+    let result = forward(state, parts, body, "batch", limits, trail).await;
+    if let Some(cache) = &state.cache {
+        trail
+            .forwarded_methods()
+            .for_each(|method| cache.observe(method));
+    }
+    let response = match result {
+        Err(HandlerError::Internal(error)) => {
+            let Some((status, failure)) = upstream_failure(&error) else {
+                return Err(HandlerError::Internal(error));
+            };
+            let failed = json!({ "error": failure });
+            return Ok(mark(batch_response(
+                status,
+                merge_batch_responses(slots, failed),
+            )));
+        }
+        result => result?,
+    };
+    if !filtered && cache_misses.is_empty() {
+        return Ok(response);
+    }
+
+    let (upstream_parts, upstream_body) = response.into_parts();
+    let upstream_bytes = read_upstream_body(state, upstream_body).await?;
+
+    let Ok(upstream_value) = serde_json::from_slice::<Value>(&upstream_bytes) else {
+        if !filtered {
+            warn!("upstream batch response was not valid JSON; returning it unmodified");
+            return Ok(mark(Response::from_parts(
+                upstream_parts,
+                Body::from(upstream_bytes),
+            )));
+        }
+        warn!("upstream batch response was not valid JSON; answering its entries with errors");
+        let failed = json!({
+            "error": { "code": -32603, "message": "invalid response from upstream" },
+        });
+        return Ok(mark(batch_response(
+            StatusCode::BAD_GATEWAY,
+            merge_batch_responses(slots, failed),
+        )));
+    };
+
+    if let (Some(cache), Value::Array(responses)) = (&state.cache, &upstream_value)
+        && upstream_parts.status.is_success()
+    {
+        for (id, key) in cache_misses {
+            if let Some(response) = responses
+                .iter()
+                .find(|response| response.get("id") == Some(&id))
+            {
+                cache.insert(key, response);
+            }
+        }
+    }
+    if !filtered {
+        return Ok(mark(Response::from_parts(
+            upstream_parts,
+            Body::from(upstream_bytes),
+        )));
+    }
 
-    let mut forward_request = Request::from_parts(forward_parts, Body::from(bytes));
-    sanitize_request_headers(forward_request.headers_mut());
+    Ok(mark(batch_response(
+        upstream_parts.status,
+        merge_batch_responses(slots, upstream_value),
+    )))
+}
 
-    let response = state
-        .client
-        .request(forward_request)
+/// Read the body of an upstream response, counting a failure in the metrics.
+async fn read_upstream_body(state: &AppState, body: Body) -> Result<Bytes, HandlerError> {
+    Ok(body
+        .collect()
         .await
         .map_err(|error| {
-            error!(
-                error = ?error,
-                method = %json_rpc.method,
-                upstream = %target_uri,
-                "upstream request failed"
-            );
-            HandlerError::from(ProxyError::Upstream(error))
+            error!(error = ?error, "failed to read upstream response body");
+            let error = ProxyError::UpstreamBody(Box::new(error));
+            if let Some(metrics) = &state.metrics {
+                metrics.record_upstream_error(&error);
+            }
+            HandlerError::from(error)
         })?
-        .map(Body::new);
+        .to_bytes())
+}
 
-    Ok(response)
+/// Read the upstream answer to a cacheable call, caching a successful result.
+///
+/// The response is passed on unchanged, marked as a cache miss.
+async fn cache_response(
+    state: &AppState,
+    key: CacheKey,
+    response: Response,
+) -> Result<Response, HandlerError> {
+    let (mut parts, body) = response.into_parts();
+    let bytes = read_upstream_body(state, body).await?;
+    if let Some(cache) = &state.cache
+        && parts.status.is_success()
+        && let Ok(value) = serde_json::from_slice::<Value>(&bytes)
+    {
+        cache.insert(key, &value);
+    }
+    parts.headers.insert(
+        X_CACHE,
+        HeaderValue::from_static(CacheStatus::Miss.as_str()),
+    );
+    Ok(Response::from_parts(parts, Body::from(bytes)))
 }
 
-/// Remove hop-by-hop headers before forwarding the request upstream.
-fn sanitize_request_headers(headers: &mut HeaderMap) {
-    headers.remove("host");
+/// Build the [`Response`] answering a single call from the response cache.
+fn cached_response(id: &Value, result: &Value) -> Response {
+    let body = serde_json::to_string(&CachedResponse::new(id, result))
+        .expect("cached response serializes");
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "application/json")
+        .header(X_CACHE, CacheStatus::Hit.as_str())
+        .body(Body::from(body))
+        .expect("valid cached response")
 }
 
-/// Build the JSON-RPC error [`Response`] sent when a method is blocked.
-fn blocked_method_response(id: &Value, method: &str) -> Response {
+/// Result of filtering the entries of a JSON-RPC batch.
+#[derive(Debug)]
+pub(crate) struct BatchPlan<'a> {
+    /// Response slots for non-notification entries, in original batch order.
+    pub(crate) slots: Vec<BatchSlot>,
+    /// Allowed entries to forward upstream as a batch.
+    pub(crate) forwarded: Vec<&'a RawValue>,
+    /// Whether any entry was blocked or invalid.
+    pub(crate) filtered: bool,
+    /// Decision taken for every entry, in original batch order.
+    pub(crate) calls: Vec<AuditCall>,
+    /// Longest `Retry-After` of the rate-limited entries, if any were.
+    pub(crate) retry_after: Option<u64>,
+    /// Limits of the forwarded entries, merged.
+    pub(crate) limits: CallLimits,
+    /// Whether any entry was answered from the response cache.
+    pub(crate) cache_hits: bool,
+    /// Forwarded entries whose result may be cached, with their request ids.
+    pub(crate) cache_misses: Vec<(Value, CacheKey)>,
+}
+
+impl BatchPlan<'_> {
+    /// Whether the cacheable entries were all answered from the cache, if there were any.
+    pub(crate) const fn cache_status(&self) -> Option<CacheStatus> {
+        match (self.cache_hits, self.cache_misses.is_empty()) {
+            (_, false) => Some(CacheStatus::Miss),
+            (true, true) => Some(CacheStatus::Hit),
+            (false, true) => None,
+        }
+    }
+}
+
+/// Split batch entries into locally answered errors and allowed calls to forward.
+///
+/// With a `cache`, allowed calls whose result is cached are answered locally too.
+pub(crate) fn plan_batch<'a>(
+    state: &AppState,
+    client: &ClientKey,
+    entries: Vec<BatchEntry<'a>>,
+    cache: Option<&Cache>,
+) -> BatchPlan<'a> {
+    let mut plan = BatchPlan {
+        slots: Vec::with_capacity(entries.len()),
+        forwarded: Vec::with_capacity(entries.len()),
+        filtered: false,
+        calls: Vec::with_capacity(entries.len()),
+        retry_after: None,
+        limits: CallLimits::EMPTY_BATCH,
+        cache_hits: false,
+        cache_misses: Vec::new(),
+    };
+
+    for entry in entries {
+        match entry {
+            BatchEntry::Invalid(error) => {
+                debug!(error = ?error, "rejecting json-rpc batch entry");
+                if let Some(metrics) = &state.metrics {
+                    metrics.record_parse_error(&error);
+                }
+                plan.calls.push(AuditCall {
+                    method: None,
+                    id: Value::Null,
+                    decision: Decision::Invalid,
+                });
+                plan.filtered = true;
+                plan.slots
+                    .push(BatchSlot::Local(error_payload(&error, Value::Null)));
+            }
+            BatchEntry::Request {
+                request,
+                raw,
+                notification,
+            } => {
+                let rejection = state.check_request(client, &request);
+                plan.calls.push(AuditCall {
+                    method: Some(request.method.clone()),
+                    id: request.id.clone(),
+                    decision: rejection.as_ref().map_or(Decision::Forwarded, |rejection| {
+                        Decision::Blocked(rejection.rule().to_owned())
+                    }),
+                });
+                if let Some(rejection) = rejection {
+                    warn!(method = %request.method, ?rejection, "blocked json-rpc method in batch");
+                    plan.filtered = true;
+                    plan.retry_after = plan.retry_after.max(rejection.retry_after());
+                    if !notification {
+                        plan.slots.push(BatchSlot::Local(blocked_method_payload(
+                            &request.id,
+                            &request.method,
+                            &rejection,
+                        )));
+                    }
+                    continue;
+                }
+
+                let cached = cache.filter(|_| !notification).and_then(|cache| {
+                    cache.lookup(&request.method, &request.params, state.metrics.as_deref())
+                });
+                match cached {
+                    Some((_, Some(result))) => {
+                        if let Some(call) = plan.calls.last_mut() {
+                            call.decision = Decision::Cached;
+                        }
+                        plan.filtered = true;
+                        plan.cache_hits = true;
+                        plan.slots.push(BatchSlot::Local(
+                            serde_json::to_value(CachedResponse::new(&request.id, &result))
+                                .expect("cached response serializes"),
+                        ));
+                    }
+                    cached => {
+                        if let Some((key, None)) = cached {
+                            plan.cache_misses.push((request.id.clone(), key));
+                        }
+                        plan.forwarded.push(raw);
+                        plan.limits = plan.limits.merge(state.call_limits(&request.method));
+                        if !notification {
+                            plan.slots.push(BatchSlot::Upstream(request.id));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    plan
+}
+
+/// Deadline and retry eligibility of a forwarded call, or of every call in a batch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct CallLimits {
+    /// Time allowed for the upstream to answer.
+    pub(crate) timeout: Duration,
+    /// Whether the call may be sent again after an upstream connection error.
+    pub(crate) idempotent: bool,
+}
+
+impl CallLimits {
+    /// Limits of a batch before any call is added with [`CallLimits::merge`].
+    const EMPTY_BATCH: Self = Self {
+        timeout: Duration::ZERO,
+        idempotent: true,
+    };
+
+    /// Limits of a batch holding calls with both limits: the longer deadline, and retried only
+    /// when every call may be.
+    fn merge(self, other: Self) -> Self {
+        Self {
+            timeout: self.timeout.max(other.timeout),
+            idempotent: self.idempotent && other.idempotent,
+        }
+    }
+}
+
+/// Forward the request body upstream, retrying idempotent calls after connection errors.
+///
+/// The retries share the deadline of the first attempt, and wait a jittered backoff in between;
+/// a retry that could not start before the deadline is not attempted.
+async fn forward(
+    state: &AppState,
+    parts: Parts,
+    body: Bytes,
+    method: &str,
+    limits: CallLimits,
+    trail: &mut AuditTrail,
+) -> Result<Response, HandlerError> {
+    let deadline = tokio::time::Instant::now() + limits.timeout;
+    let retries = if limits.idempotent {
+        state.retries.retries()
+    } else {
+        0
+    };
+
+    let mut retry = 0;
+    loop {
+        let error = match try_upstreams(state, &parts, &body, method, limits, deadline, trail).await
+        {
+            Err(HandlerError::Internal(error @ ProxyError::Upstream(_))) if retry < retries => {
+                error
+            }
+            result => return result,
+        };
+
+        retry += 1;
+        let delay = state.retries.delay(retry);
+        if tokio::time::Instant::now() + delay >= deadline {
+            return Err(HandlerError::from(error));
+        }
+        warn!(%method, retry, ?delay, %error, "retrying idempotent call after upstream error");
+        if let Some(metrics) = &state.metrics {
+            metrics.record_upstream_retry(method);
+        }
+        sleep(delay).await;
+    }
+}
+
+/// Send the request body upstream once, returning the first usable response.
+///
+/// Upstreams are tried in the order chosen by the balancer: a connection error or `5xx`
+/// response marks the upstream unhealthy and moves on to the next one. Calls that are not
+/// idempotent only move on when the request could not be sent, since a node that failed
+/// afterwards may already have acted on it. The last `5xx` response is returned as-is. Every
+/// attempt must get its whole response before `deadline`, so an upstream that stalls halfway
+/// through the body cannot hold the client; once the deadline passes the call fails with
+/// [`ProxyError::UpstreamTimeout`], counting as a failure of the upstream that ran out of time.
+/// Each upstream's circuit is consulted just before sending to it: upstreams whose circuit is
+/// open are skipped, and when none is left the call fails fast with
+/// [`ProxyError::UpstreamUnavailable`].
+async fn try_upstreams(
+    state: &AppState,
+    parts: &Parts,
+    body: &Bytes,
+    method: &str,
+    limits: CallLimits,
+    deadline: tokio::time::Instant,
+    trail: &mut AuditTrail,
+) -> Result<Response, HandlerError> {
+    let timeout = limits.timeout;
+    let mut failure = None;
+    let mut attempt = 0;
+
+    for upstream in state.upstreams.candidates() {
+        let Some(permit) = upstream.admit() else {
+            continue;
+        };
+        attempt += 1;
+        let target_uri = match build_target_uri(upstream.uri(), &parts.uri) {
+            Ok(uri) => uri,
+            Err(error) => {
+                error!(
+                    error = ?error,
+                    incoming = %parts.uri,
+                    upstream = %upstream.uri(),
+                    "failed to construct upstream uri"
+                );
+                if let Some(metrics) = &state.metrics {
+                    metrics.record_upstream_error(&error);
+                }
+                return Err(HandlerError::from(error));
+            }
+        };
+
+        debug!(%method, upstream = %target_uri, attempt, "forwarding json-rpc request");
+        trail.upstream = Some(upstream.uri().to_string());
+
+        let mut forward_parts = parts.clone();
+        forward_parts.uri = target_uri.clone();
+
+        let mut forward_request = Request::from_parts(forward_parts, Body::from(body.clone()));
+        sanitize_request_headers(forward_request.headers_mut());
+
+        let in_flight = upstream.begin();
+        let metrics_in_flight = state
+            .metrics
+            .as_ref()
+            .map(|metrics| metrics.upstream_started(upstream.uri()));
+        let started = Instant::now();
+        let result = timeout_at(deadline, exchange(&state.client, forward_request)).await;
+        drop((in_flight, metrics_in_flight));
+        if let Some(metrics) = &state.metrics {
+            metrics.observe_upstream(upstream.uri(), started.elapsed());
+        }
+
+        let Ok(result) = result else {
+            warn!(%method, upstream = %target_uri, ?timeout, "upstream request timed out");
+            let error = ProxyError::UpstreamTimeout(timeout);
+            upstream.record_failure(&error);
+            permit.fail();
+            if let Some(metrics) = &state.metrics {
+                metrics.record_upstream_error(&error);
+            }
+            return Err(HandlerError::from(error));
+        };
+
+        match result {
+            Ok(response) if response.status().is_server_error() => {
+                if let Some(metrics) = &state.metrics {
+                    metrics.record_upstream_server_error();
+                }
+                upstream.record_failure(format_args!("HTTP {}", response.status()));
+                permit.fail();
+                if !limits.idempotent {
+                    return Ok(response);
+                }
+                warn!(%method, upstream = %target_uri, status = %response.status(), "upstream server error; trying the next upstream");
+                failure = Some(Ok(response));
+            }
+            Ok(response) => {
+                upstream.record_latency(started.elapsed());
+                upstream.record_success();
+                permit.succeed();
+                return Ok(response);
+            }
+            Err(error) => {
+                error!(
+                    error = ?error,
+                    %method,
+                    upstream = %target_uri,
+                    "upstream request failed"
+                );
+                upstream.record_failure(&error);
+                permit.fail();
+                let sent = !matches!(&error, ProxyError::Upstream(error) if error.is_connect());
+                if let Some(metrics) = &state.metrics {
+                    metrics.record_upstream_error(&error);
+                }
+                if sent && !limits.idempotent {
+                    return Err(HandlerError::from(error));
+                }
+                failure = Some(Err(error));
+            }
+        }
+    }
+
+    match failure {
+        Some(Ok(response)) => Ok(response),
+        Some(Err(error)) => Err(HandlerError::from(error)),
+        None => {
+            warn!(%method, "every upstream circuit is open; failing fast");
+            let error = ProxyError::UpstreamUnavailable;
+            if let Some(metrics) = &state.metrics {
+                metrics.record_upstream_error(&error);
+            }
+            Err(HandlerError::from(error))
+        }
+    }
+}
+
+/// Send one request upstream and read the whole response.
+async fn exchange(
+    client: &Client<UpstreamConnector, Body>,
+    request: Request<Body>,
+) -> Result<Response, ProxyError> {
+    let (parts, body) = client
+        .request(request)
+        .await
+        .map_err(ProxyError::Upstream)?
+        .into_parts();
+    let bytes = body
+        .collect()
+        .await
+        .map_err(|error| ProxyError::UpstreamBody(Box::new(error)))?
+        .to_bytes();
+    Ok(Response::from_parts(parts, Body::from(bytes)))
+}
+
+/// Position of a non-notification entry within the batch response.
+#[derive(Debug)]
+pub(crate) enum BatchSlot {
+    /// Response produced by the proxy itself.
+    Local(Value),
+    /// Response expected from the upstream, matched by request id.
+    Upstream(Value),
+}
+
+/// Collect the locally produced responses of a batch that was never forwarded.
+pub(crate) fn local_responses(slots: Vec<BatchSlot>) -> Vec<Value> {
+    slots
+        .into_iter()
+        .filter_map(|slot| match slot {
+            BatchSlot::Local(value) => Some(value),
+            BatchSlot::Upstream(_) => None,
+        })
+        .collect()
+}
+
+/// Merge upstream batch responses with locally produced ones in original batch order.
+fn merge_batch_responses(slots: Vec<BatchSlot>, upstream: Value) -> Vec<Value> {
+    let mut by_id: HashMap<String, VecDeque<Value>> = HashMap::new();
+    let mut upstream_error = None;
+
+    match upstream {
+        Value::Array(responses) => {
+            for response in responses {
+                let key = response.get("id").unwrap_or(&Value::Null).to_string();
+                by_id.entry(key).or_default().push_back(response);
+            }
+        }
+        Value::Object(mut object) => upstream_error = object.remove("error"),
+        _ => {}
+    }
+
+    slots
+        .into_iter()
+        .map(|slot| match slot {
+            BatchSlot::Local(value) => value,
+            BatchSlot::Upstream(id) => by_id
+                .get_mut(&id.to_string())
+                .and_then(VecDeque::pop_front)
+                .unwrap_or_else(|| {
+                    let error = upstream_error.clone().unwrap_or_else(|| {
+                        json!({
+                            "code": -32603,
+                            "message": "missing response from upstream",
+                        })
+                    });
+                    json!({ "jsonrpc": "2.0", "error": error, "id": id })
+                }),
+        })
+        .collect()
+}
+
+/// Build the [`Response`] for a merged batch; an empty batch result produces an empty body.
+fn batch_response(status: StatusCode, responses: Vec<Value>) -> Response {
+    let builder = Response::builder().status(status);
+    if responses.is_empty() {
+        return builder.body(Body::empty()).expect("valid batch response");
+    }
+
+    builder
+        .header("content-type", "application/json")
+        .body(Body::from(Value::Array(responses).to_string()))
+        .expect("valid batch response")
+}
+
+/// HTTP status and JSON-RPC error object answering a call the upstream could not serve.
+///
+/// Covers calls the upstream did not answer in time (`504`) and calls refused because every
+/// upstream circuit is open (`503`); other errors are left to the caller.
+fn upstream_failure(error: &ProxyError) -> Option<(StatusCode, Value)> {
+    match error {
+        ProxyError::UpstreamTimeout(_) => Some((
+            StatusCode::GATEWAY_TIMEOUT,
+            json!({ "code": -32603, "message": "upstream timeout" }),
+        )),
+        ProxyError::UpstreamUnavailable => Some((
+            StatusCode::SERVICE_UNAVAILABLE,
+            json!({ "code": -32603, "message": "upstream unavailable" }),
+        )),
+        _ => None,
+    }
+}
+
+/// Build the [`Response`] for a single call the upstream could not serve.
+fn upstream_failure_response(status: StatusCode, id: &Value, error: Value) -> Response {
     let payload = json!({
         "jsonrpc": "2.0",
-        "error": {
-            "code": -32601,
-            "message": format!("Method '{method}' blocked by veto proxy"),
-        },
+        "error": error,
         "id": id.clone(),
     });
 
     Response::builder()
-        .status(StatusCode::OK)
+        .status(status)
+        .header("content-type", "application/json")
+        .body(Body::from(payload.to_string()))
+        .expect("valid upstream failure response")
+}
+
+/// Remove hop-by-hop headers before forwarding the request upstream.
+fn sanitize_request_headers(headers: &mut HeaderMap) {
+    headers.remove("host");
+}
+
+/// Remove the client's framing headers from a request whose body the proxy rewrote, so the
+/// upstream request is framed by the new body instead.
+fn rewrite_request_body(headers: &mut HeaderMap) {
+    headers.remove(CONTENT_LENGTH);
+    headers.remove(TRANSFER_ENCODING);
+}
+
+/// Build the JSON-RPC error payload returned for a blocked method.
+pub(crate) fn blocked_method_payload(id: &Value, method: &str, rejection: &Rejection) -> Value {
+    let mut error = json!({
+        "code": rejection.code(),
+        "message": rejection.message(method),
+    });
+    if let Some(data) = rejection.data() {
+        error["data"] = data;
+    }
+
+    json!({
+        "jsonrpc": "2.0",
+        "error": error,
+        "id": id.clone(),
+    })
+}
+
+/// Build the JSON-RPC error [`Response`] sent when a method is blocked.
+///
+/// The status follows [`Rejection::status`]; rate-limited calls also get a `Retry-After` header.
+pub(crate) fn blocked_method_response(id: &Value, method: &str, rejection: &Rejection) -> Response {
+    let payload = blocked_method_payload(id, method, rejection);
+
+    let mut builder = Response::builder().status(rejection.status());
+    if let Some(retry_after) = rejection.retry_after() {
+        builder = builder.header(RETRY_AFTER, retry_after);
+    }
+    builder
         .header("content-type", "application/json")
         .body(Body::from(payload.to_string()))
         .expect("valid blocked response")
 }
 
 /// Construct the upstream [`Uri`] by combining the base host with the incoming path/query.
-fn build_target_uri(base: &Uri, incoming: &Uri) -> Result<Uri, ProxyError> {
+pub(crate) fn build_target_uri(base: &Uri, incoming: &Uri) -> Result<Uri, ProxyError> {
     let mut parts = base.clone().into_parts();
     if let Some(path_and_query) = incoming.path_and_query() {
         parts.path_and_query = Some(path_and_query.clone());
@@ -196,15 +1190,25 @@ impl From<JsonRpcError> for HandlerError {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::circuit::CircuitState;
     use http::{Request, StatusCode};
     use rstest::rstest;
     use serde_json::{Value, json};
     use std::collections::HashSet;
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use tower::util::ServiceExt;
+    use veto_config::{
+        ApiKey, CircuitBreaker, MethodTimeout, PolicyMode, RateLimit, ResponseCache,
+        UpstreamTimeouts, default_cache_rules,
+    };
 
     #[tokio::test]
     async fn blocked_method_response_contains_message() {
-        let response = blocked_method_response(&Value::from(1), "eth_sendTransaction");
+        let response = blocked_method_response(
+            &Value::from(1),
+            "eth_sendTransaction",
+            &Rejection::Blocked("eth_sendtransaction".into()),
+        );
         assert_eq!(response.status(), StatusCode::OK);
         let bytes = http_body_util::BodyExt::collect(response.into_body())
             .await
@@ -229,6 +1233,20 @@ mod tests {
         assert_eq!(result.to_string(), "http://127.0.0.1:8545/custom");
     }
 
+    #[cfg(not(feature = "tls"))]
+    #[rstest]
+    fn https_upstream_requires_tls_feature() {
+        let config = Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            "https://node.example".parse().unwrap(),
+            HashSet::new(),
+        );
+        assert!(matches!(
+            AppState::try_from_config(config),
+            Err(ProxyError::TlsUnsupported)
+        ));
+    }
+
     #[tokio::test]
     async fn end_to_end_blocks_configured_method() {
         let config = Config::new(
@@ -262,4 +1280,1006 @@ mod tests {
         let value: Value = serde_json::from_slice(&bytes).unwrap();
         assert_eq!(value["error"]["code"], -32601);
     }
+
+    #[tokio::test]
+    async fn allowlist_mode_rejects_unlisted_methods() {
+        let config = Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            "http://127.0.0.1:1".parse().unwrap(),
+            HashSet::new(),
+        )
+        .with_mode(PolicyMode::Allowlist)
+        .with_allowed_methods(HashSet::from([String::from("eth_chainid")]));
+        let app = router(AppState::try_from_config(config).unwrap());
+
+        let payload = json!({"jsonrpc": "2.0", "id": 9, "method": "eth_call", "params": []});
+        let response = app.oneshot(batch_request(&payload)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let value = read_json(response).await;
+        assert_eq!(value["id"], 9);
+        assert_eq!(value["error"]["code"], -32601);
+        assert!(
+            value["error"]["message"]
+                .as_str()
+                .unwrap()
+                .contains("allowlist mode")
+        );
+    }
+
+    async fn read_json(response: Response) -> Value {
+        let bytes = http_body_util::BodyExt::collect(response.into_body())
+            .await
+            .unwrap()
+            .to_bytes();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    /// Spawn an upstream that answers every batch entry with its method name, in reverse order.
+    async fn spawn_echo_upstream() -> Uri {
+        async fn echo(body: Bytes) -> Response {
+            let entries: Vec<Value> = serde_json::from_slice(&body).unwrap();
+            let responses: Vec<Value> = entries
+                .iter()
+                .rev()
+                .filter(|entry| entry.get("id").is_some())
+                .map(
+                    |entry| json!({"jsonrpc": "2.0", "id": entry["id"], "result": entry["method"]}),
+                )
+                .collect();
+            Response::new(Body::from(Value::Array(responses).to_string()))
+        }
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, Router::new().fallback(any(echo)))
+                .await
+                .unwrap();
+        });
+        format!("http://{address}").parse().unwrap()
+    }
+
+    fn batch_request(payload: &Value) -> Request<Body> {
+        let body = payload.to_string();
+        Request::builder()
+            .method("POST")
+            .uri("/")
+            .header("content-type", "application/json")
+            .header(CONTENT_LENGTH, body.len())
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    #[rstest]
+    fn merge_batch_keeps_original_order() {
+        let slots = vec![
+            BatchSlot::Upstream(json!(1)),
+            BatchSlot::Local(blocked_method_payload(
+                &json!(2),
+                "eth_sendTransaction",
+                &Rejection::Blocked("eth_sendtransaction".into()),
+            )),
+            BatchSlot::Upstream(json!("three")),
+            BatchSlot::Upstream(json!(4)),
+        ];
+        let upstream = json!([
+            {"jsonrpc": "2.0", "id": "three", "result": "0x3"},
+            {"jsonrpc": "2.0", "id": 1, "result": "0x1"},
+        ]);
+
+        let merged = merge_batch_responses(slots, upstream);
+        assert_eq!(merged.len(), 4);
+        assert_eq!(merged[0]["result"], "0x1");
+        assert_eq!(merged[1]["error"]["code"], -32601);
+        assert_eq!(merged[2]["id"], "three");
+        assert_eq!(merged[3]["error"]["code"], -32603);
+        assert_eq!(merged[3]["id"], 4);
+    }
+
+    #[tokio::test]
+    async fn end_to_end_batch_filters_blocked_entries() {
+        let upstream = spawn_echo_upstream().await;
+        let config = Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            upstream,
+            HashSet::from([String::from("eth_sendtransaction")]),
+        );
+        let app = router(AppState::try_from_config(config).unwrap());
+
+        let payload = json!([
+            {"jsonrpc": "2.0", "id": 1, "method": "eth_chainId"},
+            {"jsonrpc": "2.0", "id": 2, "method": "eth_sendTransaction", "params": []},
+            {"jsonrpc": "2.0", "method": "eth_sendTransaction", "params": []},
+            {"jsonrpc": "2.0", "id": 3, "method": "eth_blockNumber"},
+            {"jsonrpc": "2.0", "id": 4},
+        ]);
+        let response = app.oneshot(batch_request(&payload)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let value = read_json(response).await;
+        let responses = value.as_array().unwrap();
+        assert_eq!(responses.len(), 4);
+        assert_eq!(responses[0]["id"], 1);
+        assert_eq!(responses[0]["result"], "eth_chainId");
+        assert_eq!(responses[1]["id"], 2);
+        assert_eq!(responses[1]["error"]["code"], -32601);
+        assert_eq!(responses[2]["id"], 3);
+        assert_eq!(responses[2]["result"], "eth_blockNumber");
+        assert_eq!(responses[3]["error"]["code"], -32600);
+    }
+
+    #[tokio::test]
+    async fn filtered_batches_are_reframed_for_the_upstream() {
+        async fn headers(request: Request<Body>) -> Response {
+            let (parts, body) = request.into_parts();
+            let body = http_body_util::BodyExt::collect(body)
+                .await
+                .unwrap()
+                .to_bytes();
+            let header = |name| parts.headers.get(name).map(|value| value.to_str().unwrap());
+            let result = json!({
+                "body": body.len(),
+                "content-length": header(CONTENT_LENGTH),
+                "accept-encoding": header(ACCEPT_ENCODING),
+            });
+            let entries: Vec<Value> = serde_json::from_slice(&body).unwrap();
+            let responses: Vec<Value> = entries
+                .iter()
+                .map(|entry| json!({"jsonrpc": "2.0", "id": entry["id"], "result": result}))
+                .collect();
+            Response::new(Body::from(Value::Array(responses).to_string()))
+        }
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, Router::new().fallback(any(headers)))
+                .await
+                .unwrap();
+        });
+        let config = Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            format!("http://{address}").parse().unwrap(),
+            HashSet::from([String::from("eth_sendtransaction")]),
+        );
+        let app = router(AppState::try_from_config(config).unwrap());
+
+        let payload = json!([
+            {"jsonrpc": "2.0", "id": 1, "method": "eth_chainId"},
+            {"jsonrpc": "2.0", "id": 2, "method": "eth_sendTransaction", "params": []},
+        ]);
+        let mut request = batch_request(&payload);
+        request
+            .headers_mut()
+            .insert(ACCEPT_ENCODING, HeaderValue::from_static("gzip"));
+        let response = tokio::time::timeout(Duration::from_secs(5), app.oneshot(request))
+            .await
+            .expect("filtered batch is answered")
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let value = read_json(response).await;
+        let result = &value[0]["result"];
+        assert_eq!(result["content-length"], result["body"].to_string());
+        assert_eq!(result["accept-encoding"], "identity");
+        assert_eq!(value[1]["error"]["code"], -32601);
+    }
+
+    #[tokio::test]
+    async fn param_rules_filter_batch_entries_by_params() {
+        let upstream = spawn_echo_upstream().await;
+        let rule = serde_json::from_value(json!({
+            "name": "no-fork-reset",
+            "method": "anvil_reset",
+            "when": [{ "path": "$[0].forking.jsonRpcUrl", "exists": true }]
+        }))
+        .unwrap();
+        let config = Config::new("127.0.0.1:0".parse().unwrap(), upstream, HashSet::new())
+            .with_param_rules(vec![rule]);
+        let app = router(AppState::try_from_config(config).unwrap());
+
+        let payload = json!([
+            {"jsonrpc": "2.0", "id": 1, "method": "anvil_reset", "params": []},
+            {
+                "jsonrpc": "2.0",
+                "id": 2,
+                "method": "anvil_reset",
+                "params": [{"forking": {"jsonRpcUrl": "https://mainnet.example"}}]
+            },
+        ]);
+        let response = app.oneshot(batch_request(&payload)).await.unwrap();
+        let value = read_json(response).await;
+
+        assert_eq!(value[0]["result"], "anvil_reset");
+        assert_eq!(value[1]["error"]["code"], -32601);
+        assert!(
+            value[1]["error"]["message"]
+                .as_str()
+                .unwrap()
+                .contains("no-fork-reset")
+        );
+    }
+
+    #[tokio::test]
+    async fn raw_transaction_rejection_carries_decoded_reason() {
+        let rule = veto_config::TxRule {
+            name: "no-value".into(),
+            value: Some(veto_config::QuantityRange {
+                min: Some(veto_config::Quantity(1)),
+                max: None,
+            }),
+            ..Default::default()
+        };
+        let config = Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            "http://127.0.0.1:1".parse().unwrap(),
+            HashSet::new(),
+        )
+        .with_tx_rules(vec![rule]);
+        let app = router(AppState::try_from_config(config).unwrap());
+
+        let payload = json!({
+            "jsonrpc": "2.0",
+            "id": 5,
+            "method": "eth_sendRawTransaction",
+            "params": [crate::tx::tests::EIP155_EXAMPLE]
+        });
+        let value = read_json(app.oneshot(batch_request(&payload)).await.unwrap()).await;
+
+        assert_eq!(value["id"], 5);
+        assert_eq!(value["error"]["code"], -32003);
+        assert!(
+            value["error"]["message"]
+                .as_str()
+                .unwrap()
+                .contains("rule 'no-value'")
+        );
+        assert_eq!(value["error"]["data"]["rule"], "no-value");
+        assert_eq!(
+            value["error"]["data"]["transaction"]["to"],
+            "0x3535353535353535353535353535353535353535"
+        );
+    }
+
+    #[tokio::test]
+    async fn fully_blocked_batch_is_answered_locally() {
+        let config = Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            "http://127.0.0.1:1".parse().unwrap(),
+            HashSet::from([String::from("anvil_mine")]),
+        );
+        let app = router(AppState::try_from_config(config).unwrap());
+
+        let payload = json!([
+            {"jsonrpc": "2.0", "id": 1, "method": "anvil_mine"},
+            {"jsonrpc": "2.0", "id": 2, "method": "ANVIL_MINE"},
+        ]);
+        let response = app.oneshot(batch_request(&payload)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let value = read_json(response).await;
+        assert_eq!(value[0]["id"], 1);
+        assert_eq!(value[1]["id"], 2);
+        assert_eq!(value[1]["error"]["code"], -32601);
+    }
+
+    #[tokio::test]
+    async fn rate_limited_calls_get_too_many_requests() {
+        let upstream = spawn_echo_upstream().await;
+        let config = Config::new("127.0.0.1:0".parse().unwrap(), upstream, HashSet::new())
+            .with_rate_limits(vec![RateLimit {
+                method: Some("eth_call".into()),
+                requests_per_second: 1,
+                burst: None,
+            }]);
+        let app = router(AppState::try_from_config(config).unwrap());
+        let call = json!({"jsonrpc": "2.0", "id": 7, "method": "eth_call", "params": []});
+
+        let response = app.clone().oneshot(batch_request(&json!([call]))).await;
+        assert_eq!(response.unwrap().status(), StatusCode::OK);
+
+        let response = app.clone().oneshot(batch_request(&call)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[RETRY_AFTER], "1");
+        let value = read_json(response).await;
+        assert_eq!(value["id"], 7);
+        assert_eq!(value["error"]["code"], -32005);
+        assert_eq!(value["error"]["message"], "limit exceeded");
+        assert_eq!(value["error"]["data"]["limit"], "eth_call");
+
+        // A batch with other methods is still forwarded; one with only limited calls is not.
+        let other = json!({"jsonrpc": "2.0", "id": 8, "method": "eth_chainId"});
+        let response = app
+            .clone()
+            .oneshot(batch_request(&json!([call, other])))
+            .await;
+        let response = response.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let value = read_json(response).await;
+        assert_eq!(value[0]["error"]["code"], -32005);
+        assert_eq!(value[1]["result"], "eth_chainId");
+
+        let response = app
+            .oneshot(batch_request(&json!([call, call])))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[RETRY_AFTER], "1");
+    }
+
+    #[tokio::test]
+    async fn api_keys_select_the_policy() {
+        let upstream = spawn_echo_upstream().await;
+        let key = |name: &str, blocked: Option<Vec<String>>| ApiKey {
+            name: name.into(),
+            key: format!("{name}-secret"),
+            mode: None,
+            blocked_methods: blocked,
+            allowed_methods: None,
+            rate_limits: Some(vec![RateLimit {
+                method: None,
+                requests_per_second: 1,
+                burst: Some(2),
+            }]),
+        };
+        let config = Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            upstream,
+            HashSet::from([String::from("anvil_mine")]),
+        )
+        .with_api_keys(vec![key("ci", Some(Vec::new())), key("tooling", None)]);
+        let app = router(AppState::try_from_config(config).unwrap());
+        let mine = json!([{"jsonrpc": "2.0", "id": 1, "method": "anvil_mine"}]);
+        let post = |uri: &str, key: Option<&str>| {
+            let mut request = batch_request(&mine);
+            *request.uri_mut() = uri.parse().unwrap();
+            if let Some(key) = key {
+                request
+                    .headers_mut()
+                    .insert("x-api-key", key.parse().unwrap());
+            }
+            request
+        };
+
+        for request in [post("/", None), post("/", Some("wrong"))] {
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            let value = read_json(response).await;
+            assert_eq!(value["error"]["code"], -32001);
+            assert_eq!(value["id"], Value::Null);
+        }
+
+        let response = app.clone().oneshot(post("/", Some("tooling-secret"))).await;
+        let value = read_json(response.unwrap()).await;
+        assert_eq!(value[0]["error"]["code"], -32601);
+
+        let response = app.clone().oneshot(post("/key/ci-secret", None)).await;
+        let value = read_json(response.unwrap()).await;
+        assert_eq!(value[0]["result"], "anvil_mine");
+
+        // Each key has its own bucket, regardless of how it is presented.
+        let response = app.clone().oneshot(post("/?api_key=ci-secret", None)).await;
+        assert_eq!(response.unwrap().status(), StatusCode::OK);
+        let response = app.clone().oneshot(post("/", Some("ci-secret"))).await;
+        assert_eq!(response.unwrap().status(), StatusCode::TOO_MANY_REQUESTS);
+        let response = app.oneshot(post("/", Some("tooling-secret"))).await;
+        assert_eq!(response.unwrap().status(), StatusCode::OK);
+    }
+
+    /// Spawn an upstream that answers every request with `503 Service Unavailable`.
+    async fn spawn_failing_upstream() -> Uri {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let app = Router::new().fallback(any(|| async { StatusCode::SERVICE_UNAVAILABLE }));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{address}").parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn fails_over_on_connection_errors_and_server_errors() {
+        let healthy = spawn_echo_upstream().await;
+        let failing = spawn_failing_upstream().await;
+        let config = Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            "http://127.0.0.1:1".parse().unwrap(),
+            HashSet::new(),
+        )
+        .with_upstreams(vec![
+            "http://127.0.0.1:1".parse().unwrap(),
+            failing,
+            healthy.clone(),
+        ]);
+        let state = AppState::try_from_config(config).unwrap();
+        let app = router(state.clone());
+
+        let payload = json!([{"jsonrpc": "2.0", "id": 1, "method": "eth_chainId"}]);
+        let response = app.oneshot(batch_request(&payload)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(read_json(response).await[0]["result"], "eth_chainId");
+
+        let candidates = state.upstreams.candidates();
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].uri(), &healthy);
+    }
+
+    #[tokio::test]
+    async fn requests_are_counted_when_metrics_are_enabled() {
+        let config = Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            spawn_echo_upstream().await,
+            HashSet::from(["eth_sendtransaction".to_string()]),
+        )
+        .with_metrics_address(Some("127.0.0.1:0".parse().unwrap()));
+        let state = AppState::try_from_config(config).unwrap();
+        let app = router(state.clone());
+
+        let payload = json!([
+            {"jsonrpc": "2.0", "id": 1, "method": "eth_chainId"},
+            {"jsonrpc": "2.0", "id": 2, "method": "eth_sendTransaction"},
+            {"jsonrpc": "2.0", "id": 3, "method": ""},
+        ]);
+        let response = app.clone().oneshot(batch_request(&payload)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app.oneshot(batch_request(&json!("nope"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let rendered = state.metrics.as_ref().unwrap().render();
+        for line in [
+            r#"veto_requests_forwarded_total{method="eth_chainId"} 1"#,
+            r#"veto_requests_blocked_total{method="eth_sendTransaction",rule="eth_sendtransaction"} 1"#,
+            r#"veto_parse_errors_total{error="invalid_request"} 2"#,
+            r#"veto_request_duration_seconds_count{method="batch"} 1"#,
+            "veto_requests_in_flight 0",
+        ] {
+            assert!(rendered.contains(line), "missing {line:?} in:\n{rendered}");
+        }
+        assert!(rendered.contains("veto_upstream_duration_seconds_count{upstream="));
+    }
+
+    #[tokio::test]
+    async fn decisions_are_written_to_the_audit_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+        let upstream = spawn_echo_upstream().await;
+        let config = Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            upstream.clone(),
+            HashSet::from(["anvil_setcode".to_string()]),
+        )
+        .with_audit_log(Some(veto_config::AuditLog {
+            path: path.clone(),
+            max_bytes: 1 << 20,
+            max_files: 1,
+        }));
+        let app = router(AppState::try_from_config(config).unwrap());
+
+        let payload = json!({"jsonrpc": "2.0", "id": 9, "method": "anvil_setCode"});
+        app.clone().oneshot(batch_request(&payload)).await.unwrap();
+        let payload = json!([{"jsonrpc": "2.0", "id": 10, "method": "eth_chainId"}]);
+        app.oneshot(batch_request(&payload)).await.unwrap();
+
+        let mut lines = Vec::new();
+        for _ in 0..100 {
+            let contents = std::fs::read_to_string(&path).unwrap();
+            lines = contents
+                .lines()
+                .map(|line| serde_json::from_str::<Value>(line).unwrap())
+                .collect();
+            if lines.len() == 2 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["method"], "anvil_setCode");
+        assert_eq!(lines[0]["decision"], "blocked");
+        assert_eq!(lines[0]["rule"], "anvil_setcode");
+        assert_eq!(lines[0]["id"], 9);
+        assert_eq!(lines[1]["decision"], "forwarded");
+        assert_eq!(lines[1]["upstream"], upstream.to_string());
+        assert_eq!(lines[1]["status"], 200);
+    }
+
+    #[tokio::test]
+    async fn status_endpoint_reports_rotation() {
+        let config = Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            spawn_echo_upstream().await,
+            HashSet::new(),
+        );
+        let state = AppState::try_from_config(config).unwrap();
+        let app = router(state.clone());
+        let status = || Request::get(STATUS_PATH).body(Body::empty()).unwrap();
+
+        let response = app.clone().oneshot(status()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let value = read_json(response).await;
+        assert_eq!(value["status"], "ok");
+        assert_eq!(value["upstreams"][0]["in_rotation"], true);
+
+        state.upstreams.upstreams()[0].record_failure("test");
+        let response = app.oneshot(status()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(read_json(response).await["status"], "down");
+    }
+
+    #[tokio::test]
+    async fn round_robin_spreads_requests_and_reports_stats() {
+        let first = spawn_echo_upstream().await;
+        let second = spawn_echo_upstream().await;
+        let config = Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            first.clone(),
+            HashSet::new(),
+        )
+        .with_upstreams(vec![first, second])
+        .with_balance(veto_config::BalanceStrategy::RoundRobin);
+        let state = AppState::try_from_config(config).unwrap();
+        let app = router(state.clone());
+
+        let payload = json!([{"jsonrpc": "2.0", "id": 1, "method": "eth_chainId"}]);
+        for _ in 0..4 {
+            let response = app.clone().oneshot(batch_request(&payload)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        let stats = state.upstream_stats();
+        assert_eq!(stats.len(), 2);
+        for upstream in stats {
+            assert_eq!(upstream.requests, 2);
+            assert_eq!(upstream.in_flight, 0);
+            assert!(upstream.healthy);
+            assert!(upstream.ewma_latency_ms.is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn last_upstream_server_error_is_returned() {
+        let failing = spawn_failing_upstream().await;
+        let config = Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            "http://127.0.0.1:1".parse().unwrap(),
+            HashSet::new(),
+        )
+        .with_upstreams(vec!["http://127.0.0.1:1".parse().unwrap(), failing]);
+        let app = router(AppState::try_from_config(config).unwrap());
+
+        let payload = json!({"jsonrpc": "2.0", "id": 1, "method": "eth_chainId"});
+        let response = app.oneshot(batch_request(&payload)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn oversized_batch_is_rejected() {
+        let config = Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            "http://127.0.0.1:1".parse().unwrap(),
+            HashSet::new(),
+        )
+        .with_max_batch_size(1);
+        let app = router(AppState::try_from_config(config).unwrap());
+
+        let payload = json!([
+            {"jsonrpc": "2.0", "id": 1, "method": "eth_chainId"},
+            {"jsonrpc": "2.0", "id": 2, "method": "eth_chainId"},
+        ]);
+        let response = app.oneshot(batch_request(&payload)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(read_json(response).await["error"]["code"], -32600);
+    }
+
+    #[tokio::test]
+    async fn oversized_body_is_rejected() {
+        let config = Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            "http://127.0.0.1:1".parse().unwrap(),
+            HashSet::new(),
+        )
+        .with_max_request_bytes(32);
+        let app = router(AppState::try_from_config(config).unwrap());
+
+        let payload = json!({"jsonrpc": "2.0", "id": 1, "method": "eth_getBlockByNumber"});
+        let response = app.clone().oneshot(batch_request(&payload)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let body = read_json(response).await;
+        assert_eq!(body["error"]["code"], -32600);
+        assert_eq!(
+            body["error"]["message"],
+            "request body exceeds the maximum of 32 bytes"
+        );
+
+        // A declared Content-Length is refused before the body is read.
+        let mut request = batch_request(&json!({}));
+        request
+            .headers_mut()
+            .insert(CONTENT_LENGTH, HeaderValue::from(1_000_000));
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn slow_upstream_calls_time_out() {
+        async fn slow(body: Bytes) -> Response {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            let entries: Vec<Value> = serde_json::from_slice(&body).unwrap();
+            let responses: Vec<Value> = entries
+                .iter()
+                .map(
+                    |entry| json!({"jsonrpc": "2.0", "id": entry["id"], "result": entry["method"]}),
+                )
+                .collect();
+            Response::new(Body::from(Value::Array(responses).to_string()))
+        }
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, Router::new().fallback(any(slow)))
+                .await
+                .unwrap();
+        });
+        let timeouts = UpstreamTimeouts::new(
+            Duration::from_millis(50),
+            vec![MethodTimeout {
+                method: "debug_*".into(),
+                timeout_ms: 5_000,
+            }],
+        )
+        .unwrap();
+        let config = Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            format!("http://{address}").parse().unwrap(),
+            HashSet::new(),
+        )
+        .with_upstream_timeouts(timeouts);
+        let app = router(AppState::try_from_config(config).unwrap());
+
+        let call = json!({"jsonrpc": "2.0", "id": 7, "method": "eth_call"});
+        let response = app.clone().oneshot(batch_request(&call)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        let body = read_json(response).await;
+        assert_eq!(body["id"], 7);
+        assert_eq!(body["error"]["code"], -32603);
+        assert_eq!(body["error"]["message"], "upstream timeout");
+
+        let response = app
+            .clone()
+            .oneshot(batch_request(&json!([call])))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        let body = read_json(response).await;
+        assert_eq!(body[0]["id"], 7);
+        assert_eq!(body[0]["error"]["message"], "upstream timeout");
+
+        // A batch waits for the longest deadline among its calls.
+        let batch = json!([
+            {"jsonrpc": "2.0", "id": 1, "method": "debug_traceTransaction"},
+            call,
+        ]);
+        let response = app.oneshot(batch_request(&batch)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = read_json(response).await;
+        assert_eq!(body[1]["result"], "eth_call");
+    }
+
+    /// Upstream that drops its first `resets` connections unanswered, then answers every call.
+    async fn spawn_flaky_upstream(resets: usize) -> (Uri, Arc<AtomicUsize>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&connections);
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buffer = [0; 4096];
+                let _ = stream.read(&mut buffer).await;
+                if counter.fetch_add(1, Ordering::SeqCst) < resets {
+                    continue;
+                }
+                let body = r#"{"jsonrpc":"2.0","id":1,"result":"0x1"}"#;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        (format!("http://{address}").parse().unwrap(), connections)
+    }
+
+    #[tokio::test]
+    async fn only_idempotent_calls_are_retried() {
+        for (method, status, attempts) in [
+            ("eth_call", StatusCode::OK, 2),
+            ("eth_sendTransaction", StatusCode::INTERNAL_SERVER_ERROR, 1),
+        ] {
+            let (upstream, connections) = spawn_flaky_upstream(1).await;
+            let config = Config::new("127.0.0.1:0".parse().unwrap(), upstream, HashSet::new());
+            let app = router(AppState::try_from_config(config).unwrap());
+
+            let call = json!({"jsonrpc": "2.0", "id": 1, "method": method});
+            let response = app.oneshot(batch_request(&call)).await.unwrap();
+            assert_eq!(response.status(), status, "{method}");
+            assert_eq!(connections.load(Ordering::SeqCst), attempts, "{method}");
+        }
+    }
+
+    #[tokio::test]
+    async fn non_idempotent_calls_fail_over_only_before_they_are_sent() {
+        let failing = spawn_failing_upstream().await;
+        let (healthy, connections) = spawn_flaky_upstream(0).await;
+        let call = json!({"jsonrpc": "2.0", "id": 1, "method": "eth_sendTransaction"});
+
+        let upstreams = vec![failing, healthy.clone()];
+        let config = Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            healthy.clone(),
+            HashSet::new(),
+        )
+        .with_upstreams(upstreams);
+        let app = router(AppState::try_from_config(config).unwrap());
+        let response = app.oneshot(batch_request(&call)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(connections.load(Ordering::SeqCst), 0);
+
+        let upstreams = vec!["http://127.0.0.1:1".parse().unwrap(), healthy.clone()];
+        let config = Config::new("127.0.0.1:0".parse().unwrap(), healthy, HashSet::new())
+            .with_upstreams(upstreams);
+        let app = router(AppState::try_from_config(config).unwrap());
+        let response = app.oneshot(batch_request(&call)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn open_circuit_fails_fast() {
+        let (upstream, connections) = spawn_flaky_upstream(2).await;
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(60), 1).unwrap();
+        let config = Config::new("127.0.0.1:0".parse().unwrap(), upstream, HashSet::new())
+            .with_circuit_breaker(Some(breaker));
+        let state = AppState::try_from_config(config).unwrap();
+        let app = router(state.clone());
+
+        let call = json!({"jsonrpc": "2.0", "id": 7, "method": "eth_sendTransaction"});
+        for _ in 0..2 {
+            let response = app.clone().oneshot(batch_request(&call)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        }
+        assert_eq!(state.upstream_stats()[0].circuit, Some(CircuitState::Open));
+
+        let response = app.clone().oneshot(batch_request(&call)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = read_json(response).await;
+        assert_eq!(body["id"], 7);
+        assert_eq!(body["error"]["message"], "upstream unavailable");
+
+        let batch = json!([call, {"jsonrpc": "2.0", "id": 8, "method": "eth_chainId"}]);
+        let response = app.oneshot(batch_request(&batch)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = read_json(response).await;
+        assert_eq!(body[1]["error"]["message"], "upstream unavailable");
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn timeouts_open_the_circuit() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let app = Router::new().fallback(any(std::future::pending::<Response>));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(60), 1).unwrap();
+        let timeouts = UpstreamTimeouts::new(Duration::from_millis(50), Vec::new()).unwrap();
+        let config = Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            format!("http://{address}").parse().unwrap(),
+            HashSet::new(),
+        )
+        .with_upstream_timeouts(timeouts)
+        .with_circuit_breaker(Some(breaker));
+        let state = AppState::try_from_config(config).unwrap();
+        let app = router(state.clone());
+
+        let call = json!({"jsonrpc": "2.0", "id": 1, "method": "eth_call"});
+        let response = app.clone().oneshot(batch_request(&call)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(state.upstream_stats()[0].circuit, Some(CircuitState::Open));
+
+        let response = app.oneshot(batch_request(&call)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn stalled_response_bodies_time_out() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            use tokio::io::AsyncWriteExt;
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let head = "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: 64\r\n\r\n{\"jsonrpc\":";
+                    socket.write_all(head.as_bytes()).await.unwrap();
+                    std::future::pending::<()>().await;
+                });
+            }
+        });
+        let timeouts = UpstreamTimeouts::new(Duration::from_millis(50), Vec::new()).unwrap();
+        let config = Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            format!("http://{address}").parse().unwrap(),
+            HashSet::new(),
+        )
+        .with_upstream_timeouts(timeouts);
+        let app = router(AppState::try_from_config(config).unwrap());
+
+        let call = json!({"jsonrpc": "2.0", "id": 1, "method": "eth_call"});
+        let response =
+            tokio::time::timeout(Duration::from_secs(5), app.oneshot(batch_request(&call)))
+                .await
+                .expect("the body read is bounded by the deadline")
+                .unwrap();
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        let body = read_json(response).await;
+        assert_eq!(body["error"]["message"], "upstream timeout");
+    }
+
+    #[tokio::test]
+    async fn untried_upstreams_are_not_admitted() {
+        let (healthy, _) = spawn_flaky_upstream(0).await;
+        let standby: Uri = "http://127.0.0.1:1".parse().unwrap();
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(10), 1).unwrap();
+        let config = Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            healthy.clone(),
+            HashSet::new(),
+        )
+        .with_upstreams(vec![healthy, standby.clone()])
+        .with_circuit_breaker(Some(breaker))
+        .with_metrics_address(Some("127.0.0.1:0".parse().unwrap()));
+        let state = AppState::try_from_config(config).unwrap();
+        state.upstreams.upstreams()[1].admit().unwrap().fail();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let call = json!({"jsonrpc": "2.0", "id": 1, "method": "eth_chainId"});
+        let response = router(state.clone())
+            .oneshot(batch_request(&call))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let rendered = state.metrics.as_ref().unwrap().render();
+        let open = format!(r#"veto_upstream_circuit_state{{upstream="{standby}"}} 2"#);
+        assert!(rendered.contains(&open), "missing {open:?} in:\n{rendered}");
+    }
+
+    fn cached_config(upstream: Uri) -> Config {
+        let cache =
+            ResponseCache::new(100, Duration::from_secs(60), default_cache_rules()).unwrap();
+        Config::new("127.0.0.1:0".parse().unwrap(), upstream, HashSet::new())
+            .with_response_cache(Some(cache))
+    }
+
+    #[tokio::test]
+    async fn cache_misses_ask_the_upstream_for_plain_responses() {
+        async fn encoding(request: Request<Body>) -> Response {
+            let (parts, body) = request.into_parts();
+            let body = http_body_util::BodyExt::collect(body)
+                .await
+                .unwrap()
+                .to_bytes();
+            let call: Value = serde_json::from_slice(&body).unwrap();
+            let encoding = parts
+                .headers
+                .get(ACCEPT_ENCODING)
+                .map(|value| value.to_str().unwrap());
+            let response = json!({"jsonrpc": "2.0", "id": call["id"], "result": encoding});
+            Response::new(Body::from(response.to_string()))
+        }
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, Router::new().fallback(any(encoding)))
+                .await
+                .unwrap();
+        });
+        let app = router(
+            AppState::try_from_config(cached_config(format!("http://{address}").parse().unwrap()))
+                .unwrap(),
+        );
+
+        for (method, expected) in [("eth_chainId", "identity"), ("eth_blockNumber", "gzip")] {
+            let call = json!({"jsonrpc": "2.0", "id": 1, "method": method});
+            let mut request = batch_request(&call);
+            request
+                .headers_mut()
+                .insert(ACCEPT_ENCODING, HeaderValue::from_static("gzip"));
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(read_json(response).await["result"], expected, "{method}");
+        }
+    }
+
+    #[tokio::test]
+    async fn cacheable_calls_are_answered_from_the_cache() {
+        let (upstream, connections) = spawn_flaky_upstream(0).await;
+        let app = router(AppState::try_from_config(cached_config(upstream)).unwrap());
+        let call = |method: &str, id: u64| json!({"jsonrpc": "2.0", "id": id, "method": method});
+
+        let response = app
+            .clone()
+            .oneshot(batch_request(&call("eth_chainId", 1)))
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[X_CACHE], "MISS");
+        let response = app
+            .clone()
+            .oneshot(batch_request(&call("eth_chainId", 2)))
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[X_CACHE], "HIT");
+        let body = read_json(response).await;
+        assert_eq!(body, json!({"jsonrpc": "2.0", "id": 2, "result": "0x1"}));
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+        for _ in 0..2 {
+            let response = app
+                .clone()
+                .oneshot(batch_request(&call("eth_blockNumber", 3)))
+                .await
+                .unwrap();
+            assert!(response.headers().get(X_CACHE).is_none());
+        }
+        assert_eq!(connections.load(Ordering::SeqCst), 3);
+
+        app.clone()
+            .oneshot(batch_request(&call("evm_revert", 4)))
+            .await
+            .unwrap();
+        let response = app
+            .oneshot(batch_request(&call("eth_chainId", 5)))
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[X_CACHE], "MISS");
+    }
+
+    #[tokio::test]
+    async fn batches_mix_cached_and_forwarded_entries() {
+        let app =
+            router(AppState::try_from_config(cached_config(spawn_echo_upstream().await)).unwrap());
+        let block = json!({"jsonrpc": "2.0", "id": 1, "method": "eth_getBlockByNumber", "params": ["finalized", false]});
+        let latest = json!({"jsonrpc": "2.0", "id": 2, "method": "eth_getBlockByNumber", "params": ["latest", false]});
+
+        let response = app
+            .clone()
+            .oneshot(batch_request(&json!([block, latest])))
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[X_CACHE], "MISS");
+        let response = app
+            .clone()
+            .oneshot(batch_request(&json!([latest, block])))
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[X_CACHE], "HIT");
+        let body = read_json(response).await;
+        assert_eq!(body[0]["id"], 2);
+        assert_eq!(
+            body[1],
+            json!({"jsonrpc": "2.0", "id": 1, "result": "eth_getBlockByNumber"})
+        );
+
+        let mut cached = block.clone();
+        cached["id"] = json!(9);
+        let response = app.oneshot(batch_request(&json!([cached]))).await.unwrap();
+        assert_eq!(response.headers()[X_CACHE], "HIT");
+        let body = read_json(response).await;
+        assert_eq!(
+            body,
+            json!([{"jsonrpc": "2.0", "id": 9, "result": "eth_getBlockByNumber"}])
+        );
+    }
 }
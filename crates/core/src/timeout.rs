@@ -0,0 +1,72 @@
+use crate::errors::ProxyError;
+use std::time::Duration;
+use veto_config::{MethodMatcher, UpstreamTimeouts};
+
+/// Upstream deadlines with the method patterns of the overrides compiled.
+#[derive(Debug)]
+pub(crate) struct Timeouts {
+    default: Duration,
+    methods: Vec<(MethodMatcher, Duration)>,
+}
+
+impl Timeouts {
+    /// Compile the method patterns of the configured overrides.
+    pub(crate) fn from_config(timeouts: &UpstreamTimeouts) -> Result<Self, ProxyError> {
+        let methods = timeouts
+            .methods()
+            .iter()
+            .map(|timeout| {
+                MethodMatcher::new([&timeout.method])
+                    .map(|matcher| (matcher, timeout.timeout()))
+                    .map_err(ProxyError::Policy)
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            default: timeouts.default_timeout(),
+            methods,
+        })
+    }
+
+    /// Deadline of calls no override matches.
+    pub(crate) const fn default_timeout(&self) -> Duration {
+        self.default
+    }
+
+    /// Deadline of the first override matching `method`, or the default.
+    pub(crate) fn for_method(&self, method: &str) -> Duration {
+        self.methods
+            .iter()
+            .find(|(matcher, _)| matcher.matches(method))
+            .map_or(self.default, |(_, timeout)| *timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use veto_config::MethodTimeout;
+
+    #[rstest]
+    #[case::exact("debug_traceTransaction", 60)]
+    #[case::first_pattern_wins("debug_traceCall", 60)]
+    #[case::later_pattern("debug_getRawBlock", 20)]
+    #[case::default("eth_call", 5)]
+    fn overrides_match_in_order(#[case] method: &str, #[case] secs: u64) {
+        let timeout = |method: &str, secs: u64| MethodTimeout {
+            method: method.into(),
+            timeout_ms: secs * 1_000,
+        };
+        let timeouts = UpstreamTimeouts::new(
+            Duration::from_secs(5),
+            vec![
+                timeout("debug_trace*", 60),
+                timeout("debug_*", 20),
+                timeout("debug_tracecall", 1),
+            ],
+        )
+        .unwrap();
+        let timeouts = Timeouts::from_config(&timeouts).unwrap();
+        assert_eq!(timeouts.for_method(method), Duration::from_secs(secs));
+    }
+}
@@ -0,0 +1,427 @@
+use crate::errors::ProxyError;
+use arc_swap::ArcSwap;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{ClientConfig, RootCertStore, ServerConfig};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::server::TlsStream;
+use tracing::{debug, info, warn};
+use veto_config::{ListenerTls, UpstreamTls};
+
+/// Time allowed for a client to complete the TLS handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Pause after a failed `accept`, e.g. when the process runs out of file descriptors.
+const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Completed handshakes buffered between the accept loop and the server.
+const HANDSHAKE_BACKLOG: usize = 64;
+
+/// Build the rustls [`ClientConfig`] used for `https://` and `wss://` upstreams.
+///
+/// The WebPKI roots are always trusted; [`UpstreamTls::ca_path`] adds extra authorities and the
+/// client certificate, when configured, is presented for mutual TLS.
+pub(crate) fn client_config(settings: &UpstreamTls) -> Result<Arc<ClientConfig>, ProxyError> {
+    let mut roots = RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    if let Some(path) = &settings.ca_path {
+        for cert in load_certs(path)? {
+            roots.add(cert).map_err(ProxyError::Tls)?;
+        }
+    }
+
+    let builder =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(ProxyError::Tls)?
+            .with_root_certificates(roots);
+
+    let config = match (&settings.client_cert_path, &settings.client_key_path) {
+        (Some(cert), Some(key)) => builder
+            .with_client_auth_cert(load_certs(cert)?, load_key(key)?)
+            .map_err(ProxyError::Tls)?,
+        _ => builder.with_no_client_auth(),
+    };
+
+    Ok(Arc::new(config))
+}
+
+/// Build the rustls [`ServerConfig`] used to terminate TLS on the proxy listener.
+///
+/// When [`ListenerTls::client_ca_path`] is set, clients must present a certificate signed by one
+/// of those authorities.
+pub(crate) fn server_config(settings: &ListenerTls) -> Result<Arc<ServerConfig>, ProxyError> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(ProxyError::Tls)?;
+
+    let builder = match &settings.client_ca_path {
+        Some(path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in load_certs(path)? {
+                roots.add(cert).map_err(ProxyError::Tls)?;
+            }
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                .build()
+                .map_err(ProxyError::TlsClientVerifier)?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    let mut config = builder
+        .with_single_cert(
+            load_certs(&settings.cert_path)?,
+            load_key(&settings.key_path)?,
+        )
+        .map_err(ProxyError::Tls)?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(Arc::new(config))
+}
+
+/// Listener certificates that can be swapped while the proxy is running.
+///
+/// Connections keep the configuration they were accepted with, so a reload never interrupts
+/// requests already in flight.
+#[derive(Debug, Clone)]
+pub(crate) struct ServerCertificates {
+    settings: ListenerTls,
+    current: Arc<ArcSwap<ServerConfig>>,
+}
+
+impl ServerCertificates {
+    /// Load the certificate and key named by `settings`.
+    pub(crate) fn load(settings: ListenerTls) -> Result<Self, ProxyError> {
+        let current = Arc::new(ArcSwap::new(server_config(&settings)?));
+        Ok(Self { settings, current })
+    }
+
+    /// Re-read the certificate files; on error the previous configuration stays active.
+    pub(crate) fn reload(&self) -> Result<(), ProxyError> {
+        self.current.store(server_config(&self.settings)?);
+        Ok(())
+    }
+
+    /// Reload the certificates whenever the process receives `SIGHUP`.
+    #[cfg(unix)]
+    pub(crate) fn reload_on_sighup(&self) -> Result<(), ProxyError> {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let mut hangup = signal(SignalKind::hangup()).map_err(ProxyError::Signal)?;
+        let certificates = self.clone();
+        tokio::spawn(async move {
+            while hangup.recv().await.is_some() {
+                match certificates.reload() {
+                    Ok(()) => info!(
+                        cert = %certificates.settings.cert_path.display(),
+                        "reloaded listener TLS certificates"
+                    ),
+                    Err(error) => warn!(
+                        %error,
+                        "failed to reload listener TLS certificates; keeping the previous ones"
+                    ),
+                }
+            }
+        });
+        Ok(())
+    }
+
+    fn acceptor(&self) -> TlsAcceptor {
+        TlsAcceptor::from(self.current.load_full())
+    }
+}
+
+/// HTTPS listener for the proxy.
+///
+/// Handshakes run on their own tasks so a slow or silent client cannot stall other connections.
+pub(crate) struct TlsListener {
+    incoming: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+    local_addr: SocketAddr,
+}
+
+impl TlsListener {
+    /// Start accepting TLS connections on `tcp` with the given certificates.
+    pub(crate) fn new(
+        tcp: TcpListener,
+        certificates: ServerCertificates,
+    ) -> Result<Self, ProxyError> {
+        let local_addr = tcp.local_addr().map_err(ProxyError::Bind)?;
+        let (sender, incoming) = mpsc::channel(HANDSHAKE_BACKLOG);
+        tokio::spawn(accept_loop(tcp, certificates, sender));
+        Ok(Self {
+            incoming,
+            local_addr,
+        })
+    }
+}
+
+impl axum::serve::Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.incoming.recv().await {
+            Some(connection) => connection,
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+async fn accept_loop(
+    tcp: TcpListener,
+    certificates: ServerCertificates,
+    sender: mpsc::Sender<(TlsStream<TcpStream>, SocketAddr)>,
+) {
+    while !sender.is_closed() {
+        let (stream, address) = match tcp.accept().await {
+            Ok(connection) => connection,
+            Err(error) => {
+                warn!(%error, "failed to accept connection");
+                tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
+                continue;
+            }
+        };
+
+        let acceptor = certificates.acceptor();
+        let sender = sender.clone();
+        tokio::spawn(async move {
+            match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                Ok(Ok(stream)) => {
+                    let _ = sender.send((stream, address)).await;
+                }
+                Ok(Err(error)) => debug!(%address, %error, "TLS handshake failed"),
+                Err(_) => debug!(%address, "TLS handshake timed out"),
+            }
+        });
+    }
+}
+
+/// Read every certificate from a PEM file.
+pub(crate) fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, ProxyError> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(Iterator::collect::<Result<Vec<_>, _>>)
+        .map_err(|source| ProxyError::TlsPem {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+    if certs.is_empty() {
+        return Err(ProxyError::TlsPem {
+            path: path.to_path_buf(),
+            source: rustls::pki_types::pem::Error::NoItemsFound,
+        });
+    }
+    Ok(certs)
+}
+
+/// Read the first private key from a PEM file.
+pub(crate) fn load_key(path: &Path) -> Result<PrivateKeyDer<'static>, ProxyError> {
+    PrivateKeyDer::from_pem_file(path).map_err(|source| ProxyError::TlsPem {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::server::{AppState, router};
+    use axum::Router;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode, Uri};
+    use axum::response::Response;
+    use axum::routing::any;
+    use http_body_util::BodyExt;
+    use rstest::rstest;
+    use serde_json::{Value, json};
+    use std::collections::HashSet;
+    use std::path::PathBuf;
+    use tower::util::ServiceExt;
+    use veto_config::Config;
+
+    /// Path of a PEM fixture under `testdata/`.
+    pub(crate) fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata")
+            .join(name)
+    }
+
+    fn listener_settings(require_client_cert: bool) -> ListenerTls {
+        ListenerTls {
+            cert_path: fixture("server.pem"),
+            key_path: fixture("server.key"),
+            client_ca_path: require_client_cert.then(|| fixture("ca.pem")),
+        }
+    }
+
+    /// Spawn an HTTPS upstream signed by the test CA that answers every call with `"tls-ok"`.
+    async fn spawn_tls_upstream(require_client_cert: bool) -> Uri {
+        let certificates =
+            ServerCertificates::load(listener_settings(require_client_cert)).unwrap();
+        let tcp = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let listener = TlsListener::new(tcp, certificates).unwrap();
+        let address = axum::serve::Listener::local_addr(&listener).unwrap();
+        let app = Router::new().fallback(any(|| async {
+            Response::new(Body::from(
+                json!({"jsonrpc": "2.0", "id": 1, "result": "tls-ok"}).to_string(),
+            ))
+        }));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("https://localhost:{}", address.port())
+            .parse()
+            .unwrap()
+    }
+
+    async fn call_through_proxy(upstream: Uri, settings: UpstreamTls) -> (StatusCode, Value) {
+        let config = Config::new("127.0.0.1:0".parse().unwrap(), upstream, HashSet::new())
+            .with_upstream_tls(settings);
+        let app = router(AppState::try_from_config(config).unwrap());
+        let payload = json!({"jsonrpc": "2.0", "id": 1, "method": "eth_chainId"});
+        let request = Request::builder()
+            .method("POST")
+            .uri("/")
+            .header("content-type", "application/json")
+            .body(Body::from(payload.to_string()))
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        (
+            status,
+            serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+        )
+    }
+
+    #[tokio::test]
+    async fn forwards_to_https_upstream_with_custom_ca() {
+        let upstream = spawn_tls_upstream(false).await;
+        let settings = UpstreamTls {
+            ca_path: Some(fixture("ca.pem")),
+            ..UpstreamTls::default()
+        };
+        let (status, value) = call_through_proxy(upstream, settings).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(value["result"], "tls-ok");
+    }
+
+    #[tokio::test]
+    async fn untrusted_upstream_certificate_fails() {
+        let upstream = spawn_tls_upstream(false).await;
+        let (status, _) = call_through_proxy(upstream, UpstreamTls::default()).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[tokio::test]
+    async fn presents_client_certificate_for_mutual_tls() {
+        let upstream = spawn_tls_upstream(true).await;
+        let (status, _) = call_through_proxy(
+            upstream.clone(),
+            UpstreamTls {
+                ca_path: Some(fixture("ca.pem")),
+                ..UpstreamTls::default()
+            },
+        )
+        .await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+
+        let settings = UpstreamTls {
+            ca_path: Some(fixture("ca.pem")),
+            client_cert_path: Some(fixture("client.pem")),
+            client_key_path: Some(fixture("client.key")),
+        };
+        let (status, value) = call_through_proxy(upstream, settings).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(value["result"], "tls-ok");
+    }
+
+    #[rstest]
+    fn builds_config_with_custom_ca_and_client_cert() {
+        let settings = UpstreamTls {
+            ca_path: Some(fixture("ca.pem")),
+            client_cert_path: Some(fixture("client.pem")),
+            client_key_path: Some(fixture("client.key")),
+        };
+        let config = client_config(&settings).unwrap();
+        assert!(config.client_auth_cert_resolver.has_certs());
+    }
+
+    #[rstest]
+    fn missing_ca_bundle_is_reported() {
+        let settings = UpstreamTls {
+            ca_path: Some(fixture("missing.pem")),
+            ..UpstreamTls::default()
+        };
+        let error = client_config(&settings).unwrap_err();
+        assert!(matches!(error, ProxyError::TlsPem { path, .. } if path.ends_with("missing.pem")));
+    }
+
+    #[tokio::test]
+    async fn silent_client_does_not_block_handshakes() {
+        let upstream = spawn_tls_upstream(false).await;
+        let port = upstream.port_u16().unwrap();
+        let _silent = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+
+        let settings = UpstreamTls {
+            ca_path: Some(fixture("ca.pem")),
+            ..UpstreamTls::default()
+        };
+        let (status, value) = call_through_proxy(upstream, settings).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(value["result"], "tls-ok");
+    }
+
+    #[rstest]
+    fn reload_swaps_certificates_and_keeps_previous_on_error() {
+        let directory = tempfile::tempdir().unwrap();
+        let settings = ListenerTls {
+            cert_path: directory.path().join("cert.pem"),
+            key_path: directory.path().join("key.pem"),
+            client_ca_path: None,
+        };
+        std::fs::copy(fixture("server.pem"), &settings.cert_path).unwrap();
+        std::fs::copy(fixture("server.key"), &settings.key_path).unwrap();
+
+        let certificates = ServerCertificates::load(settings.clone()).unwrap();
+        let original = certificates.current.load_full();
+
+        std::fs::remove_file(&settings.key_path).unwrap();
+        assert!(matches!(
+            certificates.reload(),
+            Err(ProxyError::TlsPem { .. })
+        ));
+        assert!(Arc::ptr_eq(&original, &certificates.current.load_full()));
+
+        std::fs::copy(fixture("client.pem"), &settings.cert_path).unwrap();
+        std::fs::copy(fixture("client.key"), &settings.key_path).unwrap();
+        certificates.reload().unwrap();
+        assert!(!Arc::ptr_eq(&original, &certificates.current.load_full()));
+    }
+
+    #[rstest]
+    fn listener_config_negotiates_http1_and_checks_client_ca() {
+        let config = server_config(&listener_settings(true)).unwrap();
+        assert_eq!(config.alpn_protocols, vec![b"http/1.1".to_vec()]);
+
+        let settings = ListenerTls {
+            client_ca_path: Some(fixture("missing.pem")),
+            ..listener_settings(false)
+        };
+        assert!(matches!(
+            server_config(&settings),
+            Err(ProxyError::TlsPem { path, .. }) if path.ends_with("missing.pem")
+        ));
+    }
+}
@@ -0,0 +1,598 @@
+use serde_json::{Value, json};
+use std::fmt;
+use thiserror::Error;
+
+/// EIP-2718 envelope type of a signed transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TxType {
+    /// Pre-EIP-2718 RLP transaction (type `0x0`).
+    Legacy,
+    /// EIP-2930 access list transaction (type `0x1`).
+    AccessList,
+    /// EIP-1559 dynamic fee transaction (type `0x2`).
+    DynamicFee,
+    /// EIP-4844 blob transaction (type `0x3`).
+    Blob,
+    /// EIP-7702 set code transaction (type `0x4`).
+    SetCode,
+}
+
+impl TxType {
+    /// Numeric EIP-2718 type, `0` for legacy transactions.
+    pub const fn as_u8(self) -> u8 {
+        match self {
+            Self::Legacy => 0,
+            Self::AccessList => 1,
+            Self::DynamicFee => 2,
+            Self::Blob => 3,
+            Self::SetCode => 4,
+        }
+    }
+
+    /// Number of RLP fields in the signed transaction payload.
+    const fn field_count(self) -> usize {
+        match self {
+            Self::Legacy => 9,
+            Self::AccessList => 11,
+            Self::DynamicFee => 12,
+            Self::SetCode => 13,
+            Self::Blob => 14,
+        }
+    }
+}
+
+/// Errors produced while decoding an `eth_sendRawTransaction` payload.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum TxDecodeError {
+    /// The payload is missing or not a `0x`-prefixed hex string.
+    #[error("raw transaction must be a 0x-prefixed hex string")]
+    InvalidHex,
+    /// The payload decoded to zero bytes.
+    #[error("raw transaction is empty")]
+    Empty,
+    /// The EIP-2718 type byte is not supported.
+    #[error("unsupported transaction type {0:#x}")]
+    UnsupportedType(u8),
+    /// The RLP encoding is malformed.
+    #[error("malformed RLP: {0}")]
+    Rlp(&'static str),
+    /// The transaction list holds the wrong number of fields for its type.
+    #[error("expected {expected} transaction fields, found {found}")]
+    FieldCount {
+        /// Number of fields required by the transaction type.
+        expected: usize,
+        /// Number of fields found in the payload.
+        found: usize,
+    },
+    /// A field has the wrong shape (e.g. a list where a string was expected).
+    #[error("invalid `{0}` field")]
+    InvalidField(&'static str),
+    /// A numeric field does not fit the supported width.
+    #[error("`{0}` field is out of range")]
+    Overflow(&'static str),
+}
+
+/// Policy-relevant fields of a signed transaction.
+///
+/// Fees are normalized so rules can match every type the same way: legacy and EIP-2930
+/// transactions report their gas price as both the max fee and the max priority fee.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedTransaction {
+    /// Envelope type.
+    pub tx_type: TxType,
+    /// Chain id, or `None` for unprotected (pre-EIP-155) legacy transactions.
+    pub chain_id: Option<u64>,
+    /// Sender nonce.
+    pub nonce: u64,
+    /// Recipient, or `None` for contract creations.
+    pub to: Option<[u8; 20]>,
+    /// Transferred wei.
+    pub value: u128,
+    /// First four bytes of the calldata, when present.
+    pub selector: Option<[u8; 4]>,
+    /// Gas limit.
+    pub gas_limit: u64,
+    /// Max fee per gas, or the gas price for legacy and EIP-2930 transactions.
+    pub max_fee_per_gas: u128,
+    /// Max priority fee per gas, or the gas price for legacy and EIP-2930 transactions.
+    pub max_priority_fee_per_gas: u128,
+}
+
+impl DecodedTransaction {
+    /// Summary of the decoded fields as JSON-RPC quantities, attached to rejection errors.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "type": format!("{:#x}", self.tx_type.as_u8()),
+            "chainId": self.chain_id.map(|chain_id| format!("{chain_id:#x}")),
+            "nonce": format!("{:#x}", self.nonce),
+            "to": self.to.map(|to| encode_hex(&to)),
+            "value": format!("{:#x}", self.value),
+            "selector": self.selector.map(|selector| encode_hex(&selector)),
+            "gas": format!("{:#x}", self.gas_limit),
+            "maxFeePerGas": format!("{:#x}", self.max_fee_per_gas),
+            "maxPriorityFeePerGas": format!("{:#x}", self.max_priority_fee_per_gas),
+        })
+    }
+}
+
+impl fmt::Display for DecodedTransaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "type {:#x} transaction ", self.tx_type.as_u8())?;
+        match &self.to {
+            Some(to) => write!(f, "to {}", encode_hex(to))?,
+            None => f.write_str("creating a contract")?,
+        }
+        write!(f, " with value {:#x}", self.value)?;
+        if let Some(selector) = &self.selector {
+            write!(f, " calling {}", encode_hex(selector))?;
+        }
+        Ok(())
+    }
+}
+
+/// Decode the hex payload of an `eth_sendRawTransaction` call.
+///
+/// EIP-4844 transactions are accepted both bare and in their network form with blobs.
+pub fn decode_raw_transaction(raw: &str) -> Result<DecodedTransaction, TxDecodeError> {
+    let hex = raw
+        .strip_prefix("0x")
+        .or_else(|| raw.strip_prefix("0X"))
+        .ok_or(TxDecodeError::InvalidHex)?;
+    let bytes = decode_hex(hex).ok_or(TxDecodeError::InvalidHex)?;
+    decode_transaction(&bytes)
+}
+
+fn decode_transaction(bytes: &[u8]) -> Result<DecodedTransaction, TxDecodeError> {
+    let (&first, body) = bytes.split_first().ok_or(TxDecodeError::Empty)?;
+    if first >= 0xc0 {
+        return decode_fields(TxType::Legacy, bytes);
+    }
+
+    let tx_type = match first {
+        0x01 => TxType::AccessList,
+        0x02 => TxType::DynamicFee,
+        0x03 => TxType::Blob,
+        0x04 => TxType::SetCode,
+        other => return Err(TxDecodeError::UnsupportedType(other)),
+    };
+    decode_fields(tx_type, body)
+}
+
+fn decode_fields(tx_type: TxType, payload: &[u8]) -> Result<DecodedTransaction, TxDecodeError> {
+    let mut fields = decode_list(payload)?;
+
+    // Blob transactions are broadcast as `[tx_payload, blobs, commitments, proofs]`.
+    if tx_type == TxType::Blob
+        && fields.len() == 4
+        && let Item::List(inner) = fields[0]
+    {
+        fields = list_items(inner)?;
+    }
+
+    let expected = tx_type.field_count();
+    if fields.len() != expected {
+        return Err(TxDecodeError::FieldCount {
+            expected,
+            found: fields.len(),
+        });
+    }
+
+    // `rest` indexes the gas limit; `to`, `value` and `data` follow it in every type.
+    let (chain_id, nonce, max_priority_fee_per_gas, max_fee_per_gas, rest) = match tx_type {
+        TxType::Legacy => {
+            let gas_price = uint(fields[1], "gasPrice")?;
+            let chain_id = legacy_chain_id(uint(fields[6], "v")?)?;
+            (chain_id, fields[0], gas_price, gas_price, 2)
+        }
+        TxType::AccessList => {
+            let gas_price = uint(fields[2], "gasPrice")?;
+            let chain_id = Some(uint_u64(fields[0], "chainId")?);
+            (chain_id, fields[1], gas_price, gas_price, 3)
+        }
+        TxType::DynamicFee | TxType::Blob | TxType::SetCode => (
+            Some(uint_u64(fields[0], "chainId")?),
+            fields[1],
+            uint(fields[2], "maxPriorityFeePerGas")?,
+            uint(fields[3], "maxFeePerGas")?,
+            4,
+        ),
+    };
+
+    let to = address(fields[rest + 1])?;
+    if to.is_none() && matches!(tx_type, TxType::Blob | TxType::SetCode) {
+        return Err(TxDecodeError::InvalidField("to"));
+    }
+    let input = string(fields[rest + 3], "data")?;
+
+    Ok(DecodedTransaction {
+        tx_type,
+        chain_id,
+        nonce: uint_u64(nonce, "nonce")?,
+        to,
+        value: uint(fields[rest + 2], "value")?,
+        selector: input.get(..4).map(|selector| {
+            let mut bytes = [0; 4];
+            bytes.copy_from_slice(selector);
+            bytes
+        }),
+        gas_limit: uint_u64(fields[rest], "gas")?,
+        max_fee_per_gas,
+        max_priority_fee_per_gas,
+    })
+}
+
+/// Recover the EIP-155 chain id from a legacy `v`; `None` for unprotected signatures.
+fn legacy_chain_id(v: u128) -> Result<Option<u64>, TxDecodeError> {
+    match v {
+        27 | 28 => Ok(None),
+        35.. => u64::try_from((v - 35) / 2)
+            .map(Some)
+            .map_err(|_| TxDecodeError::Overflow("v")),
+        _ => Err(TxDecodeError::InvalidField("v")),
+    }
+}
+
+/// A decoded RLP item borrowing its payload from the input.
+#[derive(Debug, Clone, Copy)]
+enum Item<'a> {
+    Bytes(&'a [u8]),
+    List(&'a [u8]),
+}
+
+/// Decode a single top-level RLP list and return its items.
+fn decode_list(input: &[u8]) -> Result<Vec<Item<'_>>, TxDecodeError> {
+    let mut rest = input;
+    let Item::List(payload) = next_item(&mut rest)? else {
+        return Err(TxDecodeError::Rlp("expected a list"));
+    };
+    if !rest.is_empty() {
+        return Err(TxDecodeError::Rlp("trailing bytes after transaction"));
+    }
+    list_items(payload)
+}
+
+fn list_items(mut payload: &[u8]) -> Result<Vec<Item<'_>>, TxDecodeError> {
+    let mut items = Vec::new();
+    while !payload.is_empty() {
+        items.push(next_item(&mut payload)?);
+    }
+    Ok(items)
+}
+
+/// Decode the next RLP item and advance `input` past it.
+fn next_item<'a>(input: &mut &'a [u8]) -> Result<Item<'a>, TxDecodeError> {
+    let buf = *input;
+    let prefix = *buf
+        .first()
+        .ok_or(TxDecodeError::Rlp("unexpected end of input"))?;
+
+    let (list, offset, len) = match prefix {
+        0x00..=0x7f => (false, 0, 1),
+        0x80..=0xb7 => (false, 1, usize::from(prefix - 0x80)),
+        0xb8..=0xbf => {
+            let width = usize::from(prefix - 0xb7);
+            (false, 1 + width, long_length(&buf[1..], width)?)
+        }
+        0xc0..=0xf7 => (true, 1, usize::from(prefix - 0xc0)),
+        0xf8..=0xff => {
+            let width = usize::from(prefix - 0xf7);
+            (true, 1 + width, long_length(&buf[1..], width)?)
+        }
+    };
+
+    let end = offset
+        .checked_add(len)
+        .filter(|end| *end <= buf.len())
+        .ok_or(TxDecodeError::Rlp("item exceeds input length"))?;
+    let payload = &buf[offset..end];
+    *input = &buf[end..];
+
+    Ok(if list {
+        Item::List(payload)
+    } else {
+        Item::Bytes(payload)
+    })
+}
+
+fn long_length(buf: &[u8], width: usize) -> Result<usize, TxDecodeError> {
+    let bytes = buf
+        .get(..width)
+        .ok_or(TxDecodeError::Rlp("truncated length prefix"))?;
+    if bytes[0] == 0 || width > size_of::<usize>() {
+        return Err(TxDecodeError::Rlp("non-canonical length prefix"));
+    }
+    Ok(bytes
+        .iter()
+        .fold(0, |length, byte| (length << 8) | usize::from(*byte)))
+}
+
+const fn string<'a>(item: Item<'a>, field: &'static str) -> Result<&'a [u8], TxDecodeError> {
+    match item {
+        Item::Bytes(bytes) => Ok(bytes),
+        Item::List(_) => Err(TxDecodeError::InvalidField(field)),
+    }
+}
+
+fn uint(item: Item<'_>, field: &'static str) -> Result<u128, TxDecodeError> {
+    let bytes = string(item, field)?;
+    if bytes.first() == Some(&0) {
+        return Err(TxDecodeError::InvalidField(field));
+    }
+    if bytes.len() > size_of::<u128>() {
+        return Err(TxDecodeError::Overflow(field));
+    }
+    Ok(bytes
+        .iter()
+        .fold(0, |value, byte| (value << 8) | u128::from(*byte)))
+}
+
+fn uint_u64(item: Item<'_>, field: &'static str) -> Result<u64, TxDecodeError> {
+    u64::try_from(uint(item, field)?).map_err(|_| TxDecodeError::Overflow(field))
+}
+
+fn address(item: Item<'_>) -> Result<Option<[u8; 20]>, TxDecodeError> {
+    match string(item, "to")? {
+        [] => Ok(None),
+        bytes => bytes
+            .try_into()
+            .map(Some)
+            .map_err(|_| TxDecodeError::InvalidField("to")),
+    }
+}
+
+/// Decode an even-length hex string without a `0x` prefix.
+pub(crate) fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    hex.as_bytes()
+        .chunks_exact(2)
+        .map(|pair| {
+            let high = char::from(pair[0]).to_digit(16)?;
+            let low = char::from(pair[1]).to_digit(16)?;
+            u8::try_from(high << 4 | low).ok()
+        })
+        .collect()
+}
+
+/// Encode bytes as `0x`-prefixed lowercase hex.
+pub(crate) fn encode_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(2 + bytes.len() * 2);
+    hex.push_str("0x");
+    for byte in bytes {
+        hex.push_str(&format!("{byte:02x}"));
+    }
+    hex
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    /// Signed example transaction from EIP-155 (chain id 1).
+    pub(crate) const EIP155_EXAMPLE: &str = "0xf86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83";
+
+    fn rlp_string(bytes: &[u8]) -> Vec<u8> {
+        if let [byte] = bytes
+            && *byte < 0x80
+        {
+            return vec![*byte];
+        }
+        rlp_with_header(0x80, bytes)
+    }
+
+    fn rlp_list(items: &[Vec<u8>]) -> Vec<u8> {
+        rlp_with_header(0xc0, &items.concat())
+    }
+
+    fn rlp_with_header(offset: u8, payload: &[u8]) -> Vec<u8> {
+        let mut out = if payload.len() < 56 {
+            vec![offset + payload.len() as u8]
+        } else {
+            let length = payload.len().to_be_bytes();
+            let length = &length[length.iter().position(|byte| *byte != 0).unwrap()..];
+            let mut header = vec![offset + 55 + length.len() as u8];
+            header.extend_from_slice(length);
+            header
+        };
+        out.extend_from_slice(payload);
+        out
+    }
+
+    fn rlp_uint(value: u128) -> Vec<u8> {
+        let bytes = value.to_be_bytes();
+        let start = bytes
+            .iter()
+            .position(|byte| *byte != 0)
+            .unwrap_or(bytes.len());
+        rlp_string(&bytes[start..])
+    }
+
+    /// Encode a signed typed transaction with dummy signature values.
+    pub(crate) fn typed_tx(tx_type: u8, fields: &[Vec<u8>]) -> String {
+        let mut items = fields.to_vec();
+        items.extend([rlp_uint(1), rlp_uint(2), rlp_uint(3)]);
+        let mut bytes = vec![tx_type];
+        bytes.extend(rlp_list(&items));
+        encode_hex(&bytes)
+    }
+
+    /// EIP-1559 transfer calling `approve` on `to` with the given chain id and value.
+    pub(crate) fn dynamic_fee_tx(chain_id: u64, to: [u8; 20], value: u128) -> String {
+        typed_tx(
+            2,
+            &[
+                rlp_uint(chain_id.into()),
+                rlp_uint(7),
+                rlp_uint(1_000_000_000),
+                rlp_uint(30_000_000_000),
+                rlp_uint(60_000),
+                rlp_string(&to),
+                rlp_uint(value),
+                rlp_string(&[0x09, 0x5e, 0xa7, 0xb3, 0xff]),
+                rlp_list(&[]),
+            ],
+        )
+    }
+
+    #[rstest]
+    fn decodes_eip155_legacy_transaction() {
+        let tx = decode_raw_transaction(EIP155_EXAMPLE).unwrap();
+        assert_eq!(tx.tx_type, TxType::Legacy);
+        assert_eq!(tx.chain_id, Some(1));
+        assert_eq!(tx.nonce, 9);
+        assert_eq!(tx.to, Some([0x35; 20]));
+        assert_eq!(tx.value, 1_000_000_000_000_000_000);
+        assert_eq!(tx.gas_limit, 21_000);
+        assert_eq!(tx.max_fee_per_gas, 20_000_000_000);
+        assert_eq!(tx.max_priority_fee_per_gas, 20_000_000_000);
+        assert_eq!(tx.selector, None);
+    }
+
+    /// Pre-EIP-155 contract creation signed with `v = 27`.
+    pub(crate) fn unprotected_legacy_tx() -> String {
+        encode_hex(&rlp_list(&[
+            rlp_uint(0),
+            rlp_uint(1),
+            rlp_uint(21_000),
+            rlp_string(&[]),
+            rlp_uint(0),
+            rlp_string(&[0x60, 0x80, 0x60, 0x40]),
+            rlp_uint(27),
+            rlp_uint(1),
+            rlp_uint(1),
+        ]))
+    }
+
+    #[rstest]
+    fn decodes_unprotected_legacy_transaction() {
+        let tx = decode_raw_transaction(&unprotected_legacy_tx()).unwrap();
+        assert_eq!(tx.chain_id, None);
+        assert_eq!(tx.to, None);
+        assert_eq!(tx.selector, Some([0x60, 0x80, 0x60, 0x40]));
+    }
+
+    #[rstest]
+    fn decodes_dynamic_fee_transaction() {
+        let tx = decode_raw_transaction(&dynamic_fee_tx(10, [0xaa; 20], 5)).unwrap();
+        assert_eq!(tx.tx_type, TxType::DynamicFee);
+        assert_eq!(tx.chain_id, Some(10));
+        assert_eq!(tx.nonce, 7);
+        assert_eq!(tx.to, Some([0xaa; 20]));
+        assert_eq!(tx.value, 5);
+        assert_eq!(tx.selector, Some([0x09, 0x5e, 0xa7, 0xb3]));
+        assert_eq!(tx.gas_limit, 60_000);
+        assert_eq!(tx.max_priority_fee_per_gas, 1_000_000_000);
+        assert_eq!(tx.max_fee_per_gas, 30_000_000_000);
+    }
+
+    #[rstest]
+    fn decodes_access_list_transaction() {
+        let raw = typed_tx(
+            1,
+            &[
+                rlp_uint(1),
+                rlp_uint(0),
+                rlp_uint(5),
+                rlp_uint(21_000),
+                rlp_string(&[0xbb; 20]),
+                rlp_uint(0),
+                rlp_string(&[]),
+                rlp_list(&[rlp_list(&[rlp_string(&[0xbb; 20]), rlp_list(&[])])]),
+            ],
+        );
+        let tx = decode_raw_transaction(&raw).unwrap();
+        assert_eq!(tx.tx_type, TxType::AccessList);
+        assert_eq!(tx.max_fee_per_gas, 5);
+        assert_eq!(tx.max_priority_fee_per_gas, 5);
+    }
+
+    fn blob_fields() -> Vec<Vec<u8>> {
+        vec![
+            rlp_uint(1),
+            rlp_uint(0),
+            rlp_uint(1),
+            rlp_uint(2),
+            rlp_uint(21_000),
+            rlp_string(&[0xcc; 20]),
+            rlp_uint(0),
+            rlp_string(&[]),
+            rlp_list(&[]),
+            rlp_uint(3),
+            rlp_list(&[rlp_string(&[0x01; 32])]),
+        ]
+    }
+
+    #[rstest]
+    fn decodes_blob_transaction_in_both_forms() {
+        let bare = decode_raw_transaction(&typed_tx(3, &blob_fields())).unwrap();
+        assert_eq!(bare.tx_type, TxType::Blob);
+        assert_eq!(bare.to, Some([0xcc; 20]));
+
+        let mut body = blob_fields();
+        body.extend([rlp_uint(1), rlp_uint(2), rlp_uint(3)]);
+        let mut network = vec![3];
+        network.extend(rlp_list(&[
+            rlp_list(&body),
+            rlp_list(&[rlp_string(&[0; 64])]),
+            rlp_list(&[rlp_string(&[0; 48])]),
+            rlp_list(&[rlp_string(&[0; 48])]),
+        ]));
+        assert_eq!(decode_raw_transaction(&encode_hex(&network)).unwrap(), bare);
+    }
+
+    #[rstest]
+    fn decodes_set_code_transaction() {
+        let authorization = rlp_list(&[
+            rlp_uint(1),
+            rlp_string(&[0xdd; 20]),
+            rlp_uint(0),
+            rlp_uint(0),
+            rlp_uint(1),
+            rlp_uint(1),
+        ]);
+        let raw = typed_tx(
+            4,
+            &[
+                rlp_uint(1),
+                rlp_uint(0),
+                rlp_uint(1),
+                rlp_uint(2),
+                rlp_uint(50_000),
+                rlp_string(&[0xee; 20]),
+                rlp_uint(0),
+                rlp_string(&[]),
+                rlp_list(&[]),
+                rlp_list(&[authorization]),
+            ],
+        );
+        let tx = decode_raw_transaction(&raw).unwrap();
+        assert_eq!(tx.tx_type, TxType::SetCode);
+        assert_eq!(tx.gas_limit, 50_000);
+    }
+
+    #[rstest]
+    #[case::no_prefix("f86c", TxDecodeError::InvalidHex)]
+    #[case::odd_length("0xf86", TxDecodeError::InvalidHex)]
+    #[case::empty("0x", TxDecodeError::Empty)]
+    #[case::unknown_type("0x05c0", TxDecodeError::UnsupportedType(5))]
+    #[case::truncated("0xf86c09", TxDecodeError::Rlp("item exceeds input length"))]
+    #[case::field_count("0x02c101", TxDecodeError::FieldCount { expected: 12, found: 1 })]
+    fn rejects_malformed_payloads(#[case] raw: &str, #[case] expected: TxDecodeError) {
+        assert_eq!(decode_raw_transaction(raw), Err(expected));
+    }
+
+    #[rstest]
+    fn summary_uses_quantities() {
+        let tx = decode_raw_transaction(EIP155_EXAMPLE).unwrap();
+        let summary = tx.to_json();
+        assert_eq!(summary["type"], "0x0");
+        assert_eq!(summary["chainId"], "0x1");
+        assert_eq!(summary["value"], "0xde0b6b3a7640000");
+        assert_eq!(
+            tx.to_string(),
+            "type 0x0 transaction to 0x3535353535353535353535353535353535353535 with value 0xde0b6b3a7640000"
+        );
+    }
+}
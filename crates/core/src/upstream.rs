@@ -0,0 +1,508 @@
+use crate::balancer::{Balancer, balancer};
+use crate::circuit::{Circuit, CircuitPermit, CircuitState};
+use crate::metrics::Metrics;
+use axum::http::Uri;
+use serde::Serialize;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+use veto_config::{Backoff, Config, UpstreamTarget};
+
+/// Weight of the newest sample in the latency moving average.
+const EWMA_ALPHA: f64 = 0.3;
+
+/// Set of upstream endpoints with passive health tracking and a pluggable [`Balancer`].
+///
+/// The balancer picks among the upstreams in rotation; a connection error or `5xx` response
+/// takes an upstream out of rotation until its [`Backoff`] delay has elapsed. When a
+/// circuit breaker is configured, upstreams whose [`Circuit`] is open are never tried.
+#[derive(Debug)]
+pub(crate) struct UpstreamSet {
+    upstreams: Vec<Upstream>,
+    balancer: Box<dyn Balancer>,
+}
+
+impl UpstreamSet {
+    /// Build the set and balancer from the upstreams listed in the [`Config`], exporting
+    /// circuit breaker state changes to `metrics`.
+    pub(crate) fn from_config(config: &Config, metrics: Option<&Arc<Metrics>>) -> Self {
+        let backoff = config.upstream_backoff();
+        let upstreams: Vec<_> = config
+            .upstreams()
+            .iter()
+            .enumerate()
+            .map(|(index, target)| {
+                let upstream = Upstream::new(index, target, backoff);
+                match config.circuit_breaker() {
+                    Some(settings) => upstream.with_circuit(Circuit::new(
+                        target.url.clone(),
+                        settings,
+                        metrics.cloned(),
+                    )),
+                    None => upstream,
+                }
+            })
+            .collect();
+        let balancer = balancer(config.balance(), &upstreams);
+        Self {
+            upstreams,
+            balancer,
+        }
+    }
+
+    /// Upstreams to try for the next request, preferred first.
+    ///
+    /// The balancer orders the upstreams in rotation, including those whose backoff has
+    /// elapsed. When no upstream is in rotation they are all returned, those passing their
+    /// health check and with the soonest retry first, so requests still have somewhere to go.
+    /// Upstreams whose circuit is open are left out, so the list is empty when every circuit is.
+    pub(crate) fn candidates(&self) -> Vec<&Upstream> {
+        let now = Instant::now();
+        let closed: Vec<_> = self
+            .upstreams
+            .iter()
+            .filter(|upstream| upstream.circuit_allows(now))
+            .collect();
+        let mut available: Vec<_> = closed
+            .iter()
+            .copied()
+            .filter(|upstream| upstream.is_available(now))
+            .collect();
+        if !available.is_empty() {
+            self.balancer.order(&mut available);
+            return available;
+        }
+
+        let mut all = closed;
+        all.sort_by_key(|upstream| (!upstream.passes_health_check(), upstream.retry_at()));
+        all
+    }
+
+    /// Every upstream, in configured order.
+    pub(crate) fn upstreams(&self) -> &[Upstream] {
+        &self.upstreams
+    }
+
+    /// Snapshot the counters of every upstream, in configured order.
+    pub(crate) fn stats(&self) -> Vec<UpstreamStats> {
+        self.upstreams.iter().map(Upstream::stats).collect()
+    }
+}
+
+/// A single upstream endpoint with its health and request counters.
+#[derive(Debug)]
+pub(crate) struct Upstream {
+    index: usize,
+    uri: Uri,
+    weight: u32,
+    backoff: Backoff,
+    health: Mutex<Health>,
+    check: Mutex<Option<HealthCheckReport>>,
+    in_flight: AtomicUsize,
+    requests: AtomicU64,
+    failures: AtomicU64,
+    /// Moving average latency in microseconds; zero until the first response.
+    ewma_latency_us: AtomicU64,
+    circuit: Option<Circuit>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Health {
+    Healthy,
+    Unhealthy { failures: u32, retry_at: Instant },
+}
+
+impl Upstream {
+    pub(crate) fn new(index: usize, target: &UpstreamTarget, backoff: Backoff) -> Self {
+        Self {
+            index,
+            uri: target.url.clone(),
+            weight: target.weight,
+            backoff,
+            health: Mutex::new(Health::Healthy),
+            check: Mutex::new(None),
+            in_flight: AtomicUsize::new(0),
+            requests: AtomicU64::new(0),
+            failures: AtomicU64::new(0),
+            ewma_latency_us: AtomicU64::new(0),
+            circuit: None,
+        }
+    }
+
+    /// Guard the upstream with a circuit breaker.
+    fn with_circuit(mut self, circuit: Circuit) -> Self {
+        self.circuit = Some(circuit);
+        self
+    }
+
+    /// Position of the upstream in the configuration.
+    pub(crate) const fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Endpoint requests are forwarded to.
+    pub(crate) const fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    /// Relative weight used by the weighted balancer.
+    pub(crate) const fn weight(&self) -> u32 {
+        self.weight
+    }
+
+    /// Requests currently awaiting a response.
+    pub(crate) fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+
+    /// Moving average response latency, or `None` before the first response.
+    pub(crate) fn ewma_latency(&self) -> Option<Duration> {
+        match self.ewma_latency_us.load(Ordering::Relaxed) {
+            0 => None,
+            micros => Some(Duration::from_micros(micros)),
+        }
+    }
+
+    /// Count a request as in flight until the returned guard is dropped.
+    pub(crate) fn begin(&self) -> InFlight<'_> {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        InFlight(self)
+    }
+
+    /// Ask the circuit breaker to let a call through, returning `None` while it is open.
+    ///
+    /// The outcome of the call must be reported through the returned permit.
+    pub(crate) fn admit(&self) -> Option<CircuitPermit<'_>> {
+        self.circuit.as_ref().map_or_else(
+            || Some(CircuitPermit::unguarded()),
+            |circuit| circuit.admit(Instant::now()),
+        )
+    }
+
+    /// Fold a response latency into the moving average.
+    pub(crate) fn record_latency(&self, latency: Duration) {
+        let sample = u64::try_from(latency.as_micros())
+            .unwrap_or(u64::MAX)
+            .max(1);
+        let _ =
+            self.ewma_latency_us
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |current| {
+                    if current == 0 {
+                        return Some(sample);
+                    }
+                    let average =
+                        (sample as f64).mul_add(EWMA_ALPHA, current as f64 * (1.0 - EWMA_ALPHA));
+                    Some((average as u64).max(1))
+                });
+    }
+
+    /// Put the upstream back in rotation after a successful request.
+    pub(crate) fn record_success(&self) {
+        let mut health = self.health();
+        if let Health::Unhealthy { failures, .. } = *health {
+            info!(upstream = %self.uri, failures, "upstream recovered; back in rotation");
+        }
+        *health = Health::Healthy;
+    }
+
+    /// Take the upstream out of rotation, doubling its backoff on consecutive failures.
+    pub(crate) fn record_failure(&self, reason: impl fmt::Display) {
+        self.failures.fetch_add(1, Ordering::Relaxed);
+        let mut health = self.health();
+        let failures = match *health {
+            Health::Healthy => 1,
+            Health::Unhealthy { failures, .. } => failures.saturating_add(1),
+        };
+        let delay = self.backoff.delay(failures);
+        let was_healthy = matches!(*health, Health::Healthy);
+        *health = Health::Unhealthy {
+            failures,
+            retry_at: Instant::now() + delay,
+        };
+        drop(health);
+
+        if was_healthy {
+            warn!(upstream = %self.uri, %reason, retry_in = ?delay, "upstream marked unhealthy");
+        } else {
+            warn!(
+                upstream = %self.uri,
+                %reason,
+                failures,
+                retry_in = ?delay,
+                "upstream still unhealthy"
+            );
+        }
+    }
+
+    /// Store the outcome of an active health check, logging when it changes the rotation.
+    pub(crate) fn record_check(&self, report: HealthCheckReport) {
+        let mut check = self
+            .check
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let was_passing = check.as_ref().is_none_or(|previous| previous.passing);
+        match (was_passing, report.passing) {
+            (true, false) => warn!(
+                upstream = %self.uri,
+                reason = report.reason(),
+                block_number = ?report.block_number,
+                "upstream failed health check; out of rotation"
+            ),
+            (false, true) => info!(
+                upstream = %self.uri,
+                block_number = ?report.block_number,
+                "upstream passed health check; back in rotation"
+            ),
+            _ => debug!(
+                upstream = %self.uri,
+                passing = report.passing,
+                block_number = ?report.block_number,
+                "upstream health check"
+            ),
+        }
+        *check = Some(report);
+    }
+
+    fn circuit_allows(&self, now: Instant) -> bool {
+        self.circuit
+            .as_ref()
+            .is_none_or(|circuit| circuit.allows(now))
+    }
+
+    fn passes_health_check(&self) -> bool {
+        self.check
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .as_ref()
+            .is_none_or(|report| report.passing)
+    }
+
+    fn stats(&self) -> UpstreamStats {
+        let (healthy, consecutive_failures, retry_in_ms) = match *self.health() {
+            Health::Healthy => (true, 0, None),
+            Health::Unhealthy { failures, retry_at } => (
+                false,
+                failures,
+                Some(duration_millis(
+                    retry_at.saturating_duration_since(Instant::now()),
+                )),
+            ),
+        };
+        UpstreamStats {
+            url: self.uri.to_string(),
+            weight: self.weight,
+            in_rotation: self.is_available(Instant::now()),
+            healthy,
+            consecutive_failures,
+            retry_in_ms,
+            in_flight: self.in_flight(),
+            requests: self.requests.load(Ordering::Relaxed),
+            failures: self.failures.load(Ordering::Relaxed),
+            ewma_latency_ms: self
+                .ewma_latency()
+                .map(|latency| latency.as_secs_f64() * 1_000.0),
+            health_check: self
+                .check
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .clone(),
+            circuit: self
+                .circuit
+                .as_ref()
+                .map(|circuit| circuit.state(Instant::now())),
+        }
+    }
+
+    fn is_available(&self, now: Instant) -> bool {
+        let retry_due = match *self.health() {
+            Health::Healthy => true,
+            Health::Unhealthy { retry_at, .. } => retry_at <= now,
+        };
+        retry_due && self.passes_health_check()
+    }
+
+    fn retry_at(&self) -> Option<Instant> {
+        match *self.health() {
+            Health::Healthy => None,
+            Health::Unhealthy { retry_at, .. } => Some(retry_at),
+        }
+    }
+
+    fn health(&self) -> std::sync::MutexGuard<'_, Health> {
+        self.health
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn duration_millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+/// Keeps a request counted as in flight on its [`Upstream`] until dropped.
+#[derive(Debug)]
+pub(crate) struct InFlight<'a>(&'a Upstream);
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Point-in-time counters for one upstream, for debugging and status reporting.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UpstreamStats {
+    /// Upstream endpoint.
+    pub url: String,
+    /// Configured balancing weight.
+    pub weight: u32,
+    /// Whether the balancer may currently pick the upstream.
+    pub in_rotation: bool,
+    /// Whether the last forwarded request succeeded.
+    pub healthy: bool,
+    /// Failures since the upstream last succeeded.
+    pub consecutive_failures: u32,
+    /// Milliseconds until an unhealthy upstream is retried.
+    pub retry_in_ms: Option<u64>,
+    /// Requests currently awaiting a response.
+    pub in_flight: usize,
+    /// Requests sent since start-up.
+    pub requests: u64,
+    /// Failed requests since start-up.
+    pub failures: u64,
+    /// Moving average response latency in milliseconds.
+    pub ewma_latency_ms: Option<f64>,
+    /// Outcome of the latest active health check, when checks are enabled.
+    pub health_check: Option<HealthCheckReport>,
+    /// Circuit breaker state, when circuit breaking is enabled.
+    pub circuit: Option<CircuitState>,
+}
+
+/// Outcome of one active health check against an upstream.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HealthCheckReport {
+    /// Whether the upstream may stay in rotation.
+    pub passing: bool,
+    /// Latest block reported by `eth_blockNumber`.
+    pub block_number: Option<u64>,
+    /// Blocks behind the highest block seen across the upstreams.
+    pub block_lag: Option<u64>,
+    /// Whether `eth_syncing` reported an ongoing sync.
+    pub syncing: bool,
+    /// Why the check failed, if the upstream could not be queried.
+    pub error: Option<String>,
+}
+
+impl HealthCheckReport {
+    /// Short description of why the check failed (or `"ok"`).
+    pub fn reason(&self) -> String {
+        match (&self.error, self.block_lag) {
+            (Some(error), _) => error.clone(),
+            (None, _) if self.syncing => "syncing".into(),
+            (None, Some(lag)) if !self.passing => format!("{lag} blocks behind"),
+            _ => "ok".into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use std::collections::HashSet;
+
+    fn set(upstreams: &[&str], backoff: Duration) -> UpstreamSet {
+        let upstreams: Vec<Uri> = upstreams.iter().map(|uri| uri.parse().unwrap()).collect();
+        let config = Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            upstreams[0].clone(),
+            HashSet::new(),
+        )
+        .with_upstreams(upstreams)
+        .with_upstream_backoff(Backoff::new(backoff, backoff * 4).unwrap());
+        UpstreamSet::from_config(&config, None)
+    }
+
+    fn ports(candidates: &[&Upstream]) -> Vec<u16> {
+        candidates
+            .iter()
+            .map(|upstream| upstream.uri().port_u16().unwrap())
+            .collect()
+    }
+
+    fn retry_in(upstream: &Upstream) -> Option<Duration> {
+        upstream
+            .retry_at()
+            .map(|retry_at| retry_at.saturating_duration_since(Instant::now()))
+    }
+
+    #[rstest]
+    fn unhealthy_upstreams_leave_rotation_until_backoff_elapses() {
+        let upstreams = set(
+            &["http://127.0.0.1:1", "http://127.0.0.1:2"],
+            Duration::from_millis(20),
+        );
+        assert_eq!(ports(&upstreams.candidates()), [1, 2]);
+
+        upstreams.upstreams[0].record_failure("connection refused");
+        assert!(retry_in(&upstreams.upstreams[0]).is_some());
+        assert_eq!(ports(&upstreams.candidates()), [2]);
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(ports(&upstreams.candidates()), [1, 2]);
+
+        upstreams.upstreams[0].record_success();
+        assert_eq!(retry_in(&upstreams.upstreams[0]), None);
+    }
+
+    #[rstest]
+    fn consecutive_failures_double_the_backoff() {
+        let upstreams = set(&["http://127.0.0.1:1"], Duration::from_secs(10));
+        let upstream = &upstreams.upstreams[0];
+        upstream.record_failure("HTTP 502");
+        assert!(retry_in(upstream).unwrap() <= Duration::from_secs(10));
+        upstream.record_failure("HTTP 502");
+        assert!(retry_in(upstream).unwrap() > Duration::from_secs(10));
+    }
+
+    #[rstest]
+    fn all_unhealthy_upstreams_are_tried_soonest_first() {
+        let upstreams = set(
+            &["http://127.0.0.1:1", "http://127.0.0.1:2"],
+            Duration::from_secs(10),
+        );
+        upstreams.upstreams[0].record_failure("down");
+        upstreams.upstreams[0].record_failure("down");
+        upstreams.upstreams[1].record_failure("down");
+        assert_eq!(ports(&upstreams.candidates()), [2, 1]);
+    }
+
+    #[rstest]
+    fn stats_track_requests_failures_and_latency() {
+        let upstreams = set(&["http://127.0.0.1:1"], Duration::from_secs(1));
+        let upstream = &upstreams.upstreams[0];
+
+        let guard = upstream.begin();
+        assert_eq!(upstreams.stats()[0].in_flight, 1);
+        upstream.record_latency(Duration::from_millis(10));
+        upstream.record_latency(Duration::from_millis(20));
+        drop(guard);
+        let _failed = upstream.begin();
+        upstream.record_failure("HTTP 500");
+
+        let stats = &upstreams.stats()[0];
+        assert_eq!(stats.url, "http://127.0.0.1:1/");
+        assert_eq!(stats.requests, 2);
+        assert_eq!(stats.failures, 1);
+        assert_eq!(stats.in_flight, 1);
+        assert!(!stats.healthy);
+        assert!(!stats.in_rotation);
+        assert_eq!(stats.health_check, None);
+        assert_eq!(stats.consecutive_failures, 1);
+        let latency = stats.ewma_latency_ms.unwrap();
+        assert!((12.9..13.1).contains(&latency), "latency {latency}");
+    }
+}
@@ -0,0 +1,553 @@
+use crate::audit::{AuditCall, AuditTrail, Decision};
+use crate::auth::authenticate;
+use crate::errors::ProxyError;
+use crate::jsonrpc::{JsonRpcPayload, batch_body, error_payload, parse_json_rpc};
+use crate::policy::Rejection;
+use crate::rate_limit::ClientKey;
+use crate::server::{
+    AppState, BatchPlan, ClientAddr, blocked_method_payload, blocked_method_response,
+    build_target_uri, local_responses, plan_batch,
+};
+use axum::body::{Body, Bytes};
+use axum::extract::ws::{self, WebSocket, WebSocketUpgrade};
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::uri::Scheme;
+use axum::http::{HeaderMap, Request, StatusCode, Uri, header};
+use axum::response::{IntoResponse, Response};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::Value;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::{self, protocol::CloseFrame};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tracing::{debug, error, warn};
+
+type UpstreamSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Returns `true` when the request asks to upgrade the connection to a WebSocket.
+pub(crate) fn is_websocket_upgrade(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::UPGRADE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.eq_ignore_ascii_case("websocket"))
+}
+
+/// Accept a client WebSocket and relay it to the upstream node, filtering every client frame.
+///
+/// The upstream connection is established before the upgrade completes, failing over across the
+/// configured upstreams, so that unreachable nodes surface as a `502 Bad Gateway` instead of an
+/// immediately closed socket.
+pub(crate) async fn websocket_handler(state: AppState, req: Request<Body>) -> Response {
+    let (mut parts, _body) = req.into_parts();
+    let upgrade = match WebSocketUpgrade::from_request_parts(&mut parts, &state).await {
+        Ok(upgrade) => upgrade,
+        Err(rejection) => return rejection.into_response(),
+    };
+    let address = parts
+        .extensions
+        .get::<ConnectInfo<ClientAddr>>()
+        .map(|ConnectInfo(ClientAddr(address))| *address);
+    let Some(client) = authenticate(&state, &mut parts, address) else {
+        debug!("rejecting websocket upgrade without a valid api key");
+        return blocked_method_response(&Value::Null, "", &Rejection::Unauthorized);
+    };
+
+    let mut connected = None;
+    for upstream in state.upstreams.candidates() {
+        let Some(permit) = upstream.admit() else {
+            continue;
+        };
+        let target_uri = match build_websocket_uri(upstream.uri(), &parts.uri) {
+            Ok(uri) => uri,
+            Err(error) => {
+                error!(
+                    error = ?error,
+                    incoming = %parts.uri,
+                    upstream = %upstream.uri(),
+                    "failed to construct upstream websocket uri"
+                );
+                return bad_gateway();
+            }
+        };
+
+        match connect_upstream(&state, &target_uri).await {
+            Ok(socket) => {
+                upstream.record_success();
+                permit.succeed();
+                connected = Some((target_uri, socket));
+                break;
+            }
+            Err(error) => {
+                error!(error = ?error, upstream = %target_uri, "upstream websocket connection failed");
+                upstream.record_failure(&error);
+                permit.fail();
+            }
+        }
+    }
+
+    let Some((target_uri, upstream)) = connected else {
+        return bad_gateway();
+    };
+
+    debug!(upstream = %target_uri, "proxying websocket connection");
+    upgrade
+        .max_message_size(state.max_request_bytes)
+        .on_upgrade(move |socket| {
+            let peer = Peer {
+                key: client,
+                address,
+                upstream: target_uri.to_string(),
+            };
+            relay(state, peer, socket, upstream)
+        })
+}
+
+/// Client of a relayed WebSocket and the upstream it is connected to, as recorded in the audit
+/// log.
+#[derive(Debug)]
+struct Peer {
+    key: ClientKey,
+    address: Option<SocketAddr>,
+    upstream: String,
+}
+
+impl Peer {
+    /// Empty audit trail for one client frame.
+    fn trail(&self) -> AuditTrail {
+        AuditTrail {
+            upstream: Some(self.upstream.clone()),
+            api_key: match &self.key {
+                ClientKey::ApiKey(name) => Some(Arc::clone(name)),
+                ClientKey::Ip(_) => None,
+            },
+            ..AuditTrail::default()
+        }
+    }
+}
+
+/// Open the upstream WebSocket, using the upstream TLS settings for `wss://` when available.
+///
+/// The handshake must finish within the default upstream timeout.
+async fn connect_upstream(state: &AppState, target: &Uri) -> Result<UpstreamSocket, ProxyError> {
+    #[cfg(feature = "tls")]
+    let connect = tokio_tungstenite::connect_async_tls_with_config(
+        target.to_string(),
+        None,
+        false,
+        Some(tokio_tungstenite::Connector::Rustls(state.tls.clone())),
+    );
+    #[cfg(not(feature = "tls"))]
+    let connect = tokio_tungstenite::connect_async(target.to_string());
+
+    let timeout = state.upstream_timeouts.default_timeout();
+    match tokio::time::timeout(timeout, connect).await {
+        Ok(Ok((upstream, _))) => Ok(upstream),
+        Ok(Err(error)) => Err(ProxyError::UpstreamWebSocket(error)),
+        Err(_) => Err(ProxyError::UpstreamTimeout(timeout)),
+    }
+}
+
+/// Pump frames between the client and upstream until either side closes.
+///
+/// The calls of every filtered client frame are written to the audit log, with the `101` status
+/// of the upgraded connection and the time spent filtering the frame. Forwarded calls that
+/// rewind or rewrite the chain clear the response cache shared with HTTP clients.
+async fn relay(state: AppState, peer: Peer, client: WebSocket, upstream: UpstreamSocket) {
+    let (mut client_tx, mut client_rx) = client.split();
+    let (mut upstream_tx, mut upstream_rx) = upstream.split();
+
+    loop {
+        tokio::select! {
+            message = client_rx.next() => {
+                let Some(Ok(message)) = message else { break };
+                let started = Instant::now();
+                let mut trail = peer.trail();
+                let (forward, reply) = match message {
+                    ws::Message::Text(text) => {
+                        let (forward, reply) =
+                            filter_frame(&state, &peer.key, Bytes::from(text), &mut trail);
+                        let forward = forward.map(|bytes| {
+                            let text = tungstenite::Utf8Bytes::try_from(bytes)
+                                .expect("forwarded text frames are valid utf-8");
+                            tungstenite::Message::Text(text)
+                        });
+                        (forward, reply)
+                    }
+                    ws::Message::Binary(bytes) => {
+                        let (forward, reply) = filter_frame(&state, &peer.key, bytes, &mut trail);
+                        (forward.map(tungstenite::Message::Binary), reply)
+                    }
+                    ws::Message::Close(frame) => {
+                        let frame = frame.map(|frame| CloseFrame {
+                            code: frame.code.into(),
+                            reason: frame.reason.as_str().into(),
+                        });
+                        let _ = upstream_tx.send(tungstenite::Message::Close(frame)).await;
+                        break;
+                    }
+                    // Pings are answered by the socket implementations on each side.
+                    ws::Message::Ping(_) | ws::Message::Pong(_) => continue,
+                };
+                if let Some(audit) = &state.audit {
+                    audit.record(
+                        peer.address,
+                        &trail,
+                        StatusCode::SWITCHING_PROTOCOLS,
+                        started.elapsed(),
+                    );
+                }
+
+                if let Some(reply) = reply {
+                    let reply = ws::Message::Text(reply.to_string().into());
+                    if client_tx.send(reply).await.is_err() {
+                        break;
+                    }
+                }
+                if let Some(forward) = forward {
+                    if let Err(error) = upstream_tx.send(forward).await {
+                        warn!(error = ?error, "failed to forward websocket frame upstream");
+                        break;
+                    }
+                    if let Some(cache) = &state.cache {
+                        trail
+                            .forwarded_methods()
+                            .for_each(|method| cache.observe(method));
+                    }
+                }
+            }
+            message = upstream_rx.next() => {
+                let Some(Ok(message)) = message else { break };
+                let message = match message {
+                    tungstenite::Message::Text(text) => ws::Message::Text(text.as_str().into()),
+                    tungstenite::Message::Binary(bytes) => ws::Message::Binary(bytes),
+                    tungstenite::Message::Close(frame) => {
+                        let frame = frame.map(|frame| ws::CloseFrame {
+                            code: frame.code.into(),
+                            reason: frame.reason.as_str().into(),
+                        });
+                        let _ = client_tx.send(ws::Message::Close(frame)).await;
+                        break;
+                    }
+                    tungstenite::Message::Ping(_)
+                    | tungstenite::Message::Pong(_)
+                    | tungstenite::Message::Frame(_) => continue,
+                };
+                if client_tx.send(message).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    debug!("websocket connection closed");
+}
+
+/// Apply the method policy to a single client frame, recording the decisions in `trail`.
+///
+/// Returns the payload to forward upstream (if any) and the JSON-RPC response to send straight
+/// back to the client (if any). Batches with blocked entries forward the allowed calls and reply
+/// with the rejected entries as a separate batch, so clients match responses by `id`.
+fn filter_frame(
+    state: &AppState,
+    client: &ClientKey,
+    bytes: Bytes,
+    trail: &mut AuditTrail,
+) -> (Option<Bytes>, Option<Value>) {
+    let payload = match parse_json_rpc(&bytes, state.max_batch_size) {
+        Ok(payload) => payload,
+        Err(error) => {
+            debug!(error = ?error, "rejecting websocket json-rpc payload");
+            trail.invalid(Value::Null);
+            return (None, Some(error_payload(&error, Value::Null)));
+        }
+    };
+
+    match payload {
+        JsonRpcPayload::Single(request) => {
+            let rejection = state.check_request(client, &request);
+            trail.calls.push(AuditCall {
+                method: Some(request.method.clone()),
+                id: request.id.clone(),
+                decision: rejection.as_ref().map_or(Decision::Forwarded, |rejection| {
+                    Decision::Blocked(rejection.rule().to_owned())
+                }),
+            });
+            if let Some(rejection) = rejection {
+                warn!(method = %request.method, ?rejection, "blocked websocket json-rpc method");
+                let reply = blocked_method_payload(&request.id, &request.method, &rejection);
+                (None, Some(reply))
+            } else {
+                debug!(method = %request.method, "forwarding websocket json-rpc request");
+                (Some(bytes.clone()), None)
+            }
+        }
+        JsonRpcPayload::Batch(entries) => {
+            let BatchPlan {
+                slots,
+                forwarded,
+                filtered,
+                calls,
+                ..
+            } = plan_batch(state, client, entries, None);
+            trail.batch = true;
+            trail.calls = calls;
+
+            if !filtered {
+                return (Some(bytes.clone()), None);
+            }
+
+            let local = local_responses(slots);
+            let reply = (!local.is_empty()).then_some(Value::Array(local));
+            let forward = (!forwarded.is_empty()).then(|| batch_body(&forwarded));
+            (forward, reply)
+        }
+    }
+}
+
+/// Construct the upstream WebSocket [`Uri`], mapping `http`/`https` to `ws`/`wss`.
+fn build_websocket_uri(base: &Uri, incoming: &Uri) -> Result<Uri, ProxyError> {
+    let target = build_target_uri(base, incoming)?;
+    let mut parts = target.into_parts();
+    let scheme = match parts.scheme.as_ref().map(Scheme::as_str) {
+        Some("https" | "wss") => "wss",
+        _ => "ws",
+    };
+    parts.scheme = Some(scheme.parse().expect("valid websocket scheme"));
+    Uri::from_parts(parts).map_err(ProxyError::BadUpstreamUri)
+}
+
+fn bad_gateway() -> Response {
+    Response::builder()
+        .status(StatusCode::BAD_GATEWAY)
+        .body(Body::from("upstream websocket unavailable"))
+        .expect("valid response")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::router;
+    use axum::Router;
+    use axum::routing::any;
+    use rstest::rstest;
+    use serde_json::json;
+    use std::collections::HashSet;
+    use tokio_tungstenite::connect_async;
+    use veto_config::Config;
+
+    #[rstest]
+    #[case("http://127.0.0.1:8545", "ws://127.0.0.1:8545/")]
+    #[case("https://node.example", "wss://node.example/")]
+    fn websocket_uri_maps_scheme(#[case] base: &str, #[case] expected: &str) {
+        let base = base.parse::<Uri>().unwrap();
+        let incoming = "/".parse::<Uri>().unwrap();
+        assert_eq!(
+            build_websocket_uri(&base, &incoming).unwrap().to_string(),
+            expected
+        );
+    }
+
+    /// Upstream that answers `eth_subscribe` and then pushes a subscription notification.
+    async fn upstream_socket(mut socket: WebSocket) {
+        while let Some(Ok(ws::Message::Text(text))) = socket.next().await {
+            let request: Value = serde_json::from_str(text.as_str()).unwrap();
+            let response = json!({"jsonrpc": "2.0", "id": request["id"], "result": "0xabc"});
+            socket
+                .send(ws::Message::Text(response.to_string().into()))
+                .await
+                .unwrap();
+
+            if request["method"] == "eth_subscribe" {
+                let notification = json!({
+                    "jsonrpc": "2.0",
+                    "method": "eth_subscription",
+                    "params": {"subscription": "0xabc", "result": {"number": "0x1"}},
+                });
+                socket
+                    .send(ws::Message::Text(notification.to_string().into()))
+                    .await
+                    .unwrap();
+            }
+        }
+    }
+
+    async fn serve(router: Router) -> SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        address
+    }
+
+    async fn next_json(socket: &mut UpstreamSocket) -> Value {
+        match socket.next().await.unwrap().unwrap() {
+            tungstenite::Message::Text(text) => serde_json::from_str(text.as_str()).unwrap(),
+            other => panic!("unexpected frame: {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn websocket_calls_are_written_to_the_audit_log() {
+        let upstream = serve(
+            Router::new().fallback(any(|upgrade: WebSocketUpgrade| async {
+                upgrade.on_upgrade(upstream_socket)
+            })),
+        )
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+        let config = Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            format!("http://{upstream}").parse().unwrap(),
+            HashSet::from([String::from("anvil_setbalance")]),
+        )
+        .with_audit_log(Some(veto_config::AuditLog {
+            path: path.clone(),
+            max_bytes: 1 << 20,
+            max_files: 1,
+        }));
+        let proxy = serve(router(AppState::try_from_config(config).unwrap())).await;
+        let (mut client, _) = connect_async(format!("ws://{proxy}")).await.unwrap();
+
+        let batch = json!([
+            {"jsonrpc": "2.0", "id": 1, "method": "anvil_setBalance", "params": []},
+            {"jsonrpc": "2.0", "id": 2, "method": "eth_chainId"},
+        ]);
+        client
+            .send(tungstenite::Message::text(batch.to_string()))
+            .await
+            .unwrap();
+        next_json(&mut client).await;
+        next_json(&mut client).await;
+
+        let mut lines = Vec::new();
+        for _ in 0..100 {
+            let contents = std::fs::read_to_string(&path).unwrap();
+            lines = contents
+                .lines()
+                .map(|line| serde_json::from_str::<Value>(line).unwrap())
+                .collect();
+            if lines.len() == 2 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["method"], "anvil_setBalance");
+        assert_eq!(lines[0]["decision"], "blocked");
+        assert_eq!(lines[0]["upstream"], Value::Null);
+        assert_eq!(lines[1]["method"], "eth_chainId");
+        assert_eq!(lines[1]["decision"], "forwarded");
+        assert_eq!(lines[1]["upstream"], format!("ws://{upstream}/"));
+        assert_eq!(lines[1]["status"], 101);
+    }
+
+    #[tokio::test]
+    async fn websocket_chain_resets_clear_the_response_cache() {
+        let upstream = serve(
+            Router::new().fallback(any(|upgrade: WebSocketUpgrade| async {
+                upgrade.on_upgrade(upstream_socket)
+            })),
+        )
+        .await;
+        let settings = veto_config::ResponseCache::new(
+            10,
+            std::time::Duration::from_secs(60),
+            veto_config::default_cache_rules(),
+        )
+        .unwrap();
+        let config = Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            format!("http://{upstream}").parse().unwrap(),
+            HashSet::new(),
+        )
+        .with_response_cache(Some(settings));
+        let state = AppState::try_from_config(config).unwrap();
+        let cache = state.cache.clone().unwrap();
+        let key = cache.key("eth_chainId", &Value::Null).unwrap();
+        cache.insert(
+            key.clone(),
+            &json!({"jsonrpc": "2.0", "id": 1, "result": "0x1"}),
+        );
+
+        let proxy = serve(router(state)).await;
+        let (mut client, _) = connect_async(format!("ws://{proxy}")).await.unwrap();
+        let reorg = json!({"jsonrpc": "2.0", "id": 1, "method": "anvil_reorg", "params": [1, []]});
+        client
+            .send(tungstenite::Message::text(reorg.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(next_json(&mut client).await["id"], 1);
+        assert_eq!(cache.get(&key), None);
+    }
+
+    #[tokio::test]
+    async fn websocket_upgrades_to_a_silent_upstream_time_out() {
+        let upstream = serve(Router::new().fallback(any(std::future::pending::<Response>))).await;
+        let timeouts =
+            veto_config::UpstreamTimeouts::new(std::time::Duration::from_millis(50), Vec::new())
+                .unwrap();
+        let config = Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            format!("http://{upstream}").parse().unwrap(),
+            HashSet::new(),
+        )
+        .with_upstream_timeouts(timeouts);
+        let proxy = serve(router(AppState::try_from_config(config).unwrap())).await;
+
+        let connect = connect_async(format!("ws://{proxy}"));
+        let result = tokio::time::timeout(std::time::Duration::from_secs(5), connect)
+            .await
+            .expect("the upstream handshake is bounded by the timeout");
+        match result {
+            Err(tungstenite::Error::Http(response)) => {
+                assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+            }
+            other => panic!("unexpected upgrade result: {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn websocket_frames_are_filtered_and_subscriptions_stream_back() {
+        let upstream = serve(
+            Router::new().fallback(any(|upgrade: WebSocketUpgrade| async {
+                upgrade.on_upgrade(upstream_socket)
+            })),
+        )
+        .await;
+
+        let config = Config::new(
+            "127.0.0.1:0".parse().unwrap(),
+            format!("http://{upstream}").parse().unwrap(),
+            HashSet::from([String::from("anvil_setbalance")]),
+        );
+        let proxy = serve(router(AppState::try_from_config(config).unwrap())).await;
+
+        let (mut client, _) = connect_async(format!("ws://{proxy}")).await.unwrap();
+
+        let blocked =
+            json!({"jsonrpc": "2.0", "id": 1, "method": "anvil_setBalance", "params": []});
+        client
+            .send(tungstenite::Message::text(blocked.to_string()))
+            .await
+            .unwrap();
+        let response = next_json(&mut client).await;
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["error"]["code"], -32601);
+
+        let subscribe =
+            json!({"jsonrpc": "2.0", "id": 2, "method": "eth_subscribe", "params": ["newHeads"]});
+        client
+            .send(tungstenite::Message::text(subscribe.to_string()))
+            .await
+            .unwrap();
+        let response = next_json(&mut client).await;
+        assert_eq!(response["id"], 2);
+        assert_eq!(response["result"], "0xabc");
+
+        let notification = next_json(&mut client).await;
+        assert_eq!(notification["method"], "eth_subscription");
+        assert_eq!(notification["params"]["subscription"], "0xabc");
+    }
+}